@@ -72,11 +72,11 @@ impl eframe::App for EframeApp {
 
         self.editors.sync_editors(
             &self.options,
-            &self.canvas.get_lines_as_idx_tuples(),
+            &self.canvas.get_weighted_arcs(),
             self.canvas.nodes.len(),
         );
         self.update_canvas_from_editors();
-        self.update_editors_from_canvas(&self.canvas.get_lines_as_idx_tuples());
+        self.update_editors_from_canvas(&self.canvas.get_weighted_arcs());
 
         self.show_top_panel(ctx);
         self.show_left_panel(ctx);
@@ -116,13 +116,15 @@ impl EframeApp {
         if self.layout.tools {
             egui::SidePanel::new(Side::Left, "left_panel").show(ctx, |ui| {
                 ui.heading("Tools");
-                let mut tool_buttons: [Tool; 4] = [Tool::Move, Tool::Node, Tool::Line, Tool::Label];
+                let mut tool_buttons: [Tool; 5] = [
+                    Tool::Move,
+                    Tool::Node,
+                    Tool::Line,
+                    Tool::Label,
+                    Tool::Weight,
+                ];
                 for tool in tool_buttons.iter_mut() {
-                    tool.show(
-                        ui,
-                        &mut self.selected_tool,
-                        &mut self.canvas_actions.add_label_text,
-                    );
+                    tool.show(ui, &mut self.selected_tool, &mut self.canvas_actions);
                 }
                 // BUG: this line is needed, allows left-panel resizing
                 // is likely fixed if egui is updated
@@ -147,24 +149,60 @@ impl EframeApp {
 
                     match self.options.mode {
                         Mode::Classical => {
+                            ui.separator();
+                            self.editors.show_classical_editors(
+                                ui,
+                                &self.options,
+                                self.canvas.nodes.len(),
+                            );
+                        }
+                        Mode::ContinuousClassical => {
                             ui.separator();
                             self.editors
-                                .show_classical_editors(ui, self.canvas.nodes.len());
+                                .show_continuous_classical_editors(ui, self.canvas.nodes.len());
                         }
                         Mode::Quantum => {
                             ui.separator();
                             self.editors.show_quantum_editors(
                                 ui,
                                 &self.options,
-                                &self.canvas.get_lines_as_idx_tuples(),
+                                self.canvas.nodes.len(),
+                                &self.canvas.get_arcs_as_idx_tuples(),
+                            );
+                        }
+                        Mode::ContinuousQuantum => {
+                            ui.separator();
+                            self.editors.show_continuous_quantum_editors(
+                                ui,
+                                &self.options,
+                                self.canvas.nodes.len(),
+                                &self.canvas.get_weighted_arcs(),
+                            );
+                        }
+                        Mode::Szegedy => {
+                            ui.separator();
+                            self.editors.show_szegedy_editors(
+                                ui,
+                                &self.options,
+                                self.canvas.nodes.len(),
+                            );
+                        }
+                        Mode::Comparison => {
+                            ui.separator();
+                            self.editors.show_comparison_editors(
+                                ui,
+                                &self.options,
+                                self.canvas.nodes.len(),
+                                &self.canvas.get_weighted_arcs(),
                             );
                         }
                         _ => {}
                     }
 
-                    let state_data = self
-                        .editors
-                        .get_state_data(&self.canvas.get_lines_as_idx_tuples());
+                    let state_data = self.editors.get_state_data(
+                        &self.canvas.get_arcs_as_idx_tuples(),
+                        self.options.generic.distribution_view,
+                    );
                     self.canvas.set_state_data(state_data);
                 });
             });
@@ -173,10 +211,15 @@ impl EframeApp {
 
     fn show_center_panel(&mut self, ctx: &egui::Context) {
         egui::CentralPanel::default().show(ctx, |ui| {
-            let state_data = self
-                .editors
-                .get_state_data(&self.canvas.get_lines_as_idx_tuples());
+            let state_data = self.editors.get_state_data(
+                &self.canvas.get_arcs_as_idx_tuples(),
+                self.options.generic.distribution_view,
+            );
             self.canvas.set_state_data(state_data);
+            let overlay_data = self.editors.get_overlay_data(&self.options);
+            self.canvas.set_overlay_data(overlay_data);
+            let comparison_data = self.editors.get_comparison_data(&self.options);
+            self.canvas.set_comparison_data(comparison_data);
             self.canvas
                 .show(ui, self.selected_tool, &self.options, &self.canvas_actions);
         });
@@ -268,6 +311,22 @@ impl EframeApp {
                     matrix_editor.on_canvas_updated();
                 }
             }
+            (Mode::ContinuousClassical, None, MatrixEditor::RateMatrix(rme)) => {
+                if rme.is_canvas_update_ready() {
+                    let matrix = &rme.rates.matrix;
+                    let canvas = &mut self.canvas;
+                    Self::update_edges_from_classical_matrix(matrix, canvas);
+                    rme.on_canvas_updated();
+                }
+            }
+            (Mode::Szegedy, None, MatrixEditor::Szegedy(se)) => {
+                if se.is_canvas_update_ready() {
+                    let matrix = &se.classical.matrix;
+                    let canvas = &mut self.canvas;
+                    Self::update_edges_from_classical_matrix(matrix, canvas);
+                    se.on_canvas_updated();
+                }
+            }
             (Mode::Quantum, None, MatrixEditor::Complex(_)) => {}
             _ => {}
         }
@@ -276,23 +335,34 @@ impl EframeApp {
     fn update_edges_from_classical_matrix(matrix: &DMatrix<f64>, canvas: &mut Canvas) {
         for (i, j) in (0..matrix.nrows()).flat_map(|i| (i + 1..matrix.ncols()).map(move |j| (i, j)))
         {
-            if matrix[(i, j)] == 0.0 && matrix[(j, i)] == 0.0 {
-                if canvas.is_line_between_nodes(i, j) {
-                    canvas.remove_line_between_nodes(i, j);
-                }
-            } else if !canvas.is_line_between_nodes(i, j) {
-                canvas.add_line_between_nodes(i, j);
+            // entry (j, i) is the weight of moving from node i to node j
+            let (forward, backward) = (matrix[(j, i)] != 0.0, matrix[(i, j)] != 0.0);
+            if (
+                canvas.is_arc_between_nodes(i, j),
+                canvas.is_arc_between_nodes(j, i),
+            ) == (forward, backward)
+            {
+                continue;
+            }
+
+            canvas.remove_line_between_nodes(i, j);
+            match (forward, backward) {
+                (true, true) => canvas.add_line_between_nodes(i, j),
+                (true, false) => canvas.add_directed_line_between_nodes(i, j),
+                (false, true) => canvas.add_directed_line_between_nodes(j, i),
+                (false, false) => (),
             }
         }
     }
 
-    fn update_editors_from_canvas(&mut self, edges: &[(usize, usize)]) {
+    fn update_editors_from_canvas(&mut self, arcs: &[(usize, usize, f64)]) {
         if !self.canvas.node_deletion_history.is_empty() {
             self.editors
                 .remove_nodes(mem::take(&mut self.canvas.node_deletion_history));
         }
 
-        self.editors.update_editor_from_edges(edges);
+        self.editors
+            .update_editor_from_edges(arcs, self.canvas.nodes.len());
 
         self.options.clear_mode_change_data();
     }
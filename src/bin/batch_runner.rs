@@ -0,0 +1,573 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+//! Runs a walk on a saved graph without opening a window, printing the node
+//! probabilities and target accumulation at every step.
+//!
+//! ```text
+//! batch_runner <graph.json>
+//!     [--mode classical|quantum|continuous-classical|continuous-quantum|szegedy]
+//!     [--coin saved|grover|fourier] [--hamiltonian saved|adjacency|laplacian]
+//!     [--time-step T] [--steps N] [--start NODE] [--targets "NODE NODE ..."]
+//!     [--format csv|json] [--output PATH]
+//! ```
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use anyhow::{anyhow, Context, Result};
+use eframe_gui_test::editors::{ComplexMatrixEditor, MatrixEditor, PropagationMethod};
+use eframe_gui_test::engine::{Coin, Graph, Simulation, SimulationMode};
+use eframe_gui_test::options::Mode;
+use eframe_gui_test::EframeApp;
+use nalgebra::Complex;
+
+const USAGE: &str = "Usage: batch_runner <graph.json> \
+[--mode classical|quantum|continuous-classical|continuous-quantum|szegedy] \
+[--coin saved|grover|fourier] \
+[--hamiltonian saved|adjacency|laplacian] [--time-step T] [--steps N] [--start NODE] \
+[--targets \"NODE NODE ...\"] [--format csv|json] [--output PATH]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoinChoice {
+    Saved,
+    Grover,
+    Fourier,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HamiltonianChoice {
+    Saved,
+    Adjacency,
+    Laplacian,
+}
+
+#[derive(Debug)]
+struct Args {
+    graph_path: String,
+    mode: Option<Mode>,
+    coin: CoinChoice,
+    hamiltonian: HamiltonianChoice,
+    time_step: Option<f64>,
+    steps: usize,
+    start_node_idx: Option<usize>,
+    target_node_indexes: Option<HashSet<usize>>,
+    format: OutputFormat,
+    output_path: Option<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut res = Self {
+            graph_path: String::new(),
+            mode: None,
+            coin: CoinChoice::Saved,
+            hamiltonian: HamiltonianChoice::Saved,
+            time_step: None,
+            steps: 10,
+            start_node_idx: None,
+            target_node_indexes: None,
+            format: OutputFormat::Csv,
+            output_path: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--mode" => {
+                    res.mode = Some(match value()?.as_str() {
+                        "classical" => Mode::Classical,
+                        "quantum" => Mode::Quantum,
+                        "continuous-classical" => Mode::ContinuousClassical,
+                        "continuous-quantum" => Mode::ContinuousQuantum,
+                        "szegedy" => Mode::Szegedy,
+                        other => return Err(anyhow!("Unknown mode: {}", other)),
+                    })
+                }
+                "--coin" => {
+                    res.coin = match value()?.as_str() {
+                        "saved" => CoinChoice::Saved,
+                        "grover" => CoinChoice::Grover,
+                        "fourier" => CoinChoice::Fourier,
+                        other => return Err(anyhow!("Unknown coin: {}", other)),
+                    }
+                }
+                "--hamiltonian" => {
+                    res.hamiltonian = match value()?.as_str() {
+                        "saved" => HamiltonianChoice::Saved,
+                        "adjacency" => HamiltonianChoice::Adjacency,
+                        "laplacian" => HamiltonianChoice::Laplacian,
+                        other => return Err(anyhow!("Unknown Hamiltonian: {}", other)),
+                    }
+                }
+                "--time-step" => {
+                    res.time_step = Some(value()?.parse().context("Invalid time step")?)
+                }
+                "--steps" => res.steps = value()?.parse().context("Invalid step count")?,
+                "--start" => {
+                    res.start_node_idx = Some(value()?.parse().context("Invalid start node")?)
+                }
+                "--targets" => {
+                    res.target_node_indexes = Some(
+                        value()?
+                            .split_whitespace()
+                            .map(|x| x.parse::<usize>())
+                            .collect::<Result<_, _>>()
+                            .context("Invalid target node list")?,
+                    )
+                }
+                "--format" => {
+                    res.format = match value()?.as_str() {
+                        "csv" => OutputFormat::Csv,
+                        "json" => OutputFormat::Json,
+                        other => return Err(anyhow!("Unknown format: {}", other)),
+                    }
+                }
+                "--output" => res.output_path = Some(value()?),
+                "--help" | "-h" => return Err(anyhow!(USAGE)),
+                _ if res.graph_path.is_empty() && !arg.starts_with("--") => res.graph_path = arg,
+                _ => return Err(anyhow!("Unexpected argument: {}\n{}", arg, USAGE)),
+            }
+        }
+
+        if res.graph_path.is_empty() {
+            return Err(anyhow!(USAGE));
+        }
+        Ok(res)
+    }
+}
+
+#[derive(serde::Serialize)]
+struct StepRecord {
+    step: usize,
+    probabilities: Vec<f64>,
+    target_accumulation: Vec<(usize, f64)>,
+    total_accumulated: f64,
+}
+
+impl StepRecord {
+    fn new(simulation: &Simulation, targets: &[usize]) -> Self {
+        let accumulation = simulation.get_target_accumulation();
+        Self {
+            step: simulation.get_step(),
+            probabilities: simulation.get_node_probabilities().as_slice().to_vec(),
+            target_accumulation: targets.iter().map(|i| (*i, accumulation[*i])).collect(),
+            total_accumulated: accumulation.iter().sum(),
+        }
+    }
+}
+
+fn build_simulation(app: &EframeApp, args: &Args) -> Result<Simulation> {
+    let graph = Graph::from_weighted_arcs(app.canvas.nodes.len(), &app.canvas.get_weighted_arcs())?;
+
+    let mode_choice = args.mode.unwrap_or(app.options.mode);
+    let mode = match mode_choice {
+        Mode::Edit => {
+            return Err(anyhow!(
+                "The graph was saved in Edit mode, choose a walk with --mode"
+            ))
+        }
+        Mode::Comparison => {
+            return Err(anyhow!(
+                "The graph was saved comparing two walks, choose one with --mode"
+            ))
+        }
+        Mode::Classical => match app.editors.get_matrix_editor() {
+            MatrixEditor::Classical(cme) if cme.matrix.nrows() == graph.node_count => {
+                SimulationMode::Classical(cme.matrix.clone())
+            }
+            _ => SimulationMode::Classical(graph.uniform_transition_matrix()),
+        },
+        Mode::Szegedy => match app.editors.get_matrix_editor() {
+            MatrixEditor::Classical(cme) if cme.matrix.nrows() == graph.node_count => {
+                SimulationMode::Szegedy(cme.matrix.clone())
+            }
+            MatrixEditor::Szegedy(se) if se.classical.matrix.nrows() == graph.node_count => {
+                SimulationMode::Szegedy(se.classical.matrix.clone())
+            }
+            _ => SimulationMode::Szegedy(graph.uniform_transition_matrix()),
+        },
+        Mode::ContinuousClassical => {
+            let saved = match app.editors.get_matrix_editor() {
+                MatrixEditor::RateMatrix(rme) if rme.rates.matrix.nrows() == graph.node_count => {
+                    Some(rme)
+                }
+                _ => None,
+            };
+            let time_step = args
+                .time_step
+                .or(saved.map(|rme| rme.get_time_step()))
+                .unwrap_or(0.1);
+            SimulationMode::ContinuousClassical {
+                rates: saved
+                    .map(|rme| rme.rates.matrix.clone())
+                    .unwrap_or_else(|| graph.adjacency_matrix()),
+                time_step,
+            }
+        }
+        Mode::Quantum => {
+            SimulationMode::Quantum(match (args.coin, app.editors.get_matrix_editor()) {
+                (CoinChoice::Saved, MatrixEditor::Complex(cme)) => {
+                    if cme.get_propagation_method() != PropagationMethod::ExampleMatrix {
+                        return Err(anyhow!(
+                            "Saved coins need the {} propagation method, choose a coin with --coin",
+                            PropagationMethod::ExampleMatrix
+                        ));
+                    }
+                    if cme.is_time_dependent() {
+                        Coin::Custom(cme.evaluate_coins(&cme.get_context(), 0)?)
+                    } else {
+                        Coin::Custom(cme.get_coins())
+                    }
+                }
+                (CoinChoice::Saved, _) => {
+                    return Err(anyhow!(
+                        "The graph has no saved quantum coins, choose one with --coin"
+                    ))
+                }
+                (CoinChoice::Grover, _) => Coin::Grover,
+                (CoinChoice::Fourier, _) => Coin::Fourier,
+            })
+        }
+        Mode::ContinuousQuantum => {
+            let saved = match app.editors.get_matrix_editor() {
+                MatrixEditor::Hamiltonian(he) => Some(he),
+                _ => None,
+            };
+            let hamiltonian = match (args.hamiltonian, saved) {
+                (HamiltonianChoice::Saved, Some(he)) => he.get_hamiltonian().clone(),
+                (HamiltonianChoice::Saved | HamiltonianChoice::Adjacency, _) => {
+                    graph.adjacency_matrix().map(Complex::from)
+                }
+                (HamiltonianChoice::Laplacian, _) => graph.laplacian().map(Complex::from),
+            };
+            let time_step = args
+                .time_step
+                .or(saved.map(|he| he.get_time_step()))
+                .unwrap_or(0.1);
+            SimulationMode::ContinuousQuantum {
+                hamiltonian,
+                time_step,
+            }
+        }
+    };
+
+    let start_node_idx = args
+        .start_node_idx
+        .unwrap_or(app.options.generic.start_node_idx);
+    let specific = &app.options.specific;
+    let target_node_indexes = match (&args.target_node_indexes, mode_choice) {
+        (Some(target_node_indexes), _) => target_node_indexes.clone(),
+        (None, Mode::Classical) => specific.classical.target_node_indexes.clone(),
+        (None, Mode::ContinuousClassical) => {
+            specific.continuous_classical.target_node_indexes.clone()
+        }
+        (None, Mode::Quantum) => specific.quantum.target_node_indexes.clone(),
+        (None, Mode::ContinuousQuantum) => specific.continuous_quantum.target_node_indexes.clone(),
+        (None, Mode::Szegedy) => specific.szegedy.target_node_indexes.clone(),
+        (None, Mode::Comparison) => specific.comparison.target_node_indexes.clone(),
+        (None, Mode::Edit) => {
+            return Err(anyhow!(
+                "{} mode has no saved target nodes, choose them with --targets",
+                mode_choice.name()
+            ))
+        }
+    };
+
+    let simulation = Simulation::new(graph, mode, start_node_idx, target_node_indexes)?;
+
+    // a saved initial state replaces the start node unless one is given
+    let initial_state_editor = app.editors.get_initial_state_editor();
+    if !initial_state_editor.enabled || args.start_node_idx.is_some() {
+        return Ok(simulation);
+    }
+    match simulation.get_mode() {
+        SimulationMode::Classical(_) | SimulationMode::ContinuousClassical { .. } => {
+            let distribution =
+                initial_state_editor.get_distribution(simulation.get_graph().node_count)?;
+            simulation.with_initial_distribution(distribution)
+        }
+        _ => {
+            let amplitudes = initial_state_editor.get_amplitudes(simulation.get_labels())?;
+            simulation.with_initial_state(amplitudes)
+        }
+    }
+}
+
+/// The editor to evaluate the coins from before every step, when the walk uses
+/// saved coins that depend on the step.
+fn time_dependent_coins<'a>(app: &'a EframeApp, args: &Args) -> Option<&'a ComplexMatrixEditor> {
+    match app.editors.get_matrix_editor() {
+        MatrixEditor::Complex(cme)
+            if args.mode.unwrap_or(app.options.mode) == Mode::Quantum
+                && args.coin == CoinChoice::Saved
+                && cme.is_time_dependent() =>
+        {
+            Some(cme)
+        }
+        _ => None,
+    }
+}
+
+/// Steps the walk `steps` times, recording it before the first step and after
+/// every one.
+fn run_steps(
+    simulation: &mut Simulation,
+    time_dependent_coins: Option<&ComplexMatrixEditor>,
+    steps: usize,
+) -> Result<Vec<StepRecord>> {
+    let mut targets = simulation
+        .get_target_node_indexes()
+        .iter()
+        .copied()
+        .filter(|i| *i < simulation.get_graph().node_count)
+        .collect::<Vec<_>>();
+    targets.sort_unstable();
+    let context = time_dependent_coins.map(|cme| cme.get_context());
+
+    let mut records = vec![StepRecord::new(simulation, &targets)];
+    for _ in 0..steps {
+        let t = simulation.get_step();
+        if let (Some(cme), Some(context)) = (time_dependent_coins, &context) {
+            if t > 0 {
+                simulation.set_coin(Coin::Custom(cme.evaluate_coins(context, t)?))?;
+            }
+        }
+        simulation.step()?;
+        records.push(StepRecord::new(simulation, &targets));
+    }
+    Ok(records)
+}
+
+fn write_csv(out: &mut dyn Write, records: &[StepRecord], node_count: usize) -> Result<()> {
+    let mut header = vec![String::from("step")];
+    header.extend((0..node_count).map(|i| format!("node_{}", i)));
+    if let Some(record) = records.first() {
+        header.extend(
+            record
+                .target_accumulation
+                .iter()
+                .map(|(i, _)| format!("target_{}", i)),
+        );
+    }
+    header.push(String::from("total_accumulated"));
+    writeln!(out, "{}", header.join(","))?;
+
+    for record in records {
+        let mut row = vec![record.step.to_string()];
+        row.extend(record.probabilities.iter().map(|p| p.to_string()));
+        row.extend(
+            record
+                .target_accumulation
+                .iter()
+                .map(|(_, v)| v.to_string()),
+        );
+        row.push(record.total_accumulated.to_string());
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<()> {
+    let file = std::fs::read(&args.graph_path)
+        .with_context(|| format!("Could not read {}", args.graph_path))?;
+    let app: EframeApp = serde_json::from_slice(&file)
+        .with_context(|| format!("Could not parse {}", args.graph_path))?;
+
+    let mut simulation = build_simulation(&app, &args)?;
+    let records = run_steps(
+        &mut simulation,
+        time_dependent_coins(&app, &args),
+        args.steps,
+    )?;
+
+    let mut out: Box<dyn Write> = match &args.output_path {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).with_context(|| format!("Could not create {}", path))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    match args.format {
+        OutputFormat::Csv => write_csv(&mut out, &records, simulation.get_graph().node_count)?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &records)?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn main() {
+    let result = Args::parse(std::env::args().skip(1)).and_then(run);
+    if let Err(e) = result {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse(args.iter().map(|x| x.to_string()))
+    }
+
+    fn cycle_graph(n: usize) -> Graph {
+        Graph::new(n, (0..n).map(|i| (i, (i + 1) % n)).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&["graph.json"]).unwrap();
+        assert_eq!(args.graph_path, "graph.json");
+        assert_eq!(args.mode, None);
+        assert_eq!(args.coin, CoinChoice::Saved);
+        assert_eq!(args.hamiltonian, HamiltonianChoice::Saved);
+        assert_eq!(args.steps, 10);
+        assert_eq!(args.format, OutputFormat::Csv);
+        assert!(args.target_node_indexes.is_none());
+
+        let args = parse(&[
+            "--mode",
+            "continuous-quantum",
+            "--hamiltonian",
+            "laplacian",
+            "--time-step",
+            "0.5",
+            "graph.json",
+            "--steps",
+            "3",
+            "--start",
+            "2",
+            "--targets",
+            "1 4",
+            "--coin",
+            "fourier",
+            "--format",
+            "json",
+            "--output",
+            "out.json",
+        ])
+        .unwrap();
+        assert_eq!(args.graph_path, "graph.json");
+        assert_eq!(args.mode, Some(Mode::ContinuousQuantum));
+        assert_eq!(args.hamiltonian, HamiltonianChoice::Laplacian);
+        assert_eq!(args.time_step, Some(0.5));
+        assert_eq!(args.steps, 3);
+        assert_eq!(args.start_node_idx, Some(2));
+        assert_eq!(args.target_node_indexes, Some(HashSet::from([1, 4])));
+        assert_eq!(args.coin, CoinChoice::Fourier);
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(args.output_path.as_deref(), Some("out.json"));
+
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--steps", "3"]).is_err());
+        assert!(parse(&["graph.json", "--steps"]).is_err());
+        assert!(parse(&["graph.json", "--steps", "many"]).is_err());
+        assert!(parse(&["graph.json", "--mode", "edit"]).is_err());
+        assert!(parse(&["graph.json", "--targets", "1 x"]).is_err());
+        assert!(parse(&["graph.json", "other.json"]).is_err());
+        assert!(parse(&["graph.json", "--unknown"]).is_err());
+    }
+
+    #[test]
+    fn test_records() {
+        let graph = Graph::new(3, vec![(0, 1), (1, 2)]).unwrap();
+        let matrix = graph.uniform_transition_matrix();
+        let mut simulation = Simulation::new(
+            graph,
+            SimulationMode::Classical(matrix),
+            0,
+            HashSet::from([2]),
+        )
+        .unwrap();
+        let records = run_steps(&mut simulation, None, 2).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].step, 2);
+        assert_eq!(records[2].target_accumulation, vec![(2, 0.5)]);
+        assert_eq!(records[2].total_accumulated, 0.5);
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &records, 3).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "step,node_0,node_1,node_2,target_2,total_accumulated"
+        );
+        assert_eq!(lines[1], "0,1,0,0,0,0");
+        assert_eq!(lines[3].split(',').count(), 6);
+        assert!(lines[3].starts_with("2,"));
+        assert!(lines[3].ends_with(",0.5,0.5"));
+
+        let json = serde_json::to_value(&records).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 3);
+        assert_eq!(json[0]["step"], 0);
+        assert_eq!(json[0]["probabilities"], serde_json::json!([1.0, 0.0, 0.0]));
+        assert_eq!(
+            json[2]["target_accumulation"],
+            serde_json::json!([[2, 0.5]])
+        );
+        assert_eq!(json[2]["total_accumulated"], 0.5);
+    }
+
+    #[test]
+    fn test_time_dependent_coins() {
+        // the Grover coin for the first two steps, then one sending the walker
+        // back the way it came
+        let graph = cycle_graph(8);
+        let mut cme = ComplexMatrixEditor::new(&graph.arcs());
+        for field in cme.text_fields.iter_mut().flatten().flatten() {
+            *field = (
+                String::from(
+                    "if(t < 2, 2 / deg - if(row == col, 1, 0), if(row == col, if(row == 0, 1, -1), 0))",
+                ),
+                String::from("0"),
+            );
+        }
+        let coins = cme.evaluate_coins(&cme.get_context(), 0).unwrap();
+        let mut simulation = Simulation::new(
+            graph.clone(),
+            SimulationMode::Quantum(Coin::Custom(coins)),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        let records = run_steps(&mut simulation, Some(&cme), 4).unwrap();
+
+        let mut expected = Simulation::new(
+            graph,
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        expected.run(2).unwrap();
+        expected
+            .set_coin(Coin::Custom(
+                cme.evaluate_coins(&cme.get_context(), 2).unwrap(),
+            ))
+            .unwrap();
+        expected.run(2).unwrap();
+        for (a, b) in records[4]
+            .probabilities
+            .iter()
+            .zip(expected.get_node_probabilities().iter())
+        {
+            assert!((a - b).abs() < 1e-9);
+        }
+        // the walker is bounced back from where the Grover coin had taken it
+        assert!(records[4].probabilities[2] > 0.49);
+    }
+}
@@ -41,15 +41,31 @@ pub struct Canvas {
     pub action_data: CanvasActions,
 
     state_data: Option<DVector<f64>>,
+
+    overlay_data: Option<DVector<f64>>,
+
+    /// Classical and quantum node probabilities, each drawn on one half of
+    /// every node when comparing the two walks.
+    comparison_data: Option<(DVector<f64>, DVector<f64>)>,
 }
 
 impl Canvas {
-    fn new(nodes: Vec<Rc<RefCell<GraphNode>>>, lines: Option<Vec<(usize, usize)>>) -> Self {
+    fn new(
+        nodes: Vec<Rc<RefCell<GraphNode>>>,
+        lines: Option<Vec<(usize, usize)>>,
+        directed: Vec<bool>,
+        weights: Vec<f64>,
+    ) -> Self {
         let lines = lines
             .map(|lines| {
                 lines
                     .into_iter()
-                    .map(|(a, b)| GraphLine::new(nodes[a].clone(), nodes[b].clone()))
+                    .enumerate()
+                    .map(|(i, (a, b))| GraphLine {
+                        directed: directed.get(i).copied().unwrap_or(false),
+                        weight: weights.get(i).copied().unwrap_or(1.0),
+                        ..GraphLine::new(nodes[a].clone(), nodes[b].clone())
+                    })
                     .collect()
             })
             .unwrap_or_default();
@@ -182,9 +198,16 @@ impl Canvas {
 
     pub fn draw_nodes(&self, plot_ui: &mut PlotUi, options: &Options) {
         // plot nodes
-        match (options.mode, self.state_data.as_ref()) {
-            (Mode::Edit, _) | (_, None) => plot_ui.points(self.nodes(options)),
-            (_, Some(state)) => self.plot_nodes_with_color_by_state(plot_ui, state),
+        match (
+            options.mode,
+            self.state_data.as_ref(),
+            &self.comparison_data,
+        ) {
+            (Mode::Comparison, _, Some((classical, quantum))) => {
+                self.plot_nodes_split_by_state(plot_ui, classical, quantum)
+            }
+            (Mode::Edit, _, _) | (_, None, _) => plot_ui.points(self.nodes(options)),
+            (_, Some(state), _) => self.plot_nodes_with_color_by_state(plot_ui, state),
         }
 
         // TODO consider performance
@@ -222,6 +245,21 @@ impl Canvas {
         }
     }
 
+    /// Whether the walker can move from the start node to the end node, either
+    /// along an undirected line or a line directed that way.
+    pub fn is_arc_between_nodes(&self, start_node_index: usize, end_node_index: usize) -> bool {
+        let start_node = self.nodes.get(start_node_index);
+        let end_node = self.nodes.get(end_node_index);
+        if let (Some(start_node), Some(end_node)) = (start_node, end_node) {
+            self.lines.iter().any(|l| {
+                (l.start == *start_node && l.end == *end_node)
+                    || (!l.directed && l.start == *end_node && l.end == *start_node)
+            })
+        } else {
+            false
+        }
+    }
+
     pub fn add_line(
         &mut self,
         plot_ui: &PlotUi,
@@ -235,8 +273,15 @@ impl Canvas {
                 <= POINTER_INTERACTION_RADIUS
             {
                 if let Some(start_node) = &self.line_start {
-                    let line = GraphLine::new(start_node.clone(), clicked_node);
-                    if line.start != line.end && !self.lines.iter().any(|l| *l == line) {
+                    let line = if self.action_data.add_directed_lines {
+                        GraphLine::new_directed(start_node.clone(), clicked_node)
+                    } else {
+                        GraphLine::new(start_node.clone(), clicked_node)
+                    };
+                    let reverse_line = GraphLine::new(line.end.clone(), line.start.clone());
+                    if line.start != line.end
+                        && !self.lines.iter().any(|l| *l == line || *l == reverse_line)
+                    {
                         self.line_start = None;
                         self.lines.push(line);
                     }
@@ -254,6 +299,17 @@ impl Canvas {
         self.lines.push(line);
     }
 
+    pub fn add_directed_line_between_nodes(
+        &mut self,
+        start_node_index: usize,
+        end_node_index: usize,
+    ) {
+        let start_node = &self.nodes[start_node_index];
+        let end_node = &self.nodes[end_node_index];
+        let line = GraphLine::new_directed(start_node.clone(), end_node.clone());
+        self.lines.push(line);
+    }
+
     pub fn remove_line_between_nodes(&mut self, start_node_index: usize, end_node_index: usize) {
         let start_node = &self.nodes[start_node_index];
         let end_node = &self.nodes[end_node_index];
@@ -285,6 +341,39 @@ impl Canvas {
         }
     }
 
+    /// Sets the weight of the line closest to the pointer to the value in the
+    /// Weight tool's text field.
+    pub fn set_line_weight(
+        &mut self,
+        plot_ui: &PlotUi,
+        pointer_coords: PlotPoint,
+        global_pointer_coords: Pos2,
+    ) {
+        let Some((_, point_on_line, line)) =
+            self.find_closest_line_and_point_on_line(pointer_coords)
+        else {
+            return;
+        };
+        let line_pos = plot_ui.screen_from_plot(point_on_line.into());
+        if euclidean_dist(&line_pos, &global_pointer_coords) > POINTER_INTERACTION_RADIUS {
+            return;
+        }
+
+        match self.action_data.set_weight_text.trim().parse::<f64>() {
+            Ok(weight) if weight.is_finite() && weight > 0.0 => {
+                if let Some(l) = self.lines.iter_mut().find(|l| **l == line) {
+                    l.weight = weight;
+                }
+            }
+            // TODO normalize errors
+            _ => eprintln!(
+                "[{}:{}] Error: Weight must be a positive number",
+                file!(),
+                line!()
+            ),
+        }
+    }
+
     pub fn add_label_to_node(&mut self, node_index: usize) {
         let node = &self.nodes[node_index];
         node.borrow_mut().label = Some(self.action_data.add_label_text.clone());
@@ -460,6 +549,9 @@ impl Canvas {
             (Tool::Label, Some(global_pointer_coords)) => {
                 self.add_label(plot_ui, pointer_coords, global_pointer_coords)
             }
+            (Tool::Weight, Some(global_pointer_coords)) => {
+                self.set_line_weight(plot_ui, pointer_coords, global_pointer_coords)
+            }
             _ => unreachable!(), // TODO add appropriate error message
         }
     }
@@ -467,9 +559,44 @@ impl Canvas {
     fn draw_lines(&self, plot_ui: &mut PlotUi, options: &Options) {
         for line in &self.lines {
             plot_ui.line(Line::new(line.clone()).color(options.get_line_color()));
+            if line.directed {
+                Self::draw_arrowhead(plot_ui, line, options.get_line_color());
+            }
+            // unweighted lines are left unlabelled to keep the canvas readable
+            if line.weight != 1.0 {
+                plot_ui.text(
+                    Text::new(line.midpoint().into(), format!("{}", line.weight))
+                        .color(Color32::WHITE)
+                        .anchor(Align2::CENTER_BOTTOM),
+                );
+            }
         }
     }
 
+    /// Draws an arrowhead at the end of a directed line, sized in screen space
+    /// so it stays readable at any zoom level.
+    fn draw_arrowhead(plot_ui: &mut PlotUi, line: &GraphLine, color: Color32) {
+        let start = plot_ui.screen_from_plot(line.start.borrow().clone().into());
+        let end = plot_ui.screen_from_plot(line.end.borrow().clone().into());
+        let direction = (end - start).normalized();
+        if !direction.is_finite() {
+            return;
+        }
+
+        // stop short of the end node so the arrowhead is not hidden behind it
+        let tip = end - direction * 7.0;
+        let back = tip - direction * 10.0;
+        let side = direction.rot90() * 5.0;
+        let points = [back + side, tip, back - side]
+            .iter()
+            .map(|p| {
+                let p = plot_ui.plot_from_screen(*p);
+                [p.x, p.y]
+            })
+            .collect::<Vec<_>>();
+        plot_ui.line(Line::new(points).color(color));
+    }
+
     /// Draws nodes, edges, state data for the related nodes, and any previews for lines being
     /// placed or nodes being moved, and lastly handles input.
     fn plot_show(
@@ -481,6 +608,8 @@ impl Canvas {
         self.draw_lines(plot_ui, options);
         self.draw_nodes(plot_ui, options);
         self.draw_state_data(plot_ui);
+        self.draw_overlay_data(plot_ui);
+        self.draw_comparison_data(plot_ui);
 
         self.draw_previews(plot_ui, pointer_coords);
     }
@@ -617,34 +746,126 @@ impl Canvas {
     pub fn get_lines_as_idx_tuples(&self) -> Vec<(usize, usize)> {
         self.lines
             .iter()
-            .map(|l| {
-                self.nodes
-                    .iter()
-                    .enumerate()
-                    .fold((None, None), |(start, end), (i, n)| {
-                        if start.is_none() && l.start == *n {
-                            return (Some(i), end);
-                        }
-                        if end.is_none() && l.end == *n {
-                            return (start, Some(i));
-                        }
-                        (start, end)
-                    })
-            })
-            .filter_map(|(a, b)| {
-                if let (Some(a), Some(b)) = (a, b) {
-                    Some((a, b))
+            .filter_map(|l| self.line_as_idx_tuple(l))
+            .collect()
+    }
+
+    /// Returns every direction the walker can move along a line as sorted
+    /// `(from, to)` node index pairs, so undirected lines appear once in each
+    /// direction and directed lines only from start to end.
+    pub fn get_arcs_as_idx_tuples(&self) -> Vec<(usize, usize)> {
+        self.get_weighted_arcs()
+            .into_iter()
+            .map(|(a, b, _)| (a, b))
+            .collect()
+    }
+
+    /// Same as [`Self::get_arcs_as_idx_tuples`] with the weight of the line
+    /// each arc belongs to. Lines that give the same arc, such as a directed
+    /// line alongside an undirected one, are merged into a single arc whose
+    /// weight is the sum of theirs.
+    pub fn get_weighted_arcs(&self) -> Vec<(usize, usize, f64)> {
+        let mut arcs = self
+            .lines
+            .iter()
+            .filter_map(|l| self.line_as_idx_tuple(l).map(|arc| (arc, l)))
+            .flat_map(|((a, b), l)| {
+                if l.directed {
+                    vec![(a, b, l.weight)]
                 } else {
-                    None
+                    vec![(a, b, l.weight), (b, a, l.weight)]
                 }
             })
-            .collect()
+            .collect::<Vec<_>>();
+        arcs.sort_unstable_by_key(|(a, b, _)| (*a, *b));
+        arcs.dedup_by(|(a, b, weight), (kept_a, kept_b, kept_weight)| {
+            let is_parallel = a == kept_a && b == kept_b;
+            if is_parallel {
+                *kept_weight += *weight;
+            }
+            is_parallel
+        });
+        arcs
+    }
+
+    fn line_as_idx_tuple(&self, line: &GraphLine) -> Option<(usize, usize)> {
+        let (a, b) = self
+            .nodes
+            .iter()
+            .enumerate()
+            .fold((None, None), |(start, end), (i, n)| {
+                if start.is_none() && line.start == *n {
+                    return (Some(i), end);
+                }
+                if end.is_none() && line.end == *n {
+                    return (start, Some(i));
+                }
+                (start, end)
+            });
+        if let (Some(a), Some(b)) = (a, b) {
+            Some((a, b))
+        } else {
+            None
+        }
     }
 
     pub(crate) fn set_state_data(&mut self, state_data: Option<DVector<f64>>) {
         self.state_data = state_data;
     }
 
+    pub(crate) fn set_overlay_data(&mut self, overlay_data: Option<DVector<f64>>) {
+        self.overlay_data = overlay_data;
+    }
+
+    pub(crate) fn set_comparison_data(
+        &mut self,
+        comparison_data: Option<(DVector<f64>, DVector<f64>)>,
+    ) {
+        self.comparison_data = comparison_data;
+    }
+
+    /// Labels each node with its classical probability above it and its
+    /// quantum probability below it.
+    fn draw_comparison_data(&self, plot_ui: &mut PlotUi) {
+        let Some((classical, quantum)) = self.comparison_data.as_ref() else {
+            return;
+        };
+
+        for ((node, c), q) in self.nodes.iter().zip(classical.iter()).zip(quantum.iter()) {
+            let global_node = plot_ui.screen_from_plot(node.borrow().clone().into());
+            let above = plot_ui.plot_from_screen(global_node + [5.0, -5.0].into());
+            let below = plot_ui.plot_from_screen(global_node + [5.0, 5.0].into());
+            plot_ui.text(
+                Text::new(above, format!("C {:.02}", c))
+                    .color(Color32::LIGHT_BLUE)
+                    .anchor(Align2::LEFT_BOTTOM),
+            );
+            plot_ui.text(
+                Text::new(below, format!("Q {:.02}", q))
+                    .color(Color32::GOLD)
+                    .anchor(Align2::LEFT_TOP),
+            );
+        }
+    }
+
+    /// Draws a second set of probabilities, such as the stationary distribution,
+    /// above each node so it can be compared with the state data below it.
+    fn draw_overlay_data(&self, plot_ui: &mut PlotUi) {
+        let Some(overlay_data) = self.overlay_data.as_ref() else {
+            return;
+        };
+
+        for (node, probability) in self.nodes.iter().zip(overlay_data.iter()) {
+            let global_node = plot_ui.screen_from_plot(node.borrow().clone().into());
+            let adjusted_node = plot_ui.plot_from_screen(global_node + [5.0, -5.0].into());
+            plot_ui.text(
+                Text::new(adjusted_node, format!("π {:.02}", probability))
+                    .color(Color32::LIGHT_GREEN)
+                    .anchor(Align2::LEFT_BOTTOM),
+            );
+        }
+    }
+
     /// Uses node position data combined with state probabilities to draw state probabilities
     /// onto the canvas next to each relevant node.
     fn draw_state_data(&self, plot_ui: &mut PlotUi) {
@@ -668,7 +889,8 @@ impl Canvas {
         }
     }
 
-    fn plot_nodes_with_color_by_state(&self, plot_ui: &mut PlotUi, state: &DVector<f64>) {
+    /// Colours a probability from blue at zero to red at one.
+    fn state_color(prob: f64) -> Color32 {
         trait FromHsl {
             fn from_hsl(hsl: Hsl<f64, Deg<f64>>) -> Self;
         }
@@ -684,27 +906,52 @@ impl Canvas {
             }
         }
 
-        let color = |prob: f64| {
-            // TODO stop somewhere when a NaN occurs
-            let hue = if (0.0..=1.0).contains(&prob) {
-                240.0 * (1.0 - prob)
-            } else {
-                0.0
-            };
-            let hsl = Hsl::new(Deg(hue), 1.0, 0.5);
-            Color32::from_hsl(hsl)
+        // TODO stop somewhere when a NaN occurs
+        let hue = if (0.0..=1.0).contains(&prob) {
+            240.0 * (1.0 - prob)
+        } else {
+            0.0
         };
+        let hsl = Hsl::new(Deg(hue), 1.0, 0.5);
+        Color32::from_hsl(hsl)
+    }
 
+    fn plot_nodes_with_color_by_state(&self, plot_ui: &mut PlotUi, state: &DVector<f64>) {
         let coords = self.nodes_coords();
         for (i, coord) in coords.iter().enumerate() {
             plot_ui.points(
                 Points::new(vec![(*coord)])
                     .filled(true)
                     .radius(5.0)
-                    .color(color(state[i])),
+                    .color(Self::state_color(state[i])),
             )
         }
     }
+
+    /// Draws each node as two halves, the left coloured by the classical
+    /// probability and the right by the quantum probability.
+    fn plot_nodes_split_by_state(
+        &self,
+        plot_ui: &mut PlotUi,
+        classical: &DVector<f64>,
+        quantum: &DVector<f64>,
+    ) {
+        for (i, node) in self.nodes.iter().enumerate() {
+            let center = plot_ui.screen_from_plot(node.borrow().clone().into());
+            for (offset, state) in [(-3.0, classical), (3.0, quantum)] {
+                let Some(prob) = state.get(i) else {
+                    continue;
+                };
+                let half = plot_ui.plot_from_screen(center + [offset, 0.0].into());
+                plot_ui.points(
+                    Points::new(vec![[half.x, half.y]])
+                        .filled(true)
+                        .radius(4.0_f32)
+                        .color(Self::state_color(*prob)),
+                )
+            }
+        }
+    }
 }
 
 impl Serialize for Canvas {
@@ -712,11 +959,20 @@ impl Serialize for Canvas {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("Canvas", 2)?;
+        let mut state = serializer.serialize_struct("Canvas", 4)?;
         state.serialize_field("nodes", &self.nodes)?;
 
-        let serializable_lines: Vec<(usize, usize)> = self.get_lines_as_idx_tuples();
+        let lines = self
+            .lines
+            .iter()
+            .filter_map(|l| self.line_as_idx_tuple(l).map(|idx| (idx, l)))
+            .collect::<Vec<_>>();
+        let serializable_lines: Vec<(usize, usize)> = lines.iter().map(|(idx, _)| *idx).collect();
+        let directed: Vec<bool> = lines.iter().map(|(_, l)| l.directed).collect();
+        let weights: Vec<f64> = lines.iter().map(|(_, l)| l.weight).collect();
         state.serialize_field("lines", &serializable_lines)?;
+        state.serialize_field("directed", &directed)?;
+        state.serialize_field("weights", &weights)?;
 
         state.end()
     }
@@ -731,12 +987,56 @@ impl<'de> Deserialize<'de> for Canvas {
         struct CanvasParts {
             nodes: Vec<Rc<RefCell<GraphNode>>>,
             lines: Vec<(usize, usize)>,
+            #[serde(default)]
+            directed: Vec<bool>,
+            #[serde(default)]
+            weights: Vec<f64>,
         }
 
         let parts: CanvasParts = Deserialize::deserialize(deserializer)?;
 
-        let canvas = Canvas::new(parts.nodes, Some(parts.lines));
+        let canvas = Canvas::new(
+            parts.nodes,
+            Some(parts.lines),
+            parts.directed,
+            parts.weights,
+        );
 
         Ok(canvas)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_round_trip_keeps_direction_and_weight() {
+        let nodes = (0..3)
+            .map(|i| Rc::new(RefCell::new(GraphNode::new_unlabelled(i as f64, 0.0))))
+            .collect::<Vec<_>>();
+        let mut canvas = Canvas::new(nodes, Some(vec![(0, 1)]), vec![], vec![2.5]);
+        canvas.add_directed_line_between_nodes(2, 1);
+
+        let json = serde_json::to_string(&canvas).unwrap();
+        let canvas: Canvas = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            canvas.get_weighted_arcs(),
+            vec![(0, 1, 2.5), (1, 0, 2.5), (2, 1, 1.0)]
+        );
+    }
+
+    #[test]
+    fn test_parallel_arcs_sum_their_weights() {
+        let nodes = (0..3)
+            .map(|i| Rc::new(RefCell::new(GraphNode::new_unlabelled(i as f64, 0.0))))
+            .collect::<Vec<_>>();
+        let mut canvas = Canvas::new(nodes, Some(vec![(0, 1), (1, 2)]), vec![], vec![2.5]);
+        canvas.add_directed_line_between_nodes(0, 1);
+        canvas.add_line_between_nodes(2, 1);
+        assert_eq!(
+            canvas.get_weighted_arcs(),
+            vec![(0, 1, 3.5), (1, 0, 2.5), (1, 2, 2.0), (2, 1, 2.0)]
+        );
+    }
+}
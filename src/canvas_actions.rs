@@ -8,12 +8,31 @@ use crate::graph_line::GraphLine;
 use crate::graph_node::GraphNode;
 use crate::EframeApp;
 
-#[derive(Clone, Default, serde::Serialize, serde::Deserialize, Debug)]
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
 pub struct CanvasActions {
     pub add_label_text: String,
+    #[serde(default)]
+    pub add_directed_lines: bool,
+    #[serde(default = "default_weight_text")]
+    pub set_weight_text: String,
     pub add_graph_values: PlaceGraphValues,
 }
 
+impl Default for CanvasActions {
+    fn default() -> Self {
+        Self {
+            add_label_text: String::new(),
+            add_directed_lines: false,
+            set_weight_text: default_weight_text(),
+            add_graph_values: PlaceGraphValues::default(),
+        }
+    }
+}
+
+fn default_weight_text() -> String {
+    String::from("1")
+}
+
 impl CanvasActions {
     pub fn canvas_menu(
         &mut self,
@@ -86,7 +105,7 @@ impl TryInto<GraphNode> for PlaceGraphValues {
 /// the canvas.
 struct CanvasDetails {
     pub nodes: Vec<GraphNode>,
-    pub lines: Vec<(usize, usize)>,
+    pub lines: Vec<(usize, usize, bool, f64)>,
 }
 
 impl CanvasDetails {
@@ -109,11 +128,15 @@ impl CanvasDetails {
             let node = node.clone() + center_translation.clone(); // TODO tidy cloning here
             canvas.nodes.push(Rc::new(RefCell::new(node)));
         }
-        for (start_idx, end_idx) in &self.lines {
-            canvas.lines.push(GraphLine::new(
-                canvas.nodes[start_idx + old_len].clone(),
-                canvas.nodes[end_idx + old_len].clone(),
-            ));
+        for (start_idx, end_idx, directed, weight) in &self.lines {
+            canvas.lines.push(GraphLine {
+                directed: *directed,
+                weight: *weight,
+                ..GraphLine::new(
+                    canvas.nodes[start_idx + old_len].clone(),
+                    canvas.nodes[end_idx + old_len].clone(),
+                )
+            });
         }
     }
 }
@@ -126,7 +149,7 @@ impl From<EframeApp> for CanvasDetails {
             .iter()
             .map(|n| n.borrow().clone())
             .collect();
-        let lines: Vec<(usize, usize)> = value
+        let lines: Vec<(usize, usize, bool, f64)> = value
             .canvas
             .lines
             .iter()
@@ -137,7 +160,7 @@ impl From<EframeApp> for CanvasDetails {
                     nodes.iter().position(|n| *n == b),
                 );
                 if let (Some(a), Some(b)) = (a, b) {
-                    Some((a, b))
+                    Some((a, b, l.directed, l.weight))
                 } else {
                     None
                 }
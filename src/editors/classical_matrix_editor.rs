@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use evalexpr::{context_map, eval_with_context, HashMapContext, Value};
 use nalgebra::DMatrix;
@@ -16,6 +16,10 @@ pub struct ClassicalMatrixEditor {
 
     text_fields_modified: bool,
 
+    /// The weighted arcs last read from the canvas, used to tell which nodes'
+    /// outgoing lines have changed since.
+    canvas_arcs: Option<Vec<(usize, usize, f64)>>,
+
     is_canvas_update_ready: bool,
 }
 
@@ -38,6 +42,7 @@ impl ClassicalMatrixEditor {
             previous_text_fields: text_fields.clone(),
             text_fields,
             text_fields_modified: false,
+            canvas_arcs: None,
             is_canvas_update_ready: false,
         }
     }
@@ -175,36 +180,55 @@ impl ClassicalMatrixEditor {
         }
         self.matrix = new_matrix;
         self.text_fields = new_text_fields;
+        self.canvas_arcs = None;
         self.previous_text_fields
             .clone_from(&self.text_fields.clone());
     }
 
-    pub(crate) fn update_from_canvas_edges(&mut self, edges: &[(usize, usize)]) {
-        let matrix = &mut self.matrix;
+    /// Keeps the matrix in step with the weighted `(from, to)` arcs on the
+    /// canvas, where entry `(i, j)` is the probability of moving from node `j`
+    /// to node `i`.
+    ///
+    /// A node's column is refilled with weight / weighted degree whenever a
+    /// line is added to or removed from it, or the weight of one of its lines
+    /// changes. Columns that still agree with the canvas keep any values typed
+    /// into the editor.
+    pub(crate) fn update_from_canvas_edges(&mut self, arcs: &[(usize, usize, f64)]) {
+        if self.canvas_arcs.as_deref() == Some(arcs) {
+            return;
+        }
+        let previous_arcs = self.canvas_arcs.replace(arcs.to_vec());
 
-        let edges: HashSet<(usize, usize)> = HashSet::from_iter(edges.iter().cloned());
-        for i in 0..matrix.nrows() {
-            for j in 0..matrix.ncols() {
-                if i == j {
-                    continue;
-                }
+        let n = self.matrix.nrows();
+        let outgoing = |arcs: &[(usize, usize, f64)], j: usize| {
+            arcs.iter()
+                .filter(|(from, to, _)| *from == j && *to != j && *to < n)
+                .map(|(_, to, weight)| (*to, *weight))
+                .collect::<HashMap<_, _>>()
+        };
 
-                let matrix_edge_exists = matrix[(i, j)] != 0.0 || matrix[(j, i)] != 0.0;
-                let canvas_edge_exists = edges.contains(&(i, j)) || edges.contains(&(j, i));
-
-                if matrix_edge_exists && !canvas_edge_exists {
-                    matrix[(i, j)] = 0.0;
-                    matrix[(j, i)] = 0.0;
-                    self.text_fields[i * matrix.nrows() + j] = format!("{}", 0.0);
-                    self.text_fields[j * matrix.nrows() + i] = format!("{}", 0.0);
-                } else if !matrix_edge_exists && canvas_edge_exists {
-                    matrix[(i, j)] = 1.0;
-                    matrix[(j, i)] = 1.0;
-                    self.text_fields[i * matrix.nrows() + j] = format!("{}", 1.0);
-                    self.text_fields[j * matrix.nrows() + i] = format!("{}", 1.0);
-                }
+        for j in 0..n {
+            let weights = outgoing(arcs, j);
+            let is_existence_changed =
+                (0..n).any(|i| i != j && (self.matrix[(i, j)] != 0.0) != weights.contains_key(&i));
+            let is_weight_changed = previous_arcs.as_deref().is_some_and(|previous_arcs| {
+                let previous_weights = outgoing(previous_arcs, j);
+                previous_weights.len() == weights.len()
+                    && previous_weights.keys().all(|i| weights.contains_key(i))
+                    && previous_weights != weights
+            });
+            if !is_existence_changed && !is_weight_changed {
+                continue;
+            }
+
+            let weighted_degree = weights.values().sum::<f64>();
+            for i in (0..n).filter(|i| *i != j) {
+                let value = weights.get(&i).map_or(0.0, |w| w / weighted_degree);
+                self.matrix[(i, j)] = value;
+                self.text_fields[i * n + j] = format!("{}", value);
             }
         }
+        self.previous_text_fields.clone_from(&self.text_fields);
     }
 }
 
@@ -231,6 +255,7 @@ impl From<SerializedMatrixEditor> for ClassicalMatrixEditor {
             previous_text_fields: m.text_fields.clone(),
             text_fields: m.text_fields,
             text_fields_modified: false,
+            canvas_arcs: None,
             is_canvas_update_ready: false,
         }
     }
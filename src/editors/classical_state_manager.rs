@@ -1,7 +1,13 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
 use anyhow::{anyhow, Error, Result};
 use nalgebra::{DMatrix, DVector};
 
-use crate::editors::classical_transition_matrix::ClassicalTransitionMatrix;
+use crate::editors::{classical_transition_matrix::ClassicalTransitionMatrix, StepHistory};
+
+/// The state and target accumulation at a recorded step.
+type ClassicalSnapshot = (DVector<f64>, HashMap<usize, f64>);
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClassicalStateManager {
@@ -9,79 +15,212 @@ pub struct ClassicalStateManager {
     step: usize,
     transition_matrix: ClassicalTransitionMatrix,
     start_node_idx: Option<usize>,
+    target_node_indexes: HashSet<usize>,
+    target_node_accumulation: HashMap<usize, f64>,
+    /// Distribution over nodes used in place of starting at the start node,
+    /// until the start node changes.
+    custom_initial_distribution: Option<DVector<f64>>,
+    history: StepHistory<ClassicalSnapshot>,
 }
 
 impl TryFrom<&DMatrix<f64>> for ClassicalStateManager {
     type Error = Error;
 
     fn try_from(matrix: &DMatrix<f64>) -> Result<Self, Self::Error> {
-        match ClassicalTransitionMatrix::try_from(matrix) {
-            Ok(transition_matrix) => {
-                let initial_state = transition_matrix.get_initial_state(&None);
-                let mut res = Self {
-                    state: initial_state,
-                    step: 0,
-                    transition_matrix,
-                    start_node_idx: None,
-                };
-
-                // TODO implement for reset button also
-                // state starts on edge 0,0. this scatters the state to the
-                // relevant edges without adding to steps.
-                match res.step_forward() {
-                    Err(e) => Err(e),
-                    Ok(_) => {
-                        res.step = 0;
-                        Ok(res)
-                    }
-                }
-            }
-            Err(e) => Err(anyhow!(e)),
-        }
+        Self::from_transition_matrix(ClassicalTransitionMatrix::try_from(matrix)?)
     }
 }
 
 impl ClassicalStateManager {
+    /// Walks on edge states rather than nodes, for rules that depend on the
+    /// edge the walker arrived by.
+    pub fn with_edge_states(matrix: &DMatrix<f64>) -> Result<Self> {
+        Self::from_transition_matrix(ClassicalTransitionMatrix::from_stochastic(matrix, true)?)
+    }
+
+    fn from_transition_matrix(transition_matrix: ClassicalTransitionMatrix) -> Result<Self> {
+        let initial_state = transition_matrix.get_initial_state(&None);
+        let mut res = Self {
+            state: initial_state,
+            step: 0,
+            transition_matrix,
+            start_node_idx: None,
+            target_node_indexes: HashSet::new(),
+            target_node_accumulation: HashMap::new(),
+            custom_initial_distribution: None,
+            history: StepHistory::default(),
+        };
+        res.scatter_initial_state()?;
+        res.record_history();
+        Ok(res)
+    }
+
     pub fn step_forward(&mut self) -> Result<()> {
         self.step += 1;
         if let Ok(updated_state) = self.transition_matrix.apply(self.state.clone()) {
             self.state = updated_state;
+            self.apply_target_nodes();
+            self.record_history();
             Ok(())
         } else {
             Err(anyhow!("Failed to apply transition matrix, try updating the transition matrix from the matrix editor"))
         }
     }
 
+    fn record_history(&mut self) {
+        let snapshot = (self.state.clone(), self.target_node_accumulation.clone());
+        self.history.record(self.step, snapshot);
+    }
+
+    /// Returns to a recorded step, keeping the steps after it so that they can
+    /// be returned to until the walk is stepped forward again.
+    pub fn go_to_step(&mut self, step: usize) -> Result<()> {
+        let (state, target_node_accumulation) = self
+            .history
+            .get(step)
+            .ok_or_else(|| anyhow!("Step {} is no longer in the history", step))?;
+        self.state = state.clone();
+        self.target_node_accumulation = target_node_accumulation.clone();
+        self.step = step;
+        Ok(())
+    }
+
+    pub fn step_back(&mut self) -> Result<()> {
+        match self.step.checked_sub(1) {
+            Some(step) => self.go_to_step(step),
+            None => Err(anyhow!("The walk is already at its first step")),
+        }
+    }
+
+    /// The steps that can be returned to.
+    pub fn get_history_range(&self) -> Option<RangeInclusive<usize>> {
+        self.history.range()
+    }
+
+    pub(crate) fn set_history_capacity(&mut self, capacity: usize) {
+        self.history.set_capacity(capacity);
+    }
+
+    /// On edge states the walk starts on edge `start,start`. This scatters
+    /// the state to the relevant edges without adding to steps.
+    fn scatter_initial_state(&mut self) -> Result<()> {
+        if !self.transition_matrix.has_edge_states() {
+            return Ok(());
+        }
+        self.step_forward()?;
+        self.step = 0;
+        Ok(())
+    }
+
+    /// Moves any probability sitting on a target node into that node's
+    /// accumulation, removing it from the walk.
+    fn apply_target_nodes(&mut self) {
+        let node_count = self.get_node_count();
+        if self.target_node_indexes.is_empty() || node_count == 0 {
+            return;
+        }
+
+        let chunk_size = self.state.len() / node_count;
+        for (i, chunk) in self.state.as_mut_slice().chunks_mut(chunk_size).enumerate() {
+            if self.target_node_indexes.contains(&i) {
+                *self.target_node_accumulation.entry(i).or_insert(0.0) += chunk.iter().sum::<f64>();
+                chunk.iter_mut().for_each(|x| *x = 0.0);
+            }
+        }
+    }
+
     pub(crate) fn get_state_data(&self) -> DVector<f64> {
-        let node_count = (self.state.nrows() as f64).sqrt() as usize;
-        if node_count == 0 {
-            return DVector::from_element(0, 0.0);
+        let node_count = self.get_node_count();
+        let mut res = self.transition_matrix.node_probabilities(&self.state);
+        for (i, v) in self.target_node_accumulation.iter() {
+            if *i < node_count {
+                res[*i] = *v;
+            }
         }
+        res
+    }
 
-        // sum every node_count elements to get the state of each node
-        DVector::from_iterator(
-            node_count,
-            self.state
-                .as_slice()
-                .chunks(node_count)
-                .map(|x| x.iter().sum::<f64>()),
-        )
+    pub(crate) fn get_target_accumulation(&self) -> DVector<f64> {
+        let mut res = DVector::from_element(self.get_node_count(), 0.0);
+        for (i, v) in self.target_node_accumulation.iter() {
+            if *i < res.len() {
+                res[*i] = *v;
+            }
+        }
+        res
     }
 
     pub(crate) fn reset_state(&mut self, matrix: &DMatrix<f64>) {
         self.step = 0;
-        if let Ok(new_transition_matrix) = ClassicalTransitionMatrix::try_from(matrix) {
+        if let Ok(new_transition_matrix) = ClassicalTransitionMatrix::from_stochastic(
+            matrix,
+            self.transition_matrix.has_edge_states(),
+        ) {
             self.transition_matrix = new_transition_matrix;
         } else {
             panic!("Failed to reset state, could not set transition matrix from matrix editor");
         }
-        self.state = self
-            .transition_matrix
-            .get_initial_state(&self.start_node_idx);
+        self.state = self.get_initial_state();
+        self.target_node_accumulation =
+            self.target_node_indexes.iter().map(|x| (*x, 0.0)).collect();
+        if let Err(e) = self.scatter_initial_state() {
+            eprintln!("Error scattering initial state: {}", e);
+        }
+        self.history.clear();
+        self.record_history();
+    }
+
+    /// Starts the walk from `distribution`, normalized, instead of the start
+    /// node.
+    pub(crate) fn set_initial_distribution(
+        &mut self,
+        distribution: DVector<f64>,
+        matrix: &DMatrix<f64>,
+    ) {
+        let sum = distribution.sum();
+        if sum <= 0.0 || distribution.len() != self.get_node_count() {
+            return;
+        }
+        self.custom_initial_distribution = Some(distribution / sum);
+        self.reset_state(matrix);
+    }
+
+    /// Starts the walk from the start node again.
+    pub(crate) fn clear_initial_distribution(&mut self, matrix: &DMatrix<f64>) {
+        if self.custom_initial_distribution.take().is_some() {
+            self.reset_state(matrix);
+        }
+    }
+
+    pub(crate) fn has_initial_distribution(&self) -> bool {
+        matches!(
+            &self.custom_initial_distribution,
+            Some(distribution) if distribution.len() == self.get_node_count()
+        )
+    }
+
+    /// The initial state, on the `start,start` edges before being scattered
+    /// when the walk is on edge states.
+    fn get_initial_state(&self) -> DVector<f64> {
+        match &self.custom_initial_distribution {
+            Some(distribution) if distribution.len() == self.get_node_count() => {
+                let mut res = DVector::from_element(self.transition_matrix.matrix.ncols(), 0.0);
+                for (x, p) in distribution.iter().enumerate() {
+                    res[self.transition_matrix.state_index(x)] = *p;
+                }
+                res
+            }
+            _ => self
+                .transition_matrix
+                .get_initial_state(&self.start_node_idx),
+        }
     }
 
     pub(crate) fn set_transition_matrix_from(&mut self, matrix: &DMatrix<f64>) {
-        if let Ok(new_transition_matrix) = ClassicalTransitionMatrix::try_from(matrix) {
+        if let Ok(new_transition_matrix) = ClassicalTransitionMatrix::from_stochastic(
+            matrix,
+            self.transition_matrix.has_edge_states(),
+        ) {
             self.transition_matrix = new_transition_matrix;
         }
     }
@@ -90,11 +229,28 @@ impl ClassicalStateManager {
         self.step
     }
 
+    /// The probability of each state, one per node unless the walk is on
+    /// edge states.
+    pub fn get_state(&self) -> &DVector<f64> {
+        &self.state
+    }
+
+    fn get_node_count(&self) -> usize {
+        self.transition_matrix.node_count()
+    }
+
     pub(crate) fn is_transition_matrix_sized_correctly(&self, node_count: usize) -> bool {
-        node_count.pow(2) == self.transition_matrix.matrix.ncols()
+        node_count == self.transition_matrix.node_count()
     }
 
     pub(crate) fn set_start_node_idx(&mut self, start_node_idx: usize) {
+        if self.start_node_idx != Some(start_node_idx) {
+            self.custom_initial_distribution = None;
+        }
         self.start_node_idx = Some(start_node_idx);
     }
+
+    pub(crate) fn set_target_node_indexes(&mut self, target_node_indexes: HashSet<usize>) {
+        self.target_node_indexes = target_node_indexes;
+    }
 }
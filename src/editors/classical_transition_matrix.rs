@@ -1,16 +1,21 @@
 use anyhow::{anyhow, Result};
 use nalgebra::{DMatrix, DVector};
 
-use super::TransitionMatrixCorrectionType;
+use super::{SparseMatrix, TransitionMatrixCorrectionType};
 
+/// A classical walk, either directly on the nodes or on edge states `(x, y)`
+/// of a walker at `x` about to move to `y`. Edge states are only needed by
+/// rules that depend on more than the current node.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClassicalTransitionMatrix {
-    pub matrix: DMatrix<f64>,
+    pub matrix: SparseMatrix<f64>,
+    node_count: usize,
+    edge_states: bool,
 }
 
 impl std::fmt::Display for ClassicalTransitionMatrix {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.matrix)
+        write!(f, "{}", self.matrix.to_dense())
     }
 }
 
@@ -18,46 +23,116 @@ impl TryFrom<&DMatrix<f64>> for ClassicalTransitionMatrix {
     type Error = anyhow::Error;
 
     fn try_from(stochastic_matrix: &DMatrix<f64>) -> Result<Self, Self::Error> {
+        Self::from_stochastic(stochastic_matrix, false)
+    }
+}
+
+impl ClassicalTransitionMatrix {
+    /// Builds the generator of a continuous-time chain from a matrix of rates,
+    /// where entry `(i, j)` is the rate of moving from node `j` to node `i`.
+    /// The diagonal of `rates` is ignored and replaced so that every column
+    /// sums to zero.
+    pub fn generator_from_rates(rates: &DMatrix<f64>) -> DMatrix<f64> {
+        let mut generator = rates.clone();
+        generator.fill_diagonal(0.0);
+        let outflow = generator.row_sum();
+        for j in 0..generator.ncols() {
+            generator[(j, j)] = -outflow[j];
+        }
+        generator
+    }
+
+    /// The stochastic matrix `exp(Qt)` that evolves a distribution under the
+    /// generator `Q` for a time `t`.
+    pub fn evolution_operator(generator: &DMatrix<f64>, time_step: f64) -> DMatrix<f64> {
+        (generator * time_step).exp()
+    }
+
+    /// Builds the walk from a stochastic matrix, on the nodes or, when
+    /// `edge_states` is set, on the `n^2` edge states.
+    pub fn from_stochastic(stochastic_matrix: &DMatrix<f64>, edge_states: bool) -> Result<Self> {
         if !stochastic_matrix.is_square() {
             return Err(anyhow!("Matrix is not square"));
         }
 
         let n = stochastic_matrix.nrows();
-        let m = n.pow(2);
-        let mut matrix = DMatrix::from_element(m, m, 0.0);
-
-        for col_offset in 0..n {
-            for j in 0..n {
-                for i in 0..n {
-                    let row = i + (j % n) * n;
-                    let col = j + col_offset * n;
-                    matrix[(row, col)] = stochastic_matrix[(i, j)];
-                }
-            }
-        }
+        let matrix = if edge_states {
+            let m = n.pow(2);
+            SparseMatrix::from_triplets(
+                m,
+                m,
+                (0..n).flat_map(|col_offset| {
+                    (0..n).flat_map(move |j| {
+                        (0..n).map(move |i| {
+                            let row = i + (j % n) * n;
+                            let col = j + col_offset * n;
+                            (row, col, stochastic_matrix[(i, j)])
+                        })
+                    })
+                }),
+            )
+        } else {
+            SparseMatrix::from_dense(stochastic_matrix)
+        };
 
-        let mut res = Self { matrix };
+        let mut res = Self {
+            matrix,
+            node_count: n,
+            edge_states,
+        };
         res.normalize_stochastic();
 
-        if res.matrix.iter().any(|x| x.is_nan()) {
+        if res.matrix.iter().any(|(_, _, x)| x.is_nan()) {
             Err(anyhow!("Matrix is not stochastic"))
         } else {
             Ok(res)
         }
     }
-}
 
-impl ClassicalTransitionMatrix {
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// Whether the walk is on edge states rather than nodes.
+    pub fn has_edge_states(&self) -> bool {
+        self.edge_states
+    }
+
+    /// The state index of a walker at `node`, on its `node,node` edge state
+    /// when the walk is on edge states.
+    pub fn state_index(&self, node: usize) -> usize {
+        if self.edge_states {
+            node * self.node_count + node
+        } else {
+            node
+        }
+    }
+
+    /// The probability of each node, summing the edge states leaving it.
+    pub fn node_probabilities(&self, state: &DVector<f64>) -> DVector<f64> {
+        if !self.edge_states {
+            return state.clone();
+        }
+        if self.node_count == 0 {
+            return DVector::from_element(0, 0.0);
+        }
+
+        // sum every node_count elements to get the state of each node
+        DVector::from_iterator(
+            self.node_count,
+            state
+                .as_slice()
+                .chunks(self.node_count)
+                .map(|x| x.iter().sum::<f64>()),
+        )
+    }
+
     pub fn get_initial_state(&self, start_node_idx: &Option<usize>) -> DVector<f64> {
-        let node_count = (self.matrix.ncols() as f64).sqrt() as usize;
         let mut res = DVector::from_element(self.matrix.ncols(), 0.0);
         if res.is_empty() {
             return res;
         }
-        let start_node_idx = start_node_idx
-            .and_then(|x| Some(x * node_count + x))
-            .unwrap_or(0);
-        res[start_node_idx] = 1.0;
+        res[self.state_index(start_node_idx.unwrap_or(0))] = 1.0;
         res
     }
 
@@ -65,15 +140,18 @@ impl ClassicalTransitionMatrix {
         if state.len() != self.matrix.ncols() {
             return Err(anyhow::anyhow!("Matrix dimensions do not match"));
         }
-        Ok(&self.matrix * state)
+        Ok(self.matrix.mul_vector(&state))
     }
 
     fn normalize_stochastic(&mut self) -> TransitionMatrixCorrectionType {
         let n = self.matrix.nrows();
+        let mut sums = vec![0.0; self.matrix.ncols()];
+        for (_, j, x) in self.matrix.iter() {
+            sums[j] += x;
+        }
 
         let mut res = TransitionMatrixCorrectionType::None;
-        for j in 0..n {
-            let sum = self.matrix.column(j).iter().sum::<f64>();
+        for (j, &sum) in sums.iter().enumerate() {
             if sum != 0.0 && sum != 1.0 {
                 match res {
                     TransitionMatrixCorrectionType::None => {
@@ -91,12 +169,12 @@ impl ClassicalTransitionMatrix {
                     }
                     TransitionMatrixCorrectionType::NonScalar(ref mut res) => res[j] = 1.0 / sum,
                 }
-
-                for i in 0..n {
-                    self.matrix[(i, j)] /= sum;
-                }
             }
         }
+        self.matrix.map_entries(|_, j, x| match sums[j] {
+            sum if sum != 0.0 => x / sum,
+            _ => x,
+        });
 
         res
     }
@@ -107,7 +185,30 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_from_stochastic_matrix_3nodes() {
+    fn test_from_stochastic_matrix() {
+        let input_matrix =
+            DMatrix::from_row_slice(3, 3, &[0.6, 0.0, 1.4, 1.0, 0.0, 0.6, 0.4, 2.0, 0.0]);
+        let output_matrix = ClassicalTransitionMatrix::try_from(&input_matrix).unwrap();
+        assert!(!output_matrix.has_edge_states());
+        assert_eq!(
+            output_matrix.matrix.to_dense(),
+            DMatrix::from_row_slice(3, 3, &[0.3, 0.0, 0.7, 0.5, 0.0, 0.3, 0.2, 1.0, 0.0])
+        );
+
+        // the node walk matches the edge-state walk summed back to nodes
+        let edge_matrix = ClassicalTransitionMatrix::from_stochastic(&input_matrix, true).unwrap();
+        let mut state = output_matrix.get_initial_state(&Some(1));
+        let mut edge_state = edge_matrix.get_initial_state(&Some(1));
+        edge_state = edge_matrix.apply(edge_state).unwrap();
+        for _ in 0..5 {
+            state = output_matrix.apply(state).unwrap();
+            edge_state = edge_matrix.apply(edge_state).unwrap();
+            assert!((edge_matrix.node_probabilities(&edge_state) - &state).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_edge_states_3nodes() {
         let input_matrix = DMatrix::from_fn(3, 3, |i, j| match (i, j) {
             (0, 0) => 0.3,
             (0, 2) => 0.7,
@@ -117,7 +218,8 @@ mod tests {
             (2, 1) => 1.0,
             _ => 0.0,
         });
-        let output_matrix = ClassicalTransitionMatrix::try_from(&input_matrix).unwrap();
+        let output_matrix =
+            ClassicalTransitionMatrix::from_stochastic(&input_matrix, true).unwrap();
         let target_matrix = DMatrix::from_fn(9, 9, |i, j| match (i, j) {
             (0, 0) => 0.3,
             (0, 3) => 0.3,
@@ -139,11 +241,11 @@ mod tests {
             (7, 8) => 0.3,
             _ => 0.0,
         });
-        assert_eq!(output_matrix.matrix, target_matrix);
+        assert_eq!(output_matrix.matrix.to_dense(), target_matrix);
     }
 
     #[test]
-    fn test_from_stochastic_matrix_6nodes() {
+    fn test_edge_states_6nodes() {
         let input_matrix = DMatrix::from_fn(6, 6, |i, j| match (i, j) {
             (1, 0) => 0.5,
             (2, 0) => 0.5,
@@ -156,7 +258,8 @@ mod tests {
             (5, 5) => 1.0,
             _ => 0.0,
         });
-        let output_matrix = ClassicalTransitionMatrix::try_from(&input_matrix).unwrap();
+        let output_matrix =
+            ClassicalTransitionMatrix::from_stochastic(&input_matrix, true).unwrap();
         let target_matrix = DMatrix::from_fn(36, 36, |i, j| match (i, j) {
             (1, 0) => 0.5,
             (1, 12) => 0.5,
@@ -214,6 +317,6 @@ mod tests {
             (35, 5) => 1.0,
             _ => 0.0,
         });
-        assert_eq!(output_matrix.matrix, target_matrix);
+        assert_eq!(output_matrix.matrix.to_dense(), target_matrix);
     }
 }
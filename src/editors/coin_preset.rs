@@ -0,0 +1,178 @@
+use std::f64::consts::TAU;
+
+use anyhow::{anyhow, Result};
+use nalgebra::{Complex, DMatrix};
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use strum::{Display, VariantArray};
+
+/// Coins that can be written into the quantum editor's text fields at runtime.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Display, VariantArray, serde::Serialize, serde::Deserialize,
+)]
+pub enum CoinPreset {
+    /// Grover diffusion coin, `2/d - I`.
+    Grover,
+    /// Discrete Fourier transform coin.
+    #[strum(to_string = "DFT")]
+    Fourier,
+    /// Hadamard coin, only defined for nodes with 2 edges.
+    Hadamard,
+    Identity,
+    #[strum(to_string = "Negative Identity")]
+    NegativeIdentity,
+    /// Haar-distributed random unitary, reproducible from a seed.
+    #[strum(to_string = "Random Unitary")]
+    RandomUnitary,
+    /// Blank coin to be filled in by hand.
+    Custom,
+}
+
+impl CoinPreset {
+    /// The coin for `node` with `degree` edges. Only random unitaries use the
+    /// seed, drawing each node's coin from its own stream so that it does not
+    /// depend on which other nodes the preset is applied to.
+    pub fn matrix(&self, node: usize, degree: usize, seed: u64) -> Result<DMatrix<Complex<f64>>> {
+        let d = degree as f64;
+        Ok(match self {
+            CoinPreset::Grover => DMatrix::from_fn(degree, degree, |i, j| {
+                let diagonal = if i == j { 1.0 } else { 0.0 };
+                Complex::new(2.0 / d - diagonal, 0.0)
+            }),
+            CoinPreset::Fourier => {
+                let scale = 1.0 / d.sqrt();
+                DMatrix::from_fn(degree, degree, |i, j| {
+                    let x = (i * j) as f64 * TAU / d;
+                    Complex::new(x.cos() * scale, x.sin() * scale)
+                })
+            }
+            CoinPreset::Hadamard => {
+                if degree != 2 {
+                    return Err(anyhow!(
+                        "The Hadamard coin needs 2 edges but the node has {}",
+                        degree
+                    ));
+                }
+                let x = 1.0 / 2.0_f64.sqrt();
+                DMatrix::from_row_slice(2, 2, &[x, x, x, -x]).map(Complex::from)
+            }
+            CoinPreset::Identity => DMatrix::identity(degree, degree),
+            CoinPreset::NegativeIdentity => -DMatrix::identity(degree, degree),
+            CoinPreset::RandomUnitary => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(node as u64);
+                Self::haar_unitary(degree, &mut rng)
+            }
+            CoinPreset::Custom => DMatrix::from_element(degree, degree, Complex::new(0.0, 0.0)),
+        })
+    }
+
+    /// The preset as rows of `(real, imaginary)` text fields, in the layout
+    /// used by [`super::ComplexMatrixEditor::text_fields`] for a single node.
+    pub fn text_fields(
+        &self,
+        node: usize,
+        degree: usize,
+        seed: u64,
+    ) -> Result<Vec<Vec<(String, String)>>> {
+        let matrix = self.matrix(node, degree, seed)?;
+        Ok(matrix
+            .row_iter()
+            .map(|row| {
+                row.iter()
+                    .map(|x| (Self::format_value(x.re), Self::format_value(x.im)))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Samples a unitary from the Haar measure by taking the QR decomposition
+    /// of a matrix of complex Gaussians, then fixing the phases of `R`'s
+    /// diagonal so the distribution is uniform (Mezzadri, 2007).
+    fn haar_unitary(degree: usize, rng: &mut impl RngCore) -> DMatrix<Complex<f64>> {
+        let scale = std::f64::consts::FRAC_1_SQRT_2;
+        let ginibre = DMatrix::from_fn(degree, degree, |_, _| {
+            let (re, im) = Self::standard_normal_pair(rng);
+            Complex::new(re * scale, im * scale)
+        });
+        let qr = ginibre.qr();
+        let (mut q, r) = (qr.q(), qr.r());
+        for (j, mut column) in q.column_iter_mut().enumerate() {
+            let d = r[(j, j)];
+            if d.norm() > 0.0 {
+                column *= d / d.norm();
+            }
+        }
+        q
+    }
+
+    /// Two independent standard normal samples using the Box-Muller transform.
+    fn standard_normal_pair(rng: &mut impl RngCore) -> (f64, f64) {
+        // uniform on (0, 1] so the logarithm is finite
+        let u1 = ((rng.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64;
+        let u2 = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        let radius = (-2.0 * u1.ln()).sqrt();
+        let angle = TAU * u2;
+        (radius * angle.cos(), radius * angle.sin())
+    }
+
+    fn format_value(x: f64) -> String {
+        // trim the floating point noise left by the trigonometric functions
+        let x = if x.abs() < 1e-10 { 0.0 } else { x };
+        if x.fract() == 0.0 {
+            format!("{}", x as i64)
+        } else {
+            x.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_presets_are_unitary() {
+        for preset in CoinPreset::VARIANTS {
+            if *preset == CoinPreset::Custom {
+                continue;
+            }
+            for degree in 1..=4 {
+                let Ok(coin) = preset.matrix(0, degree, 0) else {
+                    assert_eq!(*preset, CoinPreset::Hadamard);
+                    continue;
+                };
+                assert_abs_diff_eq!(
+                    coin.adjoint() * &coin,
+                    DMatrix::identity(degree, degree),
+                    epsilon = 1e-12
+                );
+            }
+        }
+        assert_eq!(
+            CoinPreset::Grover.text_fields(0, 2, 0).unwrap(),
+            vec![
+                vec![("0".into(), "0".into()), ("1".into(), "0".into())],
+                vec![("1".into(), "0".into()), ("0".into(), "0".into())],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_random_unitary_is_reproducible() {
+        let coin = CoinPreset::RandomUnitary.matrix(3, 4, 42).unwrap();
+        assert_eq!(coin, CoinPreset::RandomUnitary.matrix(3, 4, 42).unwrap());
+        assert_ne!(coin, CoinPreset::RandomUnitary.matrix(3, 4, 43).unwrap());
+        assert_ne!(coin, CoinPreset::RandomUnitary.matrix(2, 4, 42).unwrap());
+
+        // the text fields must round trip exactly for a saved file to
+        // reproduce the same coins
+        let text_fields = CoinPreset::RandomUnitary.text_fields(3, 4, 42).unwrap();
+        let parsed = DMatrix::from_fn(4, 4, |i, j| {
+            let (re, im) = &text_fields[i][j];
+            Complex::new(re.parse().unwrap(), im.parse().unwrap())
+        });
+        assert_eq!(coin, parsed);
+    }
+}
@@ -0,0 +1,14 @@
+use strum::{Display, VariantArray};
+
+/// Which nodes a coin preset is written to.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Display, VariantArray, serde::Serialize, serde::Deserialize,
+)]
+pub enum CoinTarget {
+    #[strum(to_string = "One node")]
+    Node,
+    #[strum(to_string = "All nodes")]
+    All,
+    #[strum(to_string = "All nodes of degree")]
+    Degree,
+}
@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use egui_plot::{Line, Plot, PlotPoints};
+use nalgebra::DVector;
+
+use crate::engine::{Coin, Comparison, Graph};
+
+/// The graph, start node, targets and coin a comparison was built from.
+type ComparisonSource = (usize, Vec<(usize, usize, f64)>, usize, HashSet<usize>, bool);
+
+/// Runs a classical and a quantum walk on the canvas graph side by side,
+/// plotting how far apart their distributions are and when each reaches the
+/// targets.
+#[derive(Debug, Clone)]
+pub struct ComparisonView {
+    comparison: Option<Comparison>,
+    source: Option<ComparisonSource>,
+    use_fourier_coin: bool,
+    /// The target accumulation each walk is timed to reach.
+    threshold: f64,
+    run_steps: usize,
+    error: Option<String>,
+}
+
+impl Default for ComparisonView {
+    fn default() -> Self {
+        Self {
+            comparison: None,
+            source: None,
+            use_fourier_coin: false,
+            threshold: 0.5,
+            run_steps: 10,
+            error: None,
+        }
+    }
+}
+
+impl ComparisonView {
+    /// Rebuilds both walks when the graph, start node or targets change.
+    pub(crate) fn sync(
+        &mut self,
+        node_count: usize,
+        arcs: &[(usize, usize, f64)],
+        start_node_idx: usize,
+        target_node_indexes: &HashSet<usize>,
+    ) {
+        let source = (
+            node_count,
+            arcs.to_vec(),
+            start_node_idx,
+            target_node_indexes.clone(),
+            self.use_fourier_coin,
+        );
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+        self.source = Some(source);
+
+        let coin = if self.use_fourier_coin {
+            Coin::Fourier
+        } else {
+            Coin::Grover
+        };
+        let comparison = Graph::from_weighted_arcs(node_count, arcs).and_then(|graph| {
+            Comparison::new(graph, coin, start_node_idx, target_node_indexes.clone())
+        });
+        match comparison {
+            Ok(comparison) => {
+                self.comparison = Some(comparison);
+                self.error = None;
+            }
+            Err(e) => {
+                self.comparison = None;
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// The classical and quantum node probabilities at the current step.
+    pub(crate) fn get_state_data(&self) -> Option<(DVector<f64>, DVector<f64>)> {
+        self.comparison.as_ref().map(|comparison| {
+            (
+                comparison.get_classical().get_node_probabilities(),
+                comparison.get_quantum().get_node_probabilities(),
+            )
+        })
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Classical vs Quantum");
+        ui.horizontal(|ui| {
+            ui.label("Quantum coin");
+            ui.radio_value(&mut self.use_fourier_coin, false, "Grover");
+            ui.radio_value(&mut self.use_fourier_coin, true, "Fourier");
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        let Some(comparison) = &mut self.comparison else {
+            return;
+        };
+
+        let mut res = Ok(());
+        ui.label(format!("Step: {}", comparison.get_step()));
+        ui.horizontal(|ui| {
+            if ui.button("Step").clicked() {
+                res = comparison.step();
+            }
+            if ui.button("Reset").clicked() {
+                comparison.reset();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Run").clicked() {
+                res = comparison.run(self.run_steps.max(1));
+            }
+            ui.add(egui::DragValue::new(&mut self.run_steps).range(1..=100_000));
+            ui.label("steps");
+        });
+        if let Err(e) = res {
+            self.error = Some(e.to_string());
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Target accumulation threshold");
+            ui.add(
+                egui::DragValue::new(&mut self.threshold)
+                    .range(0.0..=1.0)
+                    .speed(0.01),
+            );
+        });
+        let describe = |step: Option<usize>| match step {
+            Some(step) => format!("step {}", step),
+            None => "not yet reached".to_string(),
+        };
+        let (classical, quantum) = comparison.time_to_threshold(self.threshold);
+        ui.label(format!("Classical: {}", describe(classical)));
+        ui.label(format!("Quantum: {}", describe(quantum)));
+
+        ui.label("Total variation distance");
+        let distances = comparison
+            .get_distances()
+            .iter()
+            .enumerate()
+            .map(|(step, x)| [step as f64, *x])
+            .collect::<PlotPoints>();
+        Plot::new("comparison_plot")
+            .height(200.0)
+            .include_x(0.0)
+            .include_y(0.0)
+            .include_y(1.0)
+            .x_axis_label("Step")
+            .label_formatter(|_, point| format!("step {}\n{:.4}", point.x.round(), point.y))
+            .show(ui, |plot_ui| plot_ui.line(Line::new(distances)));
+    }
+}
@@ -1,10 +1,20 @@
 use std::collections::HashMap;
 
-use evalexpr::{context_map, eval_with_context, HashMapContext, Value};
+use anyhow::{anyhow, Result};
+use evalexpr::{
+    build_operator_tree, context_map, eval_with_context, Context, ContextWithMutableVariables,
+    HashMapContext, Value,
+};
 use nalgebra::{Complex, DMatrix};
 use strum::VariantArray as _;
 
-use super::{Editor, PropagationMethod};
+use super::{CoinPreset, CoinTarget, Editor, PropagationMethod};
+use crate::engine::Coin;
+
+/// Variables set for every entry of a coin as it is evaluated: the step the
+/// walk is on, the node the coin belongs to, its degree, and the half-edge
+/// indexes of the entry's row and column within the coin.
+const WALK_VARIABLES: [&str; 5] = ["t", "node", "deg", "row", "col"];
 
 #[derive(Debug, Clone)]
 pub struct ComplexMatrixEditor {
@@ -13,6 +23,9 @@ pub struct ComplexMatrixEditor {
     combined_matrix: DMatrix<Complex<f64>>,
 
     math_constants: HashMapContext,
+    /// Named values declared by the user that the text fields can refer to,
+    /// alongside the math constants.
+    parameters: Vec<(String, f64)>,
 
     self_traversing_nodes: Vec<bool>,
     adjacency_list: HashMap<usize, Vec<usize>>,
@@ -20,6 +33,15 @@ pub struct ComplexMatrixEditor {
 
     propagation_method: PropagationMethod,
 
+    coin_preset: CoinPreset,
+    coin_target: CoinTarget,
+    /// The node index or degree the coin preset is applied to, depending on
+    /// the coin target.
+    coin_target_value: usize,
+    /// Seed for random unitary coins, saved so a file reproduces its coins.
+    coin_seed: u64,
+    coin_preset_error: Option<String>,
+
     /// 3 vectors deep refer to: start node, end node, line of connections
     /// edges: a->b       a->c
     /// a->b   [0][1][0]  [0][2][0]
@@ -31,6 +53,8 @@ pub struct ComplexMatrixEditor {
     pub text_fields: Vec<Vec<Vec<(String, String)>>>,
 
     text_fields_modified: bool,
+    /// Whether any text field refers to the step `t`.
+    is_time_dependent: bool,
 
     is_canvas_update_ready: bool,
 }
@@ -46,17 +70,10 @@ impl Editor for ComplexMatrixEditor {
 }
 
 impl ComplexMatrixEditor {
-    pub fn new(edges: &[(usize, usize)]) -> Self {
-        let mut adjacency_list = edges.iter().fold(HashMap::new(), |mut m, (i, j)| {
-            m.entry(*i)
-                .and_modify(|e: &mut Vec<usize>| e.push(*j))
-                .or_insert(vec![*j]);
-            m.entry(*j)
-                .and_modify(|e: &mut Vec<usize>| e.push(*i))
-                .or_insert(vec![*i]);
-            m
-        });
-        adjacency_list.values_mut().for_each(|v| v.sort_unstable());
+    /// Creates an editor for the walk along the given `(from, to)` arcs, see
+    /// [`crate::canvas::Canvas::get_arcs_as_idx_tuples`].
+    pub fn new(arcs: &[(usize, usize)]) -> Self {
+        let adjacency_list = Self::new_adjacency_list(arcs);
 
         let labels = Self::new_labels(&adjacency_list);
 
@@ -88,6 +105,7 @@ impl ComplexMatrixEditor {
             propagation_matrix,
 
             math_constants: Self::get_math_constants(),
+            parameters: Vec::new(),
 
             self_traversing_nodes,
             adjacency_list,
@@ -95,10 +113,17 @@ impl ComplexMatrixEditor {
 
             propagation_method,
 
+            coin_preset: CoinPreset::Grover,
+            coin_target: CoinTarget::All,
+            coin_target_value: 0,
+            coin_seed: 0,
+            coin_preset_error: None,
+
             previous_text_fields: text_fields.clone(),
             text_fields,
 
             text_fields_modified: false,
+            is_time_dependent: false,
             is_canvas_update_ready: false,
         }
     }
@@ -122,6 +147,10 @@ impl ComplexMatrixEditor {
             return;
         }
 
+        if let Err(e) = Self::check_unitary_walk_exists(&self.adjacency_list) {
+            ui.colored_label(egui::Color32::from_rgb(255, 50, 50), e.to_string());
+        }
+
         // sort nodes into order
         let mut from_nodes = self.adjacency_list.keys().copied().collect::<Vec<_>>();
         from_nodes.sort_unstable();
@@ -140,80 +169,13 @@ impl ComplexMatrixEditor {
                 }
             });
 
+        self.show_coin_presets(ui);
+        self.show_parameters(ui);
+
         // display section for each node's connections
         for (i, from) in from_nodes.iter().enumerate() {
             let connections = self.adjacency_list.get(from).unwrap().clone();
 
-            #[cfg(feature = "auto-fill-1")]
-            {
-                let set_zeroes_to_default = (0..self.text_fields[i].len()).all(|j| {
-                    (0..self.text_fields[i][j].len()).all(|k| {
-                        self.text_fields[i][j][k].0 == "0" && self.text_fields[i][j][k].1 == "0"
-                    })
-                });
-                if set_zeroes_to_default {
-                    self.text_fields_modified = true;
-
-                    for j in 0..connections.len() {
-                        let text_fields = &mut self.text_fields[i][j];
-                        text_fields
-                            .iter_mut()
-                            .enumerate()
-                            .for_each(|(k, field)| match (j, k) {
-                                (j, k) if j == k => {
-                                    field.0 = "1".to_string();
-                                    field.1 = "0".to_string();
-                                }
-                                _ => {
-                                    field.0 = "0".to_string();
-                                    field.1 = "1".to_string();
-                                }
-                            });
-                    }
-                }
-            }
-
-            #[cfg(feature = "auto-fill-qft")]
-            {
-                use std::f64::consts::PI;
-
-                let set_zeroes_to_default = (0..self.text_fields[i].len()).all(|j| {
-                    (0..self.text_fields[i][j].len()).all(|k| {
-                        self.text_fields[i][j][k].0 == "0" && self.text_fields[i][j][k].1 == "0"
-                    })
-                });
-                if set_zeroes_to_default {
-                    self.text_fields_modified = true;
-
-                    let size = connections.len();
-                    let mut mtx = DMatrix::from_element(size, size, Complex::new(1.0, 0.0));
-                    for i in 1..size {
-                        for j in 1..size {
-                            let x = i as f64 * j as f64 * 2.0 * PI / size as f64;
-                            mtx[(i, j)] = Complex::new(x.cos(), x.sin())
-                        }
-                    }
-                    for i in 1..size {
-                        for j in 1..size {
-                            if mtx[(i, j)].re.abs() < 1e-10 {
-                                mtx[(i, j)].re = 0.0;
-                            }
-                            if mtx[(i, j)].im.abs() < 1e-10 {
-                                mtx[(i, j)].im = 0.0;
-                            }
-                        }
-                    }
-
-                    for j in 0..connections.len() {
-                        let text_fields = &mut self.text_fields[i][j];
-                        text_fields.iter_mut().enumerate().for_each(|(k, field)| {
-                            field.0 = mtx[(j, k)].re.to_string();
-                            field.1 = mtx[(j, k)].im.to_string();
-                        });
-                    }
-                }
-            }
-
             ui.collapsing(format!("Node {}", from), |ui| {
                 if ui
                     .checkbox(
@@ -266,10 +228,161 @@ impl ComplexMatrixEditor {
         }
     }
 
+    fn show_coin_presets(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Coin")
+                .selected_text(format!("{}", self.coin_preset))
+                .show_ui(ui, |ui| {
+                    for preset in CoinPreset::VARIANTS {
+                        ui.selectable_value(&mut self.coin_preset, *preset, format!("{}", preset));
+                    }
+                });
+            egui::ComboBox::from_label("Apply to")
+                .selected_text(format!("{}", self.coin_target))
+                .show_ui(ui, |ui| {
+                    for target in CoinTarget::VARIANTS {
+                        ui.selectable_value(&mut self.coin_target, *target, format!("{}", target));
+                    }
+                });
+            if self.coin_target != CoinTarget::All {
+                ui.add(egui::DragValue::new(&mut self.coin_target_value));
+            }
+            if self.coin_preset == CoinPreset::RandomUnitary {
+                ui.label("Seed");
+                ui.add(egui::DragValue::new(&mut self.coin_seed));
+            }
+            if ui.button("Apply").clicked() {
+                self.coin_preset_error = self
+                    .apply_coin_preset(self.coin_preset, self.coin_target, self.coin_target_value)
+                    .err()
+                    .map(|e| e.to_string());
+            }
+        });
+        if let Some(e) = &self.coin_preset_error {
+            ui.colored_label(egui::Color32::from_rgb(255, 50, 50), e);
+        }
+    }
+
+    fn show_parameters(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Parameters", |ui| {
+            ui.label(
+                "Entries can also use the step t, the node, its degree deg, and the row and col \
+of the entry within the coin. Entries that use t are evaluated again before every step.",
+            );
+            let mut removed = None;
+            for i in 0..self.parameters.len() {
+                ui.horizontal(|ui| {
+                    let (name, value) = &mut self.parameters[i];
+                    if ui.text_edit_singleline(name).lost_focus() {
+                        self.text_fields_modified = true;
+                    }
+                    if ui.add(egui::DragValue::new(value).speed(0.01)).changed() {
+                        self.text_fields_modified = true;
+                    }
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                });
+                if let Err(e) = self.check_parameter_name(i) {
+                    ui.colored_label(egui::Color32::from_rgb(255, 50, 50), e.to_string());
+                }
+            }
+            if let Some(i) = removed {
+                self.parameters.remove(i);
+                self.text_fields_modified = true;
+            }
+            if ui.button("Add parameter").clicked() {
+                let name = std::iter::once(String::from("theta"))
+                    .chain((1..).map(|i| format!("p{}", i)))
+                    .find(|name| self.parameters.iter().all(|(x, _)| x != name))
+                    .unwrap();
+                self.add_parameter(&name, 0.0);
+            }
+        });
+    }
+
+    /// A parameter name must be an identifier that is neither a math constant
+    /// nor declared by an earlier parameter.
+    pub(crate) fn check_parameter_name(&self, i: usize) -> Result<()> {
+        let name = &self.parameters[i].0;
+        let mut chars = name.chars();
+        if !chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(anyhow!(
+                "Parameter \"{}\" must start with a letter or underscore and only contain letters, \
+digits and underscores",
+                name
+            ));
+        }
+        if WALK_VARIABLES.contains(&name.as_str()) {
+            return Err(anyhow!(
+                "Parameter \"{}\" is reserved for the walk, see the variables above",
+                name
+            ));
+        }
+        if self.math_constants.get_value(name).is_some()
+            || self.parameters[..i].iter().any(|(x, _)| x == name)
+        {
+            return Err(anyhow!("Parameter \"{}\" is already declared", name));
+        }
+        Ok(())
+    }
+
+    /// Writes a coin preset into the text fields of the targeted nodes, which
+    /// are applied on the next [`Self::show`]. `target_value` is the node
+    /// index or degree, depending on `target`. Nodes the preset does not fit
+    /// are left unchanged and reported in the error.
+    pub fn apply_coin_preset(
+        &mut self,
+        preset: CoinPreset,
+        target: CoinTarget,
+        target_value: usize,
+    ) -> Result<()> {
+        let mut from_nodes = self.adjacency_list.keys().copied().collect::<Vec<_>>();
+        from_nodes.sort_unstable();
+
+        let mut applied = 0;
+        let mut errors = Vec::new();
+        for (i, from) in from_nodes.iter().enumerate() {
+            let degree = self.adjacency_list[from].len();
+            let is_target = match target {
+                CoinTarget::Node => *from == target_value,
+                CoinTarget::All => true,
+                CoinTarget::Degree => degree == target_value,
+            };
+            if !is_target || degree == 0 {
+                continue;
+            }
+
+            match preset.text_fields(*from, degree, self.coin_seed) {
+                Ok(text_fields) => {
+                    self.text_fields[i] = text_fields;
+                    applied += 1;
+                }
+                Err(e) => errors.push(format!("Node {}: {}", from, e)),
+            }
+        }
+
+        if applied > 0 {
+            self.text_fields_modified = true;
+        }
+        match (applied, errors.is_empty()) {
+            (_, false) => Err(anyhow!(errors.join("\n"))),
+            (0, true) => Err(anyhow!("No nodes with edges match the coin target")),
+            _ => Ok(()),
+        }
+    }
+
     fn apply_text_fields(&mut self) {
         // the position of each group of numbers is the index of the FROM node plus
         // the sum of all previous adjacent nodes
 
+        // the matrix previewed and walked until the first step is at t = 0
+        let mut context = self.get_context();
+
         // sort nodes into order
         let mut past_adjacencies = 0;
         let mut from_nodes = self.adjacency_list.keys().copied().collect::<Vec<_>>();
@@ -282,7 +395,8 @@ impl ComplexMatrixEditor {
 
             for j in 0..connections_count {
                 for k in 0..connections_count {
-                    let re = eval_with_context(&self.text_fields[i][j][k].0, &self.math_constants);
+                    Self::set_walk_variables(&mut context, 0, i, connections_count, j, k);
+                    let re = eval_with_context(&self.text_fields[i][j][k].0, &context);
                     let re = match re {
                         Ok(Value::Int(num)) => num as f64,
                         Ok(Value::Float(num)) => num,
@@ -292,7 +406,7 @@ impl ComplexMatrixEditor {
                         }
                     };
 
-                    let im = eval_with_context(&self.text_fields[i][j][k].1, &self.math_constants);
+                    let im = eval_with_context(&self.text_fields[i][j][k].1, &context);
                     let im = match im {
                         Ok(Value::Int(num)) => num as f64,
                         Ok(Value::Float(num)) => num,
@@ -315,10 +429,41 @@ impl ComplexMatrixEditor {
 
         self.combined_matrix = &self.scatter_matrix * &self.propagation_matrix;
         self.previous_text_fields.clone_from(&self.text_fields);
+        self.is_time_dependent = Self::refers_to_step(&self.text_fields);
         self.text_fields_modified = false;
         self.is_canvas_update_ready = true;
     }
 
+    fn set_walk_variables(
+        context: &mut HashMapContext,
+        t: usize,
+        node: usize,
+        degree: usize,
+        row: usize,
+        col: usize,
+    ) {
+        for (name, value) in WALK_VARIABLES.iter().zip([t, node, degree, row, col]) {
+            context
+                .set_value(name.to_string(), Value::Float(value as f64))
+                .unwrap();
+        }
+    }
+
+    /// Whether any entry depends on the step, so that the coins have to be
+    /// evaluated again before every step.
+    pub fn is_time_dependent(&self) -> bool {
+        self.is_time_dependent
+    }
+
+    fn refers_to_step(text_fields: &[Vec<Vec<(String, String)>>]) -> bool {
+        text_fields.iter().flatten().flatten().any(|(re, im)| {
+            [re, im].into_iter().any(|text| {
+                build_operator_tree(text)
+                    .is_ok_and(|tree| tree.iter_variable_identifiers().any(|x| x == "t"))
+            })
+        })
+    }
+
     fn set_value(&mut self, row: usize, col: usize, value: Complex<f64>) {
         self.scatter_matrix[(row, col)] = value;
     }
@@ -327,6 +472,7 @@ impl ComplexMatrixEditor {
         self.labels = Self::new_labels(&self.adjacency_list);
         self.text_fields = Self::new_text_fields(&self.adjacency_list);
         self.previous_text_fields.clone_from(&self.text_fields);
+        self.is_time_dependent = false;
         self.scatter_matrix = Self::new_scatter_matrix(self.labels.len());
         self.propagation_matrix =
             Self::new_propagation_matrix(self.propagation_method, &self.labels);
@@ -334,7 +480,54 @@ impl ComplexMatrixEditor {
         self.is_canvas_update_ready = true;
     }
 
-    fn new_labels(adjacency_list: &HashMap<usize, Vec<usize>>) -> Vec<(usize, usize)> {
+    /// Builds an adjacency list from `(from, to)` arcs where each node's
+    /// outgoing neighbours are sorted in ascending order. Nodes that are only
+    /// ever arrived at still get an entry.
+    pub(crate) fn new_adjacency_list(arcs: &[(usize, usize)]) -> HashMap<usize, Vec<usize>> {
+        let mut adjacency_list = arcs.iter().fold(HashMap::new(), |mut m, (i, j)| {
+            m.entry(*i)
+                .and_modify(|e: &mut Vec<usize>| e.push(*j))
+                .or_insert(vec![*j]);
+            m.entry(*j).or_insert(vec![]);
+            m
+        });
+        adjacency_list.values_mut().for_each(|v| {
+            v.sort_unstable();
+            v.dedup();
+        });
+        adjacency_list
+    }
+
+    /// A coined walk on the arcs is only unitary when every node can be left
+    /// along as many arcs as it can be arrived at, so that the shift pairs each
+    /// incoming arc with an outgoing one. Undirected graphs always satisfy this.
+    pub(crate) fn check_unitary_walk_exists(
+        adjacency_list: &HashMap<usize, Vec<usize>>,
+    ) -> Result<()> {
+        let mut in_degrees: HashMap<usize, usize> = HashMap::new();
+        for to in adjacency_list.values().flatten() {
+            *in_degrees.entry(*to).or_default() += 1;
+        }
+
+        let mut nodes = adjacency_list.keys().copied().collect::<Vec<_>>();
+        nodes.sort_unstable();
+        for node in nodes {
+            let in_degree = in_degrees.get(&node).copied().unwrap_or(0);
+            let out_degree = adjacency_list[&node].len();
+            if in_degree != out_degree {
+                return Err(anyhow!(
+                    "Node {} can be arrived at along {} edges but left along {}, \
+so no unitary walk exists on this directed graph",
+                    node,
+                    in_degree,
+                    out_degree
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn new_labels(adjacency_list: &HashMap<usize, Vec<usize>>) -> Vec<(usize, usize)> {
         let mut labels = adjacency_list.iter().collect::<Vec<_>>();
         labels.sort_unstable();
         labels
@@ -367,7 +560,7 @@ impl ComplexMatrixEditor {
         DMatrix::from_element(half_edge_count, half_edge_count, Complex::new(0.0, 0.0))
     }
 
-    fn new_propagation_matrix(
+    pub(crate) fn new_propagation_matrix(
         propagation_method: PropagationMethod,
         labels: &[(usize, usize)],
     ) -> DMatrix<Complex<f64>> {
@@ -375,19 +568,54 @@ impl ComplexMatrixEditor {
         match propagation_method {
             PropagationMethod::Blank => DMatrix::from_element(n, n, Complex::new(0.0, 0.0)),
             PropagationMethod::ExampleMatrix => {
-                DMatrix::from_fn(n, n, |i, j| {
-                    // where the coordinates point to some node that has 2 edges, eg 0->0, 0->1
-                    // being on some edge 0->1 would then place the particle on edge 1->0
-                    // 0, 1
-                    // 1, 0
-                    if labels[i].0 == labels[j].1 && labels[i].1 == labels[j].0 {
-                        Complex::new(1.0, 0.0)
-                    } else {
-                        Complex::new(0.0, 0.0)
-                    }
-                })
+                let mut matrix = DMatrix::from_element(n, n, Complex::new(0.0, 0.0));
+                for (i, j) in Self::propagation_pairs(labels) {
+                    matrix[(i, j)] = Complex::new(1.0, 0.0);
+                }
+                matrix
+            }
+        }
+    }
+
+    /// The `(i, j)` entries of the example propagation matrix that are one,
+    /// moving the walker from half-edge `j` onto half-edge `i`.
+    pub(crate) fn propagation_pairs(labels: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        // where the coordinates point to some node that has 2 edges, eg 0->0, 0->1
+        // being on some edge 0->1 would then place the particle on edge 1->0
+        // 0, 1
+        // 1, 0
+        let mut indexes: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (i, label) in labels.iter().enumerate() {
+            indexes.entry(*label).or_default().push(i);
+        }
+        let mut pairs = labels
+            .iter()
+            .enumerate()
+            .flat_map(|(j, &(from, to))| {
+                indexes
+                    .get(&(to, from))
+                    .into_iter()
+                    .flatten()
+                    .map(move |i| (*i, j))
+            })
+            .collect::<Vec<_>>();
+
+        // directed edges have no reverse to flip onto, so the arcs
+        // arriving at each node are paired in order with the arcs
+        // leaving it that also have no reverse
+        let mut unpaired_in: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut unpaired_out: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, &(from, to)) in labels.iter().enumerate() {
+            if !indexes.contains_key(&(to, from)) {
+                unpaired_in.entry(to).or_default().push(i);
+                unpaired_out.entry(from).or_default().push(i);
             }
         }
+        for (node, arriving) in unpaired_in.iter() {
+            let leaving = unpaired_out.get(node).map(Vec::as_slice).unwrap_or(&[]);
+            pairs.extend(arriving.iter().zip(leaving.iter()).map(|(j, i)| (*i, *j)));
+        }
+        pairs
     }
 
     fn show_preview_fields(&self, ui: &mut egui::Ui) {
@@ -440,7 +668,7 @@ impl ComplexMatrixEditor {
         });
     }
 
-    fn get_math_constants() -> HashMapContext {
+    pub(crate) fn get_math_constants() -> HashMapContext {
         context_map! {
             "pi" => std::f64::consts::PI,
             "e" => std::f64::consts::E,
@@ -449,10 +677,140 @@ impl ComplexMatrixEditor {
         .unwrap()
     }
 
+    /// The math constants along with every parameter that has a valid name.
+    pub fn get_context(&self) -> HashMapContext {
+        let mut context = self.math_constants.clone();
+        for (i, (name, value)) in self.parameters.iter().enumerate() {
+            if self.check_parameter_name(i).is_ok() {
+                context
+                    .set_value(name.clone(), Value::Float(*value))
+                    .unwrap();
+            }
+        }
+        context
+    }
+
+    pub fn get_parameters(&self) -> &[(String, f64)] {
+        &self.parameters
+    }
+
+    /// Declares another parameter, which is left out of the context while its
+    /// name is invalid or already declared, see [`Self::check_parameter_name`].
+    pub fn add_parameter(&mut self, name: &str, value: f64) {
+        self.parameters.push((name.to_string(), value));
+        self.text_fields_modified = true;
+    }
+
+    /// Evaluates the text fields of every node into its coin at step `t`,
+    /// failing on the first entry that is not a number in `context`.
+    pub fn evaluate_coins(
+        &self,
+        context: &HashMapContext,
+        t: usize,
+    ) -> Result<HashMap<usize, DMatrix<Complex<f64>>>> {
+        let mut context = context.clone();
+        let evaluate = |context: &HashMapContext, node: usize, text: &str| match eval_with_context(
+            text, context,
+        ) {
+            Ok(Value::Int(num)) => Ok(num as f64),
+            Ok(Value::Float(num)) => Ok(num),
+            Ok(value) => Err(anyhow!("Node {}: \"{}\" is not a number", node, value)),
+            Err(e) => Err(anyhow!("Node {}: {}", node, e)),
+        };
+
+        let mut from_nodes = self.adjacency_list.keys().copied().collect::<Vec<_>>();
+        from_nodes.sort_unstable();
+
+        let mut coins = HashMap::new();
+        for (i, node) in from_nodes.into_iter().enumerate() {
+            let degree = self.adjacency_list[&node].len();
+            let mut coin = DMatrix::from_element(degree, degree, Complex::new(0.0, 0.0));
+            for j in 0..degree {
+                for k in 0..degree {
+                    Self::set_walk_variables(&mut context, t, node, degree, j, k);
+                    let (re, im) = &self.text_fields[i][j][k];
+                    coin[(j, k)] =
+                        Complex::new(evaluate(&context, node, re)?, evaluate(&context, node, im)?);
+                }
+            }
+            coins.insert(node, coin);
+        }
+        Ok(coins)
+    }
+
+    /// The coins at step `t` with the parameter `name` set to `value`,
+    /// declaring it for this evaluation only if it is not already.
+    pub fn evaluate_coins_with(
+        &self,
+        name: &str,
+        value: f64,
+        t: usize,
+    ) -> Result<HashMap<usize, DMatrix<Complex<f64>>>> {
+        let mut context = self.get_context();
+        context.set_value(name.to_string(), Value::Float(value))?;
+        self.evaluate_coins(&context, t)
+    }
+
+    /// The combined matrix with the coins evaluated at step `t`, which only
+    /// differs from [`Self::get_combined_matrix`] for time dependent coins.
+    /// Unlike the coins typed in, these are not corrected to be unitary, so a
+    /// coin that is not unitary at `t` is an error.
+    pub fn get_combined_matrix_at(&self, t: usize) -> Result<DMatrix<Complex<f64>>> {
+        let coins = self.evaluate_coins(&self.get_context(), t)?;
+
+        let mut from_nodes = self.adjacency_list.keys().copied().collect::<Vec<_>>();
+        from_nodes.sort_unstable();
+
+        let n = self.labels.len();
+        let mut scatter_matrix = DMatrix::from_element(n, n, Complex::new(0.0, 0.0));
+        let mut past_adjacencies = 0;
+        for node in from_nodes {
+            let degree = self.adjacency_list[&node].len();
+            let coin = &coins[&node];
+            if !Coin::is_unitary(coin) {
+                return Err(anyhow!(
+                    "The coin of node {} is not unitary at step {}",
+                    node,
+                    t
+                ));
+            }
+            scatter_matrix
+                .view_mut((past_adjacencies, past_adjacencies), (degree, degree))
+                .copy_from(coin);
+            past_adjacencies += degree;
+        }
+        Ok(scatter_matrix * &self.propagation_matrix)
+    }
+
+    pub fn get_propagation_method(&self) -> PropagationMethod {
+        self.propagation_method
+    }
+
     pub fn get_combined_matrix(&self) -> &DMatrix<Complex<f64>> {
         &self.combined_matrix
     }
 
+    /// The coin each node applies to its outgoing half-edges, read back out of
+    /// the scatter matrix.
+    pub fn get_coins(&self) -> HashMap<usize, DMatrix<Complex<f64>>> {
+        let mut from_nodes = self.adjacency_list.keys().copied().collect::<Vec<_>>();
+        from_nodes.sort_unstable();
+
+        let mut past_adjacencies = 0;
+        let mut coins = HashMap::new();
+        for node in from_nodes {
+            let degree = self.adjacency_list[&node].len();
+            coins.insert(
+                node,
+                self.scatter_matrix
+                    .view((past_adjacencies, past_adjacencies), (degree, degree))
+                    .clone_owned(),
+            );
+            past_adjacencies += degree;
+        }
+        coins
+    }
+
     pub fn get_labels(&self) -> &[(usize, usize)] {
         &self.labels
     }
@@ -473,6 +831,10 @@ struct SerializedMatrixEditor {
     labels: Vec<(usize, usize)>,
     propagation_method: PropagationMethod,
     text_fields: Vec<Vec<Vec<(String, String)>>>,
+    #[serde(default)]
+    coin_seed: u64,
+    #[serde(default)]
+    parameters: Vec<(String, f64)>,
 }
 impl From<ComplexMatrixEditor> for SerializedMatrixEditor {
     fn from(m: ComplexMatrixEditor) -> Self {
@@ -486,6 +848,8 @@ impl From<ComplexMatrixEditor> for SerializedMatrixEditor {
             labels: m.labels,
             propagation_method: m.propagation_method,
             text_fields: m.text_fields,
+            coin_seed: m.coin_seed,
+            parameters: m.parameters,
         }
     }
 }
@@ -517,11 +881,18 @@ impl From<SerializedMatrixEditor> for ComplexMatrixEditor {
                     .collect(),
             ),
             math_constants: Self::get_math_constants(),
+            parameters: m.parameters,
             self_traversing_nodes: m.self_traversing_nodes,
             adjacency_list: m.adjacency_list,
             labels: m.labels,
             propagation_method: m.propagation_method,
+            coin_preset: CoinPreset::Grover,
+            coin_target: CoinTarget::All,
+            coin_target_value: 0,
+            coin_seed: m.coin_seed,
+            coin_preset_error: None,
             previous_text_fields: m.text_fields.clone(),
+            is_time_dependent: Self::refers_to_step(&m.text_fields),
             text_fields: m.text_fields,
             text_fields_modified: false,
             is_canvas_update_ready: false,
@@ -538,3 +909,96 @@ impl<'de> serde::Deserialize<'de> for ComplexMatrixEditor {
         SerializedMatrixEditor::deserialize(deserializer).map(Self::from)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An editor for the undirected path 0 - 1 - 2, with every entry of every
+    /// coin typed in as `(re, im)`.
+    fn path_editor(re: &str, im: &str) -> ComplexMatrixEditor {
+        let mut editor = ComplexMatrixEditor::new(&[(0, 1), (1, 0), (1, 2), (2, 1)]);
+        for field in editor.text_fields.iter_mut().flatten().flatten() {
+            *field = (re.to_string(), im.to_string());
+        }
+        editor.apply_text_fields();
+        editor
+    }
+
+    #[test]
+    fn test_time_dependent_phase_coin() {
+        let editor = path_editor(
+            "if(row == col, math::cos(pi * t / 4), 0)",
+            "if(row == col, math::sin(pi * t / 4), 0)",
+        );
+        assert!(editor.is_time_dependent());
+
+        let coins = editor.evaluate_coins(&editor.get_context(), 2).unwrap();
+        assert!((coins[&1][(0, 0)] - Complex::new(0.0, 1.0)).norm() < 1e-12);
+        assert!((coins[&1][(1, 1)] - Complex::new(0.0, 1.0)).norm() < 1e-12);
+        assert_eq!(coins[&1][(0, 1)], Complex::new(0.0, 0.0));
+
+        // the typed in matrix is the walk at t = 0, and every coin is the
+        // same phase, so each step only turns the whole operator by pi / 4
+        let first = editor.get_combined_matrix_at(0).unwrap();
+        assert!((&first - editor.get_combined_matrix()).norm() < 1e-12);
+        let second = editor.get_combined_matrix_at(1).unwrap();
+        let phase = Complex::from_polar(1.0, std::f64::consts::FRAC_PI_4);
+        assert!((second - first * phase).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_position_dependent_coin() {
+        // a Grover coin written once for nodes of every degree
+        let editor = path_editor("2 / deg - if(row == col, 1, 0)", "0");
+        assert!(!editor.is_time_dependent());
+
+        let coins = editor.evaluate_coins(&editor.get_context(), 0).unwrap();
+        let real = |coin: &DMatrix<Complex<f64>>| coin.map(|x| x.re);
+        assert_eq!(real(&coins[&0]), DMatrix::from_element(1, 1, 1.0));
+        assert_eq!(real(&coins[&2]), DMatrix::from_element(1, 1, 1.0));
+        assert_eq!(
+            real(&coins[&1]),
+            DMatrix::from_row_slice(2, 2, &[0.0, 1.0, 1.0, 0.0])
+        );
+        assert_eq!(&editor.get_coins()[&1], &coins[&1]);
+
+        let editor = path_editor("node", "0");
+        let coins = editor.evaluate_coins(&editor.get_context(), 0).unwrap();
+        assert_eq!(coins[&2][(0, 0)], Complex::new(2.0, 0.0));
+    }
+
+    #[test]
+    fn test_refers_to_step() {
+        let fields = |re: &str, im: &str| vec![vec![vec![(re.to_string(), im.to_string())]]];
+        assert!(ComplexMatrixEditor::refers_to_step(&fields("t", "0")));
+        assert!(ComplexMatrixEditor::refers_to_step(&fields(
+            "0",
+            "math::sin(2 * t)"
+        )));
+        assert!(!ComplexMatrixEditor::refers_to_step(&fields("tau", "0")));
+        assert!(!ComplexMatrixEditor::refers_to_step(&fields(
+            "theta * node",
+            "deg"
+        )));
+        // an entry that cannot be read at all does not make the walk change
+        assert!(!ComplexMatrixEditor::refers_to_step(&fields("(t", "0")));
+    }
+
+    #[test]
+    fn test_coin_not_unitary_at_step() {
+        // unitary to begin with, then scaled up
+        let editor = path_editor("if(row == col, 1 + t, 0)", "0");
+        assert!(editor.get_combined_matrix_at(0).is_ok());
+        let error = editor.get_combined_matrix_at(1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The coin of node 0 is not unitary at step 1"
+        );
+
+        // not even a number once t passes 1
+        let editor = path_editor("if(row == col, math::sqrt(1 - t), 0)", "0");
+        assert!(editor.get_combined_matrix_at(0).is_ok());
+        assert!(editor.get_combined_matrix_at(2).is_err());
+    }
+}
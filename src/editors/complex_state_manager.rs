@@ -1,8 +1,30 @@
 use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
 
+use anyhow::{anyhow, Result};
 use nalgebra::{Complex, DMatrix, DVector, Normed};
 
-use super::complex_transition_matrix::ComplexTransitionMatrix;
+use crate::options::DistributionView;
+
+use super::{
+    complex_transition_matrix::ComplexTransitionMatrix, Decoherence, Measurement, StepHistory,
+    TwoParticleWalk,
+};
+
+/// The parts of a walk that change as it steps, recorded so that it can
+/// return to an earlier step.
+#[derive(Debug, Clone)]
+struct ComplexSnapshot {
+    state: DVector<Complex<f64>>,
+    density_matrix: Option<DMatrix<Complex<f64>>>,
+    target_node_accumulation: HashMap<usize, f64>,
+    amount_removed_by_accumulation: f64,
+    probability_sum: DVector<f64>,
+    measurement: Measurement,
+    measurement_count: usize,
+    last_measured_node: Option<usize>,
+    two_particle_walk: TwoParticleWalk,
+}
 
 #[derive(Debug, Clone)]
 pub struct ComplexStateManager {
@@ -12,10 +34,34 @@ pub struct ComplexStateManager {
     is_state_updated: bool,
     step: usize,
     transition_matrix: ComplexTransitionMatrix,
+    /// The first step walked with `transition_matrix`.
+    transition_matrix_step: usize,
+    /// The operators walked with before `transition_matrix`, each with the
+    /// first step walked with it, so that steps no longer in the history are
+    /// undone with the operator that walked them.
+    previous_transition_matrices: Vec<(usize, ComplexTransitionMatrix)>,
     start_node_idx: Option<usize>,
     target_node_indexes: HashSet<usize>,
     target_node_accumulation: HashMap<usize, f64>,
     amount_removed_by_accumulation: f64,
+    /// Half-edge probabilities summed over every step so far, including the
+    /// initial state.
+    probability_sum: DVector<f64>,
+    /// Cached infinite-time average of the half-edge probabilities, cleared
+    /// whenever the matrix or initial state changes.
+    limiting_probabilities: Option<Result<DVector<f64>, String>>,
+    /// State used in place of starting at the start node, until the start node
+    /// changes.
+    custom_initial_state: Option<DVector<Complex<f64>>>,
+    decoherence: Decoherence,
+    /// The mixed state evolved in place of `state` while decoherence is
+    /// enabled.
+    density_matrix: Option<DMatrix<Complex<f64>>>,
+    measurement: Measurement,
+    measurement_count: usize,
+    last_measured_node: Option<usize>,
+    two_particle_walk: TwoParticleWalk,
+    history: StepHistory<ComplexSnapshot>,
 }
 
 impl ComplexStateManager {
@@ -25,40 +71,230 @@ impl ComplexStateManager {
         start_node_idx: usize,
         target_node_indexes: HashSet<usize>,
     ) -> Self {
-        let transition_matrix = ComplexTransitionMatrix::new(matrix.clone());
+        Self::from_transition_matrix(
+            ComplexTransitionMatrix::new(matrix.clone()),
+            labels,
+            start_node_idx,
+            target_node_indexes,
+        )
+    }
 
+    /// Starts a walk on an operator that has already been built, such as one
+    /// only stored sparsely.
+    pub fn from_transition_matrix(
+        transition_matrix: ComplexTransitionMatrix,
+        labels: &[(usize, usize)],
+        start_node_idx: usize,
+        target_node_indexes: HashSet<usize>,
+    ) -> Self {
         let initial_state = transition_matrix.get_initial_state(Some(start_node_idx), labels);
 
         let target_node_accumulation = target_node_indexes.iter().map(|x| (*x, 0.0)).collect();
 
-        Self {
+        let probability_sum = initial_state.map(|x| x.norm_squared());
+
+        let mut res = Self {
             state: initial_state,
             probability_vector: DVector::from_element(0, 0.0),
             labels: labels.to_vec(),
             is_state_updated: true,
             step: 0,
             transition_matrix,
+            transition_matrix_step: 0,
+            previous_transition_matrices: Vec::new(),
             start_node_idx: Some(start_node_idx),
             target_node_indexes,
             target_node_accumulation,
             amount_removed_by_accumulation: 0.0,
-        }
+            probability_sum,
+            limiting_probabilities: None,
+            custom_initial_state: None,
+            decoherence: Decoherence::default(),
+            density_matrix: None,
+            measurement: Measurement::default(),
+            measurement_count: 0,
+            last_measured_node: None,
+            two_particle_walk: TwoParticleWalk::default(),
+            history: StepHistory::default(),
+        };
+        res.record_history();
+        res
     }
 
     pub fn step_forward(&mut self) {
+        // the steps after this one were walked with operators that are being
+        // walked over again
+        if self.transition_matrix_step > self.step {
+            self.forget_transition_matrices_from(self.step);
+            self.transition_matrix_step = self.step;
+        }
         self.step += 1;
-        self.state = self.transition_matrix.apply(self.state.clone());
-        self.apply_target_nodes();
+        match self.density_matrix.take() {
+            Some(density_matrix) => {
+                let density_matrix = self.transition_matrix.apply_to_density(&density_matrix);
+                self.density_matrix = Some(self.decoherence.apply(&density_matrix, &self.labels));
+            }
+            None => self.state = self.transition_matrix.apply(self.state.clone()),
+        }
+        if self.measurement.enabled {
+            let weight = self.measurement.weight(self.step);
+            if weight > 0.0 {
+                self.measure_position(weight);
+            }
+        } else {
+            self.apply_target_nodes(1.0);
+        }
+        if self.two_particle_walk.enabled {
+            self.two_particle_walk
+                .step(&self.transition_matrix, &self.labels);
+        }
+        let probabilities = self.half_edge_probabilities();
+        if self.probability_sum.len() == probabilities.len() {
+            self.probability_sum += probabilities;
+        }
         self.is_state_updated = true;
+        self.record_history();
+    }
+
+    fn record_history(&mut self) {
+        let snapshot = ComplexSnapshot {
+            state: self.state.clone(),
+            density_matrix: self.density_matrix.clone(),
+            target_node_accumulation: self.target_node_accumulation.clone(),
+            amount_removed_by_accumulation: self.amount_removed_by_accumulation,
+            probability_sum: self.probability_sum.clone(),
+            measurement: self.measurement.clone(),
+            measurement_count: self.measurement_count,
+            last_measured_node: self.last_measured_node,
+            two_particle_walk: self.two_particle_walk.clone(),
+        };
+        self.history.record(self.step, snapshot);
+    }
+
+    /// Returns to a recorded step, keeping the steps after it so that they can
+    /// be returned to until the walk is stepped forward again.
+    pub fn go_to_step(&mut self, step: usize) -> Result<()> {
+        let snapshot = self
+            .history
+            .get(step)
+            .ok_or_else(|| anyhow!("Step {} is no longer in the history", step))?
+            .clone();
+        self.state = snapshot.state;
+        self.density_matrix = snapshot.density_matrix;
+        self.target_node_accumulation = snapshot.target_node_accumulation;
+        self.amount_removed_by_accumulation = snapshot.amount_removed_by_accumulation;
+        self.probability_sum = snapshot.probability_sum;
+        self.measurement = snapshot.measurement;
+        self.measurement_count = snapshot.measurement_count;
+        self.last_measured_node = snapshot.last_measured_node;
+        self.two_particle_walk = snapshot.two_particle_walk;
+        self.step = step;
+        self.is_state_updated = true;
+        Ok(())
+    }
+
+    /// Returns to the previous step, undoing the step with the adjoint of the
+    /// operator it was walked with once it is no longer in the history.
+    pub fn step_back(&mut self) -> Result<()> {
+        let Some(step) = self.step.checked_sub(1) else {
+            return Err(anyhow!("The walk is already at its first step"));
+        };
+        if self.history.get(step).is_some() {
+            return self.go_to_step(step);
+        }
+        if !self.is_reversible() {
+            return Err(anyhow!(
+                "Step {} is no longer in the history and the walk cannot be undone exactly",
+                step
+            ));
+        }
+
+        let probabilities = self.half_edge_probabilities();
+        if self.probability_sum.len() == probabilities.len() {
+            self.probability_sum -= probabilities;
+        }
+        self.state = self
+            .get_transition_matrix_at(step)
+            .apply_adjoint(&self.state);
+        self.step = step;
+        self.is_state_updated = true;
+        Ok(())
+    }
+
+    /// Whether every step so far has been unitary, so that the adjoint undoes
+    /// it exactly: a single pure walker that has not been absorbed or measured.
+    pub fn is_reversible(&self) -> bool {
+        self.density_matrix.is_none()
+            && !self.measurement.enabled
+            && !self.two_particle_walk.enabled
+            && self.amount_removed_by_accumulation == 0.0
+    }
+
+    /// The operator the walk from `step` was walked with.
+    fn get_transition_matrix_at(&self, step: usize) -> &ComplexTransitionMatrix {
+        if step >= self.transition_matrix_step {
+            return &self.transition_matrix;
+        }
+        self.previous_transition_matrices
+            .iter()
+            .rev()
+            .find(|(first_step, _)| *first_step <= step)
+            .map_or(&self.transition_matrix, |(_, matrix)| matrix)
+    }
+
+    fn forget_transition_matrices_from(&mut self, step: usize) {
+        self.previous_transition_matrices
+            .retain(|(first_step, _)| *first_step < step);
+    }
+
+    /// The steps that can be returned to without undoing them.
+    pub fn get_history_range(&self) -> Option<RangeInclusive<usize>> {
+        self.history.range()
+    }
+
+    pub(crate) fn set_history_capacity(&mut self, capacity: usize) {
+        self.history.set_capacity(capacity);
     }
 
-    fn apply_target_nodes(&mut self) {
+    /// Removes the walker found at target nodes when measuring with `weight`,
+    /// where a weight below 1 only finds it with that probability.
+    fn apply_target_nodes(&mut self, weight: f64) {
+        let remaining = (1.0 - weight).sqrt();
+        if let Some(density_matrix) = &mut self.density_matrix {
+            let mut no_change = true;
+            for (idx, (i, _)) in self.labels.iter().enumerate() {
+                if idx < density_matrix.nrows() && self.target_node_indexes.contains(i) {
+                    *self.target_node_accumulation.entry(*i).or_insert(0.0) += (1.0
+                        - self.amount_removed_by_accumulation)
+                        * weight
+                        * density_matrix[(idx, idx)].re;
+                    density_matrix.row_mut(idx).scale_mut(remaining);
+                    density_matrix.column_mut(idx).scale_mut(remaining);
+
+                    no_change = false;
+                }
+            }
+
+            if no_change {
+                return;
+            }
+
+            let new_total = density_matrix.trace().re;
+            self.amount_removed_by_accumulation +=
+                (1.0 - self.amount_removed_by_accumulation) * (1.0 - new_total);
+            // nothing is left to renormalize once every target has absorbed the walker
+            if new_total > 0.0 {
+                *density_matrix /= Complex::from(new_total);
+            }
+            return;
+        }
+
         let mut no_change = true;
         for ((i, _), v) in self.labels.iter().zip(self.state.iter_mut()) {
             if self.target_node_indexes.contains(i) {
                 *self.target_node_accumulation.entry(*i).or_insert(0.0) +=
-                    (1.0 - self.amount_removed_by_accumulation) * v.norm_squared();
-                *v = Complex::new(0.0, 0.0);
+                    (1.0 - self.amount_removed_by_accumulation) * weight * v.norm_squared();
+                *v *= remaining;
 
                 no_change = false;
             }
@@ -73,9 +309,99 @@ impl ComplexStateManager {
         self.amount_removed_by_accumulation +=
             (1.0 - self.amount_removed_by_accumulation) * (1.0 - new_total);
 
-        new_total = new_total.sqrt();
-        for v in self.state.iter_mut() {
-            *v /= new_total;
+        if new_total > 0.0 {
+            new_total = new_total.sqrt();
+            for v in self.state.iter_mut() {
+                *v /= new_total;
+            }
+        }
+    }
+
+    /// Measures which node the walker is at with `weight`, absorbing it at
+    /// targets, then either collapses onto a sampled node or averages over
+    /// every node.
+    fn measure_position(&mut self, weight: f64) {
+        self.measurement_count += 1;
+        if self.measurement.is_ensemble() && self.density_matrix.is_some() {
+            self.average_position_measurement(weight);
+            return;
+        }
+        self.apply_target_nodes(weight);
+        if self.measurement.is_ensemble() {
+            return;
+        }
+
+        let probabilities = self.half_edge_probabilities();
+        let mut sample = self.measurement.sample_uniform() * probabilities.sum();
+        let Some(node) = self
+            .labels
+            .iter()
+            .zip(probabilities.iter())
+            .find(|(_, p)| {
+                sample -= *p;
+                sample < 0.0
+            })
+            .map(|((i, _), _)| *i)
+        else {
+            return;
+        };
+        self.last_measured_node = Some(node);
+
+        let is_outside = |idx: usize| self.labels.get(idx).map_or(true, |l| l.0 != node);
+        match &mut self.density_matrix {
+            Some(density_matrix) => {
+                for idx in (0..density_matrix.nrows()).filter(|idx| is_outside(*idx)) {
+                    density_matrix.row_mut(idx).fill(Complex::new(0.0, 0.0));
+                    density_matrix.column_mut(idx).fill(Complex::new(0.0, 0.0));
+                }
+                let total = density_matrix.trace();
+                *density_matrix /= total;
+            }
+            None => {
+                for (idx, v) in self.state.iter_mut().enumerate() {
+                    if is_outside(idx) {
+                        *v = Complex::new(0.0, 0.0);
+                    }
+                }
+                let norm = self.state.norm();
+                self.state /= Complex::from(norm);
+            }
+        }
+    }
+
+    /// Averages over the outcomes of a measurement made with probability
+    /// `weight`, `(1 - weight) ρ + weight Σ P_n ρ P_n` summed over the
+    /// non-target nodes `n`, so the walker found at a target is absorbed and
+    /// only the coherences within the node it was found at are kept.
+    fn average_position_measurement(&mut self, weight: f64) {
+        let Some(density_matrix) = &mut self.density_matrix else {
+            return;
+        };
+        let node_of = |idx: usize| self.labels.get(idx).map(|l| l.0);
+        let is_target =
+            |idx: usize| node_of(idx).is_some_and(|node| self.target_node_indexes.contains(&node));
+
+        for idx in (0..density_matrix.nrows()).filter(|idx| is_target(*idx)) {
+            if let Some(node) = node_of(idx) {
+                *self.target_node_accumulation.entry(node).or_insert(0.0) += (1.0
+                    - self.amount_removed_by_accumulation)
+                    * weight
+                    * density_matrix[(idx, idx)].re;
+            }
+        }
+        for i in 0..density_matrix.nrows() {
+            for j in 0..density_matrix.ncols() {
+                if is_target(i) || node_of(i) != node_of(j) {
+                    density_matrix[(i, j)] *= 1.0 - weight;
+                }
+            }
+        }
+
+        let new_total = density_matrix.trace().re;
+        self.amount_removed_by_accumulation +=
+            (1.0 - self.amount_removed_by_accumulation) * (1.0 - new_total);
+        if new_total > 0.0 {
+            *density_matrix /= Complex::from(new_total);
         }
     }
 
@@ -102,13 +428,28 @@ impl ComplexStateManager {
 
     pub(crate) fn reset_state(&mut self, labels: &[(usize, usize)]) {
         self.step = 0;
-        self.state = self
-            .transition_matrix
-            .get_initial_state(self.start_node_idx, labels);
+        self.transition_matrix_step = 0;
+        self.previous_transition_matrices.clear();
+        self.state = self.get_initial_state(labels);
+        self.density_matrix = (self.decoherence.enabled || self.measurement.is_ensemble())
+            .then(|| &self.state * self.state.adjoint());
+        self.measurement.restart();
+        self.measurement_count = 0;
+        self.last_measured_node = None;
+        if self.two_particle_walk.enabled {
+            let second = self
+                .transition_matrix
+                .get_initial_state(Some(self.two_particle_walk.second_start_node_idx), labels);
+            self.two_particle_walk.reset(&self.state, &second);
+        }
         self.target_node_accumulation =
             self.target_node_indexes.iter().map(|x| (*x, 0.0)).collect();
         self.amount_removed_by_accumulation = 0.0;
+        self.probability_sum = self.half_edge_probabilities();
+        self.limiting_probabilities = None;
         self.is_state_updated = true;
+        self.history.clear();
+        self.record_history();
     }
 
     pub(crate) fn make_transition_matrix_compatible(&mut self, matrix: &DMatrix<Complex<f64>>) {
@@ -126,6 +467,10 @@ impl ComplexStateManager {
     }
 
     pub(crate) fn set_start_node_idx(&mut self, start_node_idx: usize) {
+        if self.start_node_idx != Some(start_node_idx) {
+            self.limiting_probabilities = None;
+            self.custom_initial_state = None;
+        }
         self.start_node_idx = Some(start_node_idx);
     }
 
@@ -134,21 +479,209 @@ impl ComplexStateManager {
     }
 
     pub(crate) fn set_transition_matrix_from(&mut self, combined_matrix: &DMatrix<Complex<f64>>) {
-        self.transition_matrix = ComplexTransitionMatrix::new(combined_matrix.clone());
+        self.set_transition_matrix(ComplexTransitionMatrix::new(combined_matrix.clone()));
     }
 
-    fn update_probability_vector(&mut self, adjacency_list: &HashMap<usize, Vec<usize>>) {
-        self.probability_vector = if self.state.is_empty() {
-            DVector::from_element(0, 0.0)
+    /// Walks the steps that follow with `transition_matrix`, keeping the
+    /// current state and the operator the steps before were walked with.
+    pub(crate) fn set_transition_matrix(&mut self, transition_matrix: ComplexTransitionMatrix) {
+        let previous = std::mem::replace(&mut self.transition_matrix, transition_matrix);
+        if self.transition_matrix_step < self.step {
+            self.previous_transition_matrices
+                .push((self.transition_matrix_step, previous));
         } else {
-            // collapse rows
-            let temp = DVector::from_iterator(
-                self.state.nrows(),
-                self.state
-                    .row_iter()
-                    .map(|row| row.iter().map(|x| x.norm_squared()).sum::<f64>()),
+            self.forget_transition_matrices_from(self.step);
+        }
+        self.transition_matrix_step = self.step;
+        self.limiting_probabilities = None;
+    }
+
+    /// Starts the walk from `state`, normalized, instead of the start node.
+    pub(crate) fn set_initial_state(&mut self, state: DVector<Complex<f64>>) {
+        let norm = state.norm();
+        if norm == 0.0 || state.len() != self.transition_matrix.dimension() {
+            return;
+        }
+        self.custom_initial_state = Some(state / Complex::from(norm));
+        let labels = self.labels.clone();
+        self.reset_state(&labels);
+    }
+
+    /// Starts the walk from the start node again.
+    pub(crate) fn clear_initial_state(&mut self) {
+        if self.custom_initial_state.take().is_some() {
+            let labels = self.labels.clone();
+            self.reset_state(&labels);
+        }
+    }
+
+    pub(crate) fn has_initial_state(&self) -> bool {
+        matches!(
+            &self.custom_initial_state,
+            Some(state) if state.len() == self.transition_matrix.dimension()
+        )
+    }
+
+    /// Evolves a density matrix with `decoherence` applied after every step,
+    /// or the pure state when it is disabled, restarting the walk.
+    pub fn set_decoherence(&mut self, decoherence: Decoherence) {
+        self.decoherence = decoherence;
+        let labels = self.labels.clone();
+        self.reset_state(&labels);
+    }
+
+    /// Measures the walker's position intermittently, restarting the walk.
+    pub fn set_measurement(&mut self, measurement: Measurement) {
+        self.measurement = measurement;
+        let labels = self.labels.clone();
+        self.reset_state(&labels);
+    }
+
+    /// Walks a second particle alongside the first when `two_particle_walk`
+    /// is enabled, restarting the walk.
+    pub fn set_two_particle_walk(&mut self, two_particle_walk: TwoParticleWalk) {
+        self.two_particle_walk = two_particle_walk;
+        let labels = self.labels.clone();
+        self.reset_state(&labels);
+    }
+
+    /// The two particle walk, when enabled.
+    pub fn get_two_particle_walk(&self) -> Option<&TwoParticleWalk> {
+        self.two_particle_walk
+            .enabled
+            .then_some(&self.two_particle_walk)
+    }
+
+    fn get_initial_state(&self, labels: &[(usize, usize)]) -> DVector<Complex<f64>> {
+        match &self.custom_initial_state {
+            Some(state) if state.len() == self.transition_matrix.dimension() => state.clone(),
+            _ => self
+                .transition_matrix
+                .get_initial_state(self.start_node_idx, labels),
+        }
+    }
+
+    pub(crate) fn get_transition_matrix(&self) -> &ComplexTransitionMatrix {
+        &self.transition_matrix
+    }
+
+    /// Node probabilities in the form chosen by `view`, falling back to the
+    /// current probabilities if the infinite-time limit cannot be found.
+    pub(crate) fn get_view_data(
+        &mut self,
+        view: DistributionView,
+        adjacency_list: &HashMap<usize, Vec<usize>>,
+    ) -> DVector<f64> {
+        if self.two_particle_walk.enabled {
+            // the chance of finding either walker at each node
+            let correlations = self
+                .two_particle_walk
+                .get_correlations(&self.labels, adjacency_list.len());
+            let (first, second) = TwoParticleWalk::get_marginals(&correlations);
+            return (first + second) / 2.0;
+        }
+        match view {
+            DistributionView::Instantaneous => self.get_state_data(adjacency_list),
+            DistributionView::RunningAverage => self.get_running_average(adjacency_list),
+            DistributionView::Limit => match self.get_limiting_distribution(adjacency_list) {
+                Ok(limit) => limit,
+                Err(_) => self.get_state_data(adjacency_list),
+            },
+        }
+    }
+
+    /// The Cesàro average of the node probabilities over every step so far,
+    /// of the walker that has not been absorbed at a target.
+    pub(crate) fn get_running_average(
+        &self,
+        adjacency_list: &HashMap<usize, Vec<usize>>,
+    ) -> DVector<f64> {
+        if self.probability_sum.len() != self.half_edge_probabilities().len() {
+            // the matrix was resized without the state being reset
+            return self.collapse_state_to_nodes(adjacency_list);
+        }
+        let average = &self.probability_sum / (self.step + 1) as f64;
+        Self::collapse_to_nodes(&average, adjacency_list)
+    }
+
+    /// The node probabilities averaged over infinitely many steps from the
+    /// initial state, ignoring any target nodes.
+    pub(crate) fn get_limiting_distribution(
+        &mut self,
+        adjacency_list: &HashMap<usize, Vec<usize>>,
+    ) -> Result<DVector<f64>, String> {
+        if self.density_matrix.is_some() || self.measurement.enabled {
+            return Err(
+                "The infinite time limit is only found without decoherence or measurement"
+                    .to_string(),
+            );
+        }
+        if self.limiting_probabilities.is_none() {
+            let initial_state = self.get_initial_state(&self.labels);
+            self.limiting_probabilities = Some(
+                self.transition_matrix
+                    .time_averaged_probabilities(&initial_state)
+                    .map_err(|e| e.to_string()),
             );
+        }
+        match self.limiting_probabilities.as_ref().unwrap() {
+            Ok(probabilities) => Ok(Self::collapse_to_nodes(probabilities, adjacency_list)),
+            Err(e) => Err(e.clone()),
+        }
+    }
+
+    /// Node probabilities for the current state, including any probability
+    /// accumulated at target nodes. Unlike [`Self::get_state_data`] this does
+    /// not touch the cached probability vector.
+    pub(crate) fn get_node_probabilities(
+        &self,
+        adjacency_list: &HashMap<usize, Vec<usize>>,
+    ) -> DVector<f64> {
+        self.account_for_targets(self.collapse_state_to_nodes(adjacency_list))
+    }
+
+    pub fn get_state(&self) -> &DVector<Complex<f64>> {
+        &self.state
+    }
+
+    /// The mixed state of the walk, when decoherence is enabled.
+    pub fn get_density_matrix(&self) -> Option<&DMatrix<Complex<f64>>> {
+        self.density_matrix.as_ref()
+    }
 
+    /// The probability of each half-edge, from the diagonal of the density
+    /// matrix when decoherence is enabled.
+    fn half_edge_probabilities(&self) -> DVector<f64> {
+        match &self.density_matrix {
+            Some(density_matrix) => density_matrix.diagonal().map(|x| x.re),
+            None => self.state.map(|x| x.norm_squared()),
+        }
+    }
+
+    pub(crate) fn get_target_node_accumulation(&self) -> &HashMap<usize, f64> {
+        &self.target_node_accumulation
+    }
+
+    pub fn get_amount_removed_by_accumulation(&self) -> f64 {
+        self.amount_removed_by_accumulation
+    }
+
+    fn update_probability_vector(&mut self, adjacency_list: &HashMap<usize, Vec<usize>>) {
+        self.probability_vector = self.collapse_state_to_nodes(adjacency_list);
+    }
+
+    fn collapse_state_to_nodes(&self, adjacency_list: &HashMap<usize, Vec<usize>>) -> DVector<f64> {
+        Self::collapse_to_nodes(&self.half_edge_probabilities(), adjacency_list)
+    }
+
+    /// Sums half-edge probabilities into the node each half-edge leaves from.
+    pub(crate) fn collapse_to_nodes(
+        temp: &DVector<f64>,
+        adjacency_list: &HashMap<usize, Vec<usize>>,
+    ) -> DVector<f64> {
+        if temp.is_empty() {
+            DVector::from_element(0, 0.0)
+        } else {
             // collapse adjacent columns
             let mut res = DVector::from_element(adjacency_list.len(), 0.0);
             let mut past_edges = 0;
@@ -160,7 +693,7 @@ impl ComplexStateManager {
                 past_edges += edge_count;
             }
             res
-        };
+        }
     }
 
     pub fn show(
@@ -173,17 +706,77 @@ impl ComplexStateManager {
 
         self.transition_matrix.show(ui, labels);
 
+        ui.collapsing("Decoherence", |ui| {
+            if self.decoherence.show(ui, labels.len()) {
+                self.reset_state(labels);
+            }
+        });
+        ui.collapsing("Intermittent Measurement", |ui| {
+            if self.measurement.show(ui) {
+                self.reset_state(labels);
+            }
+            if self.measurement.enabled {
+                ui.label(format!("Measurements so far: {}", self.measurement_count));
+                if let Some(node) = self.last_measured_node {
+                    ui.label(format!("Last found at node {}", node));
+                }
+            }
+        });
+        ui.collapsing("Two Particle Walk", |ui| {
+            if self.two_particle_walk.show(ui, adjacency_list.len()) {
+                self.reset_state(labels);
+            }
+            if self.two_particle_walk.enabled {
+                let correlations = self
+                    .two_particle_walk
+                    .get_correlations(labels, adjacency_list.len());
+                let (first, second) = TwoParticleWalk::get_marginals(&correlations);
+                ui.label("First walker");
+                self.display_node_vector(ui, &first);
+                ui.label("Second walker");
+                self.display_node_vector(ui, &second);
+                ui.label("Two-site correlations");
+                TwoParticleWalk::show_correlations(ui, &correlations);
+            }
+        });
+
         ui.separator();
 
         ui.heading("State");
-        ui.collapsing("Complex", |ui| {
-            self.display_half_edge_vector(ui, &self.state);
-        });
+        match &self.density_matrix {
+            Some(density_matrix) => {
+                ui.collapsing("Density Matrix", |ui| {
+                    let purity = (density_matrix * density_matrix).trace().re;
+                    let coherence = density_matrix.iter().map(|x| x.norm()).sum::<f64>()
+                        - density_matrix.trace().re;
+                    ui.label(format!("Purity: {:.04}", purity));
+                    ui.label(format!("Coherence (l1 norm): {:.04}", coherence));
+                    self.display_half_edge_vector(ui, &density_matrix.diagonal());
+                });
+            }
+            None => {
+                ui.collapsing("Complex", |ui| {
+                    self.display_half_edge_vector(ui, &self.state);
+                });
+            }
+        }
 
         let probability_vector = self.get_state_data(adjacency_list);
         ui.collapsing("As Probabilities", |ui| {
             self.display_node_vector(ui, &probability_vector);
         });
+        let running_average = self.get_running_average(adjacency_list);
+        ui.collapsing("Running Time Average", |ui| {
+            self.display_node_vector(ui, &running_average);
+        });
+        ui.collapsing("Infinite Time Average", |ui| {
+            match self.get_limiting_distribution(adjacency_list) {
+                Ok(limit) => self.display_node_vector(ui, &limit),
+                Err(e) => {
+                    ui.label(e);
+                }
+            }
+        });
         if !self.target_node_indexes.is_empty() {
             let target_accumulation = self.get_target_accumulation();
             ui.collapsing("Target Node Accumulation", |ui| {
@@ -292,3 +885,88 @@ impl ComplexStateManager {
         self.labels = labels.to_vec();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editors::MeasurementOutcome;
+    use approx::assert_abs_diff_eq;
+
+    fn real_matrix(entries: &[f64]) -> DMatrix<Complex<f64>> {
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &entries
+                .iter()
+                .map(|x| Complex::new(*x, 0.0))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn assert_state(csm: &ComplexStateManager, expected: [f64; 2]) {
+        for (amplitude, expected) in csm.get_state().iter().zip(expected) {
+            assert_abs_diff_eq!(amplitude.re, expected, epsilon = 1e-9);
+            assert_abs_diff_eq!(amplitude.im, 0.0, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_step_back_across_operator_changes() {
+        let swap = real_matrix(&[0.0, 1.0, 1.0, 0.0]);
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let hadamard = real_matrix(&[h, h, h, -h]);
+        let mut csm = ComplexStateManager::new(&swap, &[(0, 1), (1, 0)], 0, HashSet::new());
+        csm.set_history_capacity(1);
+
+        csm.step_forward();
+        assert_state(&csm, [0.0, 1.0]);
+        csm.set_transition_matrix_from(&hadamard);
+        csm.step_forward();
+        assert_state(&csm, [h, -h]);
+
+        // each evicted step is undone with the operator it was walked with
+        csm.step_back().unwrap();
+        assert_state(&csm, [0.0, 1.0]);
+        csm.step_back().unwrap();
+        assert_state(&csm, [1.0, 0.0]);
+
+        // walking the first step again with the current operator replaces the
+        // one it was first walked with
+        csm.step_forward();
+        assert_state(&csm, [h, h]);
+        csm.set_transition_matrix_from(&swap);
+        csm.step_forward();
+        assert_state(&csm, [h, h]);
+        csm.step_back().unwrap();
+        csm.step_back().unwrap();
+        assert_eq!(csm.get_step(), 0);
+        assert_state(&csm, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_ensemble_measurement_at_targets() {
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let hadamard = real_matrix(&[h, h, h, -h]);
+        let mut csm = ComplexStateManager::new(&hadamard, &[(0, 1), (1, 0)], 0, HashSet::from([1]));
+        csm.set_measurement(Measurement::with_probability(
+            0.5,
+            MeasurementOutcome::Ensemble,
+            0,
+        ));
+
+        // the walker is spread evenly over both nodes before the measurement,
+        // which finds it at the target half of the time it is made
+        csm.step_forward();
+        assert_abs_diff_eq!(
+            csm.get_target_node_accumulation()[&1],
+            0.25,
+            epsilon = 1e-12
+        );
+        // of the 3/4 left, 1/2 was never measured and keeps its coherence
+        let density_matrix = csm.get_density_matrix().unwrap();
+        assert_abs_diff_eq!(density_matrix[(0, 0)].re, 2.0 / 3.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(density_matrix[(1, 1)].re, 1.0 / 3.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(density_matrix[(0, 1)].re, 1.0 / 3.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(density_matrix[(1, 0)].re, 1.0 / 3.0, epsilon = 1e-12);
+    }
+}
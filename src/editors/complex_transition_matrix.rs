@@ -1,7 +1,22 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
 use egui::Color32;
-use nalgebra::{Complex, DMatrix, DVector};
+use nalgebra::{Complex, DMatrix, DVector, Schur, SymmetricEigen};
+
+use super::{transition_matrix_correction_type::TransitionMatrixCorrectionType, SparseMatrix};
+
+/// Walks with more half-edges than this are stepped with a sparse copy of
+/// their operator when most of its entries are zero.
+const SPARSE_DIMENSION: usize = 64;
 
-use super::transition_matrix_correction_type::TransitionMatrixCorrectionType;
+/// How far `Uv` may be from `λv` for an eigenpair returned by
+/// [`ComplexTransitionMatrix::unitary_eigen`].
+const MAX_EIGEN_RESIDUAL: f64 = 1e-8;
+
+/// Eigenvalues alongside their eigenvectors as the columns of a matrix.
+pub type ComplexEigen = (DVector<Complex<f64>>, DMatrix<Complex<f64>>);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Headers {
@@ -11,14 +26,17 @@ enum Headers {
 
 #[derive(Debug, Clone)]
 pub struct ComplexTransitionMatrix {
+    /// The dense operator, left empty when it is only stored sparsely.
     matrix: DMatrix<Complex<f64>>,
+    /// The operator used to step large walks.
+    sparse: Option<SparseMatrix<Complex<f64>>>,
     last_normalization_correction: TransitionMatrixCorrectionType,
     max_error: f64,
 }
 
 impl std::fmt::Display for ComplexTransitionMatrix {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.matrix)
+        write!(f, "{}", self.get_complex_matrix())
     }
 }
 
@@ -26,6 +44,7 @@ impl ComplexTransitionMatrix {
     pub fn new(matrix: DMatrix<Complex<f64>>) -> Self {
         let mut res = Self {
             matrix,
+            sparse: None,
             last_normalization_correction: TransitionMatrixCorrectionType::None,
             max_error: 1e-10,
         };
@@ -33,6 +52,43 @@ impl ComplexTransitionMatrix {
         res
     }
 
+    /// Keeps a large operator in sparse form only, building the dense form
+    /// just for spectral analysis. Unlike [`Self::new`] the operator is not
+    /// corrected to be unitary, so it should be unitary by construction.
+    /// Small operators are stored densely and corrected as usual.
+    pub fn from_sparse(sparse: SparseMatrix<Complex<f64>>) -> Self {
+        if sparse.nrows() <= SPARSE_DIMENSION {
+            return Self::new(sparse.to_dense());
+        }
+        Self {
+            matrix: DMatrix::zeros(0, 0),
+            sparse: Some(sparse),
+            last_normalization_correction: TransitionMatrixCorrectionType::None,
+            max_error: 1e-10,
+        }
+    }
+
+    /// Keeps a sparse copy of a large dense operator that is mostly zeros.
+    fn update_sparse(&mut self) {
+        let n = self.matrix.nrows();
+        let non_zero = self.matrix.iter().filter(|x| x.norm_sqr() != 0.0).count();
+        self.sparse = (n > SPARSE_DIMENSION && non_zero * 4 < n * n)
+            .then(|| SparseMatrix::from_dense(&self.matrix));
+    }
+
+    /// The number of half-edges the operator acts on.
+    pub fn dimension(&self) -> usize {
+        match &self.sparse {
+            Some(sparse) => sparse.ncols(),
+            None => self.matrix.ncols(),
+        }
+    }
+
+    /// Whether steps are applied with the sparse form of the operator.
+    pub fn is_sparse(&self) -> bool {
+        self.sparse.is_some()
+    }
+
     pub(crate) fn show(&self, ui: &mut egui::Ui, labels: &[(usize, usize)]) {
         ui.heading("Transition Matrix Data");
 
@@ -60,8 +116,173 @@ impl ComplexTransitionMatrix {
         }
     }
 
-    pub fn get_complex_matrix(&self) -> &DMatrix<Complex<f64>> {
-        &self.matrix
+    /// The unitary `exp(-iHt)` that evolves a state under the Hamiltonian `H`
+    /// for a time `t`. Only the Hermitian part of `hamiltonian` is used.
+    pub fn evolution_operator(
+        hamiltonian: &DMatrix<Complex<f64>>,
+        time_step: f64,
+    ) -> DMatrix<Complex<f64>> {
+        let hermitian = (hamiltonian + hamiltonian.adjoint()) * Complex::new(0.5, 0.0);
+        let eigen = SymmetricEigen::new(hermitian);
+        let phases = eigen
+            .eigenvalues
+            .map(|x| Complex::new(0.0, -x * time_step).exp());
+        &eigen.eigenvectors * DMatrix::from_diagonal(&phases) * eigen.eigenvectors.adjoint()
+    }
+
+    /// Szegedy's quantization of a Markov chain, where entry `(i, j)` of
+    /// `stochastic` is the weight of moving from node `j` to node `i`.
+    ///
+    /// The walk lives on the pairs `(x, y)` that the chain can move between in
+    /// either direction, returned alongside the operator in the same order as
+    /// half-edge labels. Each step reflects about the states
+    /// `|x> (x) sum_y sqrt(P(y|x)) |y>` and then swaps the two registers.
+    /// Columns are normalized, and a node the chain cannot leave keeps the
+    /// walker on a pair `(x, x)` so that every node has a state.
+    pub fn szegedy_operator(
+        stochastic: &DMatrix<f64>,
+    ) -> (DMatrix<Complex<f64>>, Vec<(usize, usize)>) {
+        let n = stochastic.nrows().min(stochastic.ncols());
+        let mut chain =
+            stochastic
+                .view((0, 0), (n, n))
+                .map(|x| if x.is_finite() { x.max(0.0) } else { 0.0 });
+        for (j, mut column) in chain.column_iter_mut().enumerate() {
+            let sum = column.sum();
+            if sum > 0.0 {
+                column /= sum;
+            } else {
+                column[j] = 1.0;
+            }
+        }
+
+        let labels = (0..n)
+            .flat_map(|x| (0..n).map(move |y| (x, y)))
+            .filter(|&(x, y)| chain[(y, x)] > 0.0 || chain[(x, y)] > 0.0)
+            .collect::<Vec<_>>();
+        let index = labels
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (*l, i))
+            .collect::<HashMap<_, _>>();
+        let m = labels.len();
+
+        let psi = labels
+            .iter()
+            .map(|&(x, y)| chain[(y, x)].sqrt())
+            .collect::<Vec<_>>();
+        let reflection = DMatrix::from_fn(m, m, |i, j| {
+            let projection = if labels[i].0 == labels[j].0 {
+                2.0 * psi[i] * psi[j]
+            } else {
+                0.0
+            };
+            let identity = if i == j { 1.0 } else { 0.0 };
+            Complex::new(projection - identity, 0.0)
+        });
+
+        let mut swap = DMatrix::from_element(m, m, Complex::new(0.0, 0.0));
+        for (j, &(x, y)) in labels.iter().enumerate() {
+            swap[(index[&(y, x)], j)] = Complex::new(1.0, 0.0);
+        }
+
+        (swap * reflection, labels)
+    }
+
+    /// Eigenvalues and orthonormal eigenvectors, as columns, of the unitary
+    /// matrix. A unitary `U` is diagonalized by the Hermitian matrix
+    /// `H + aK`, where `H` and `K` are its commuting real and imaginary parts
+    /// and `a` is chosen so that distinct eigenvalues of `U` are unlikely to
+    /// coincide, which avoids relying on a Schur decomposition converging.
+    /// When two do coincide the vectors found are checked not to be
+    /// eigenvectors of `U`, and the Schur decomposition is used instead.
+    pub fn unitary_eigen(&self) -> Result<ComplexEigen> {
+        let matrix = self.get_complex_matrix();
+        let n = matrix.nrows();
+        let adjoint = matrix.adjoint();
+        if (&adjoint * matrix.as_ref() - DMatrix::identity(n, n)).norm() > 1e-8 {
+            return Err(anyhow!("The transition matrix is not unitary"));
+        }
+
+        let real = (matrix.as_ref() + &adjoint) * Complex::new(0.5, 0.0);
+        let imaginary = (matrix.as_ref() - &adjoint) * Complex::new(0.0, -0.5);
+        let mixing = Complex::new(std::f64::consts::FRAC_1_PI, 0.0);
+        let eigen = SymmetricEigen::new(real + imaginary * mixing);
+
+        if let Some(eigen) = Self::check_eigenvectors(matrix.as_ref(), eigen.eigenvectors) {
+            return Ok(eigen);
+        }
+
+        // the Schur form of a unitary matrix is diagonal, so its unitary
+        // factor holds the eigenvectors
+        let (eigenvectors, _) = Schur::try_new(matrix.clone().into_owned(), 1e-14, 10_000)
+            .ok_or_else(|| anyhow!("The eigenvalues of the transition matrix did not converge"))?
+            .unpack();
+        Self::check_eigenvectors(matrix.as_ref(), eigenvectors)
+            .ok_or_else(|| anyhow!("The eigenvectors of the transition matrix could not be found"))
+    }
+
+    /// The eigenvalues of `matrix` for each column of `eigenvectors`, if every
+    /// column is an eigenvector.
+    fn check_eigenvectors(
+        matrix: &DMatrix<Complex<f64>>,
+        eigenvectors: DMatrix<Complex<f64>>,
+    ) -> Option<ComplexEigen> {
+        let eigenvalues = DVector::from_iterator(
+            eigenvectors.ncols(),
+            eigenvectors
+                .column_iter()
+                .map(|v| (v.adjoint() * matrix * v)[0]),
+        );
+        eigenvectors
+            .column_iter()
+            .zip(eigenvalues.iter())
+            .all(|(v, eigenvalue)| (matrix * v - v * *eigenvalue).norm() <= MAX_EIGEN_RESIDUAL)
+            .then_some((eigenvalues, eigenvectors))
+    }
+
+    /// The infinite-time Cesàro average of the probability on each half-edge
+    /// for a walk starting from `initial_state`. With `U = sum λ P_λ` this is
+    /// `sum_λ |P_λ ψ|^2`, as the cross terms between distinct eigenvalues
+    /// average out.
+    pub fn time_averaged_probabilities(
+        &self,
+        initial_state: &DVector<Complex<f64>>,
+    ) -> Result<DVector<f64>> {
+        let (eigenvalues, eigenvectors) = self.unitary_eigen()?;
+
+        // group the eigenvectors into eigenspaces
+        let mut eigenspaces: Vec<(Complex<f64>, Vec<usize>)> = Vec::new();
+        for (i, eigenvalue) in eigenvalues.iter().enumerate() {
+            match eigenspaces
+                .iter_mut()
+                .find(|(x, _)| (x - eigenvalue).norm() < 1e-8)
+            {
+                Some((_, indexes)) => indexes.push(i),
+                None => eigenspaces.push((*eigenvalue, vec![i])),
+            }
+        }
+
+        let mut res = DVector::from_element(initial_state.len(), 0.0);
+        for (_, indexes) in eigenspaces {
+            let projection = indexes.iter().fold(
+                DVector::from_element(initial_state.len(), Complex::new(0.0, 0.0)),
+                |acc, &i| {
+                    let v = eigenvectors.column(i);
+                    acc + v * v.dotc(initial_state)
+                },
+            );
+            res += projection.map(|x| x.norm_sqr());
+        }
+        Ok(res)
+    }
+
+    /// The dense operator, built from the sparse form when only that is kept.
+    pub fn get_complex_matrix(&self) -> Cow<'_, DMatrix<Complex<f64>>> {
+        match &self.sparse {
+            Some(sparse) if self.matrix.is_empty() => Cow::Owned(sparse.to_dense()),
+            _ => Cow::Borrowed(&self.matrix),
+        }
     }
 
     pub fn get_initial_state(
@@ -69,7 +290,7 @@ impl ComplexTransitionMatrix {
         start_node_idx: Option<usize>,
         labels: &[(usize, usize)],
     ) -> DVector<Complex<f64>> {
-        let mut res = DVector::from_element(self.matrix.ncols(), Complex::new(0.0, 0.0));
+        let mut res = DVector::from_element(self.dimension(), Complex::new(0.0, 0.0));
         if res.is_empty() {
             return res;
         }
@@ -90,8 +311,41 @@ impl ComplexTransitionMatrix {
         res
     }
 
+    /// Evolves a density matrix by one step, `ρ -> UρU†`.
+    pub fn apply_to_density(
+        &self,
+        density_matrix: &DMatrix<Complex<f64>>,
+    ) -> DMatrix<Complex<f64>> {
+        match &self.sparse {
+            Some(sparse) => {
+                sparse.mul_matrix(&sparse.mul_matrix(&density_matrix.adjoint()).adjoint())
+            }
+            None => &self.matrix * density_matrix * self.matrix.adjoint(),
+        }
+    }
+
+    /// Steps two walkers at once, where entry `(a, b)` of `pair` is the
+    /// amplitude of the first walker on half-edge `a` and the second on `b`.
+    pub fn apply_to_pair(&self, pair: &DMatrix<Complex<f64>>) -> DMatrix<Complex<f64>> {
+        match &self.sparse {
+            Some(sparse) => sparse.mul_matrix(&sparse.mul_matrix(&pair.transpose()).transpose()),
+            None => &self.matrix * pair * self.matrix.transpose(),
+        }
+    }
+
+    /// Undoes [`Self::apply`], as the adjoint of a unitary is its inverse.
+    pub fn apply_adjoint(&self, state: &DVector<Complex<f64>>) -> DVector<Complex<f64>> {
+        match &self.sparse {
+            Some(sparse) => sparse.adjoint().mul_vector(state),
+            None => self.matrix.ad_mul(state),
+        }
+    }
+
     pub fn apply(&self, state: DVector<Complex<f64>>) -> DVector<Complex<f64>> {
-        &self.matrix * state
+        match &self.sparse {
+            Some(sparse) => sparse.mul_vector(&state),
+            None => &self.matrix * state,
+        }
     }
 
     pub fn normalize_unitary(&mut self) -> &TransitionMatrixCorrectionType {
@@ -126,16 +380,18 @@ impl ComplexTransitionMatrix {
         } else {
             self.last_normalization_correction = TransitionMatrixCorrectionType::None;
         }
+        self.update_sparse();
 
         &self.last_normalization_correction
     }
 
     fn display_matrix(&self, ui: &mut egui::Ui, labels: &[(usize, usize)]) {
-        if labels.len() != self.matrix.nrows() || labels.len() != self.matrix.ncols() {
+        let matrix = self.get_complex_matrix();
+        if labels.len() != matrix.nrows() || labels.len() != matrix.ncols() {
             panic!(
                 "Matrix dimensions do not match labels: labels:{} vs matrix:{}",
                 labels.len(),
-                self.matrix.nrows()
+                matrix.nrows()
             );
         }
 
@@ -155,10 +411,10 @@ impl ComplexTransitionMatrix {
                     for (i, l) in labels.iter().enumerate() {
                         ui.label(egui::RichText::new(format!("{}->{}", l.0, l.1)).strong());
                         for j in 0..labels.len() {
-                            if self.matrix[(i, j)].l1_norm() < 1e-3 {
+                            if matrix[(i, j)].l1_norm() < 1e-3 {
                                 ui.label("-");
                             } else {
-                                ui.label(format!("{:.03}", self.matrix[(i, j)]));
+                                ui.label(format!("{:.03}", matrix[(i, j)]));
                             }
                         }
                         ui.end_row();
@@ -266,4 +522,71 @@ mod tests {
             epsilon = 1e-10
         );
     }
+
+    #[test]
+    fn test_time_averaged_probabilities() {
+        // flipping between two half-edges spends half the time on each
+        let one = Complex::new(1.0, 0.0);
+        let zero = Complex::new(0.0, 0.0);
+        let swap =
+            ComplexTransitionMatrix::new(DMatrix::from_row_slice(2, 2, &[zero, one, one, zero]));
+        let initial_state = DVector::from_vec(vec![one, zero]);
+        assert_abs_diff_eq!(
+            swap.time_averaged_probabilities(&initial_state)
+                .unwrap()
+                .as_slice(),
+            [0.5, 0.5].as_slice(),
+            epsilon = 1e-12
+        );
+
+        // phases alone never move probability
+        let phases =
+            ComplexTransitionMatrix::new(DMatrix::from_diagonal(&DVector::from_vec(vec![
+                Complex::new(0.0, 1.0),
+                Complex::new(-1.0, 0.0),
+                one,
+            ])));
+        let initial_state = DVector::from_vec(vec![one * 0.6, one * 0.8, zero]);
+        assert_abs_diff_eq!(
+            phases
+                .time_averaged_probabilities(&initial_state)
+                .unwrap()
+                .as_slice(),
+            [0.36, 0.64, 0.0].as_slice(),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_unitary_eigen_with_coinciding_mixed_eigenvalues() {
+        // eigenphases summing to 2 atan(1/π) coincide in H + K/π, so the
+        // eigenvectors found there are mixed and have to be found again
+        let phases = [0.1, 2.0 * std::f64::consts::FRAC_1_PI.atan() - 0.1];
+        let (c, s) = (0.3_f64.cos(), 0.3_f64.sin());
+        let rotation = DMatrix::from_row_slice(2, 2, &[c, -s, s, c]).map(Complex::from);
+        let diagonal = DMatrix::from_diagonal(&DVector::from_iterator(
+            2,
+            phases.iter().map(|x| Complex::new(x.cos(), x.sin())),
+        ));
+        let matrix = &rotation * diagonal * rotation.adjoint();
+        let transition_matrix = ComplexTransitionMatrix::new(matrix.clone());
+
+        let (eigenvalues, eigenvectors) = transition_matrix.unitary_eigen().unwrap();
+        for (v, eigenvalue) in eigenvectors.column_iter().zip(eigenvalues.iter()) {
+            assert!((&matrix * v - v * *eigenvalue).norm() < 1e-8);
+        }
+        let mut found = eigenvalues.iter().map(|x| x.arg()).collect::<Vec<_>>();
+        found.sort_by(f64::total_cmp);
+        assert_abs_diff_eq!(found.as_slice(), phases.as_slice(), epsilon = 1e-9);
+
+        let initial_state = DVector::from_vec(vec![Complex::new(1.0, 0.0), Complex::new(0.0, 0.0)]);
+        assert_abs_diff_eq!(
+            transition_matrix
+                .time_averaged_probabilities(&initial_state)
+                .unwrap()
+                .as_slice(),
+            [c.powi(4) + s.powi(4), 2.0 * c * c * s * s].as_slice(),
+            epsilon = 1e-9
+        );
+    }
 }
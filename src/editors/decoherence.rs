@@ -0,0 +1,306 @@
+use anyhow::{anyhow, Result};
+use evalexpr::{eval_with_context, HashMapContext, Value};
+use nalgebra::{Complex, DMatrix};
+use strum::{Display, VariantArray};
+
+use super::ComplexMatrixEditor;
+
+/// The noise applied to a density matrix after each step of a quantum walk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, VariantArray)]
+pub enum DecoherenceChannel {
+    /// Measures which node the walker is at, removing coherence between nodes.
+    #[default]
+    #[strum(to_string = "Position Dephasing")]
+    PositionDephasing,
+    /// Replaces the coin at every node with the maximally mixed coin.
+    #[strum(to_string = "Coin Depolarisation")]
+    CoinDepolarisation,
+    /// A user supplied list of Kraus operators over every half-edge.
+    #[strum(to_string = "Kraus Operators")]
+    Kraus,
+}
+
+/// Decoherence applied at `rate` per step, as `ρ -> (1 - rate) ρ + rate Φ(ρ)`
+/// where `Φ` is the chosen channel. A rate of 1 applies the channel fully
+/// every step, which for position dephasing gives a classical walk.
+#[derive(Debug, Clone)]
+pub struct Decoherence {
+    pub enabled: bool,
+    pub channel: DecoherenceChannel,
+    pub rate: f64,
+    kraus_operators: Vec<DMatrix<Complex<f64>>>,
+    kraus_text: String,
+    kraus_error: Option<String>,
+}
+
+impl Default for Decoherence {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: DecoherenceChannel::default(),
+            rate: 0.1,
+            kraus_operators: Vec::new(),
+            kraus_text: String::new(),
+            kraus_error: None,
+        }
+    }
+}
+
+impl Decoherence {
+    pub fn new(channel: DecoherenceChannel, rate: f64) -> Self {
+        Self {
+            enabled: true,
+            channel,
+            rate: rate.clamp(0.0, 1.0),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the operators used by [`DecoherenceChannel::Kraus`], which must
+    /// satisfy `Σ K†K = I` so that no probability is lost.
+    pub fn set_kraus_operators(
+        &mut self,
+        kraus_operators: Vec<DMatrix<Complex<f64>>>,
+    ) -> Result<()> {
+        let Some(first) = kraus_operators.first() else {
+            return Err(anyhow!("No Kraus operators were given"));
+        };
+        let n = first.nrows();
+        if kraus_operators.iter().any(|k| k.shape() != (n, n)) {
+            return Err(anyhow!(
+                "Kraus operators must all be square and of the same size"
+            ));
+        }
+        let completeness = kraus_operators
+            .iter()
+            .fold(DMatrix::zeros(n, n), |acc, k| acc + k.adjoint() * k);
+        let error = (completeness - DMatrix::identity(n, n)).norm();
+        if error > 1e-6 {
+            return Err(anyhow!(
+                "Kraus operators do not preserve probability, |Σ K†K - I| = {:.3e}",
+                error
+            ));
+        }
+        self.kraus_operators = kraus_operators;
+        Ok(())
+    }
+
+    /// Reads Kraus operators separated by blank lines, with one row per line and
+    /// entries separated by whitespace. Each entry is `re` or `re,im` where
+    /// both parts may be expressions using `pi`, `e` and `tau`.
+    pub fn parse_kraus_operators(text: &str) -> Result<Vec<DMatrix<Complex<f64>>>> {
+        let context = ComplexMatrixEditor::get_math_constants();
+        let mut res = Vec::new();
+        for block in text.split("\n\n").filter(|x| !x.trim().is_empty()) {
+            let rows = block
+                .lines()
+                .filter(|x| !x.trim().is_empty())
+                .map(|line| {
+                    line.split_whitespace()
+                        .map(|entry| Self::parse_entry(entry, &context))
+                        .collect::<Result<Vec<_>>>()
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let n = rows.len();
+            if rows.iter().any(|row| row.len() != n) {
+                return Err(anyhow!("Kraus operator {} is not square", res.len()));
+            }
+            res.push(DMatrix::from_fn(n, n, |i, j| rows[i][j]));
+        }
+        Ok(res)
+    }
+
+    fn parse_entry(entry: &str, context: &HashMapContext) -> Result<Complex<f64>> {
+        let eval = |text: &str| match eval_with_context(text, context) {
+            Ok(Value::Int(num)) => Ok(num as f64),
+            Ok(Value::Float(num)) => Ok(num),
+            _ => Err(anyhow!("Could not read \"{}\" as a number", text)),
+        };
+        match entry.split_once(',') {
+            Some((re, im)) => Ok(Complex::new(eval(re)?, eval(im)?)),
+            None => Ok(Complex::new(eval(entry)?, 0.0)),
+        }
+    }
+
+    /// Applies one step of decoherence to `density_matrix`, whose rows and
+    /// columns are the half-edges in `labels`. Kraus operators of the wrong
+    /// size leave the state unchanged.
+    pub fn apply(
+        &self,
+        density_matrix: &DMatrix<Complex<f64>>,
+        labels: &[(usize, usize)],
+    ) -> DMatrix<Complex<f64>> {
+        if !self.enabled || self.rate == 0.0 || labels.len() != density_matrix.nrows() {
+            return density_matrix.clone();
+        }
+
+        let decohered = match self.channel {
+            DecoherenceChannel::PositionDephasing => {
+                DMatrix::from_fn(density_matrix.nrows(), density_matrix.ncols(), |i, j| {
+                    if labels[i].0 == labels[j].0 {
+                        density_matrix[(i, j)]
+                    } else {
+                        Complex::new(0.0, 0.0)
+                    }
+                })
+            }
+            DecoherenceChannel::CoinDepolarisation => {
+                Self::depolarise_coins(density_matrix, labels)
+            }
+            DecoherenceChannel::Kraus => {
+                if self
+                    .kraus_operators
+                    .first()
+                    .map_or(true, |k| k.shape() != density_matrix.shape())
+                {
+                    return density_matrix.clone();
+                }
+                self.kraus_operators.iter().fold(
+                    DMatrix::zeros(density_matrix.nrows(), density_matrix.ncols()),
+                    |acc, k| acc + k * density_matrix * k.adjoint(),
+                )
+            }
+        };
+
+        density_matrix * Complex::new(1.0 - self.rate, 0.0)
+            + decohered * Complex::new(self.rate, 0.0)
+    }
+
+    /// Depolarises the coin of every node independently. Each node's block
+    /// becomes its probability spread evenly over its half-edges, and the
+    /// coherence between two nodes survives only when neither has a coin to
+    /// depolarise.
+    fn depolarise_coins(
+        density_matrix: &DMatrix<Complex<f64>>,
+        labels: &[(usize, usize)],
+    ) -> DMatrix<Complex<f64>> {
+        let degree = |node: usize| labels.iter().filter(|l| l.0 == node).count();
+        let node_probability = |node: usize| {
+            (0..labels.len())
+                .filter(|i| labels[*i].0 == node)
+                .map(|i| density_matrix[(i, i)])
+                .sum::<Complex<f64>>()
+        };
+        DMatrix::from_fn(density_matrix.nrows(), density_matrix.ncols(), |i, j| {
+            let (a, b) = (labels[i].0, labels[j].0);
+            if a == b {
+                if i == j {
+                    node_probability(a) / degree(a) as f64
+                } else {
+                    Complex::new(0.0, 0.0)
+                }
+            } else if degree(a) == 1 && degree(b) == 1 {
+                density_matrix[(i, j)]
+            } else {
+                Complex::new(0.0, 0.0)
+            }
+        })
+    }
+
+    /// Shows the decoherence settings, returning whether any were changed.
+    /// `dimension` is the number of half-edges Kraus operators must act on.
+    pub fn show(&mut self, ui: &mut egui::Ui, dimension: usize) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.enabled, "Evolve as a density matrix")
+            .changed();
+        if !self.enabled {
+            return changed;
+        }
+
+        egui::ComboBox::from_label("Channel")
+            .selected_text(format!("{}", self.channel))
+            .show_ui(ui, |ui| {
+                for channel in DecoherenceChannel::VARIANTS {
+                    changed |= ui
+                        .selectable_value(&mut self.channel, *channel, format!("{}", channel))
+                        .changed();
+                }
+            });
+        changed |= ui
+            .add(egui::Slider::new(&mut self.rate, 0.0..=1.0).text("Rate per step"))
+            .changed();
+
+        if self.channel == DecoherenceChannel::Kraus {
+            ui.label(format!(
+                "Kraus operators ({0}x{0}, separated by blank lines, entries as re or re,im)",
+                dimension
+            ));
+            ui.text_edit_multiline(&mut self.kraus_text);
+            if ui.button("Apply Kraus Operators").clicked() {
+                let res = Self::parse_kraus_operators(&self.kraus_text).and_then(|operators| {
+                    match operators.first() {
+                        Some(k) if k.nrows() != dimension => Err(anyhow!(
+                            "Kraus operators are {0}x{0} but the walk has {1} half-edges",
+                            k.nrows(),
+                            dimension
+                        )),
+                        _ => self.set_kraus_operators(operators),
+                    }
+                });
+                self.kraus_error = res.err().map(|e| e.to_string());
+                changed = true;
+            }
+            if let Some(e) = &self.kraus_error {
+                ui.colored_label(egui::Color32::from_rgb(255, 50, 50), e);
+            }
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_channels_preserve_trace() {
+        // a star with centre 0 and leaves 1 and 2
+        let labels = [(0, 1), (0, 2), (1, 0), (2, 0)];
+        let state = DMatrix::from_fn(4, 1, |i, _| Complex::new(0.5, 0.1 * i as f64));
+        let state = &state / Complex::from(state.norm());
+        let density_matrix = &state * state.adjoint();
+
+        for channel in DecoherenceChannel::VARIANTS {
+            let mut decoherence = Decoherence::new(*channel, 1.0);
+            decoherence
+                .set_kraus_operators(vec![
+                    DMatrix::identity(4, 4) * Complex::new(0.6, 0.0),
+                    DMatrix::identity(4, 4) * Complex::new(0.0, 0.8),
+                ])
+                .unwrap();
+            let decohered = decoherence.apply(&density_matrix, &labels);
+            assert_abs_diff_eq!(decohered.trace().re, 1.0, epsilon = 1e-12);
+            assert!(decohered.trace().im.abs() < 1e-12);
+        }
+
+        let decohered = Decoherence::new(DecoherenceChannel::PositionDephasing, 1.0)
+            .apply(&density_matrix, &labels);
+        assert_eq!(decohered[(0, 2)], Complex::new(0.0, 0.0));
+        assert_eq!(decohered[(0, 1)], density_matrix[(0, 1)]);
+
+        // the leaves have no coin, so only the centre is depolarised
+        let decohered = Decoherence::new(DecoherenceChannel::CoinDepolarisation, 1.0)
+            .apply(&density_matrix, &labels);
+        assert_eq!(decohered[(0, 1)], Complex::new(0.0, 0.0));
+        assert_abs_diff_eq!(
+            decohered[(0, 0)].re,
+            (density_matrix[(0, 0)].re + density_matrix[(1, 1)].re) / 2.0,
+            epsilon = 1e-12
+        );
+        assert_eq!(decohered[(2, 3)], density_matrix[(2, 3)]);
+    }
+
+    #[test]
+    fn test_parse_kraus_operators() {
+        let operators = Decoherence::parse_kraus_operators("0 1\n1 0\n\n0,1 0\n0 0,-1\n").unwrap();
+        assert_eq!(operators.len(), 2);
+        assert_eq!(operators[0][(0, 1)], Complex::new(1.0, 0.0));
+        assert_eq!(operators[1][(1, 1)], Complex::new(0.0, -1.0));
+
+        let mut decoherence = Decoherence::default();
+        assert!(decoherence.set_kraus_operators(operators).is_err());
+        assert!(Decoherence::parse_kraus_operators("1 0\n0").is_err());
+    }
+}
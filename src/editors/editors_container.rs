@@ -1,29 +1,50 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
 use anyhow::Result;
-use nalgebra::DVector;
+use nalgebra::{Complex, DMatrix, DVector};
 
 use crate::{
     editors::{
         matrix_editor::MatrixEditor, state_manager::StateManager, ClassicalMatrixEditor,
-        ClassicalStateManager, ComplexMatrixEditor, ComplexStateManager, Editor,
+        ClassicalStateManager, ComparisonView, ComplexMatrixEditor, ComplexStateManager, Editor,
+        HamiltonianEditor, HittingTimeAnalysis, InitialStateEditor, ParameterSweepView,
+        ProbabilityPlot, RateMatrixEditor, RunUntil, SpectrumViewer, StationaryAnalysis,
+        SzegedyEditor,
     },
-    options::{Mode, Options},
+    options::{DistributionView, Mode, Options},
 };
 
 #[derive(Debug, Default)]
 pub struct EditorsContainer {
     matrix_editor: MatrixEditor,
     state_manager: StateManager,
+    /// The last stationary analysis and the matrix it was computed from, as
+    /// estimating the mixing time is too slow to repeat every frame.
+    stationary_analysis: Option<(DMatrix<f64>, Result<StationaryAnalysis, String>)>,
+    spectrum_viewer: SpectrumViewer,
+    initial_state_editor: InitialStateEditor,
+    run_steps: usize,
+    run_until: RunUntil,
+    /// Why the last run stopped and at which step.
+    run_summary: Option<String>,
+    probability_plot: ProbabilityPlot,
+    comparison_view: ComparisonView,
+    parameter_sweep_view: ParameterSweepView,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 struct SerializedEditorsContainer {
     matrix_editor: MatrixEditor,
+    #[serde(default)]
+    initial_state_editor: InitialStateEditor,
 }
 
 impl From<&EditorsContainer> for SerializedEditorsContainer {
     fn from(editors_container: &EditorsContainer) -> Self {
         Self {
             matrix_editor: editors_container.matrix_editor.clone(),
+            initial_state_editor: editors_container.initial_state_editor.clone(),
         }
     }
 }
@@ -32,6 +53,7 @@ impl From<SerializedEditorsContainer> for EditorsContainer {
     fn from(serialized_editors_container: SerializedEditorsContainer) -> Self {
         Self {
             matrix_editor: serialized_editors_container.matrix_editor,
+            initial_state_editor: serialized_editors_container.initial_state_editor,
             ..Default::default()
         }
     }
@@ -59,7 +81,12 @@ impl<'de> serde::Deserialize<'de> for EditorsContainer {
 
 // TODO reduce the number of calls made during show editors
 impl EditorsContainer {
-    pub fn show_classical_editors(&mut self, ui: &mut egui::Ui, node_count: usize) {
+    pub fn show_classical_editors(
+        &mut self,
+        ui: &mut egui::Ui,
+        options: &Options,
+        node_count: usize,
+    ) {
         if !self.matrix_editor.is_classical() {
             self.matrix_editor = MatrixEditor::Classical(ClassicalMatrixEditor::new(node_count));
         }
@@ -70,6 +97,41 @@ impl EditorsContainer {
             }
             cme.show(ui);
 
+            ui.collapsing(
+                "Hitting Time Analysis",
+                |ui| match HittingTimeAnalysis::new(
+                    &cme.matrix,
+                    options.generic.start_node_idx,
+                    &options.specific.classical.target_node_indexes,
+                ) {
+                    Ok(analysis) => analysis.show(ui),
+                    Err(e) => {
+                        ui.label(e.to_string());
+                    }
+                },
+            );
+
+            let is_outdated = !matches!(
+                &self.stationary_analysis,
+                Some((matrix, _)) if *matrix == cme.matrix
+            );
+            if is_outdated {
+                self.stationary_analysis = Some((
+                    cme.matrix.clone(),
+                    StationaryAnalysis::new(&cme.matrix).map_err(|e| e.to_string()),
+                ));
+            }
+            ui.collapsing("Stationary Distribution Analysis", |ui| {
+                match &self.stationary_analysis {
+                    Some((_, Ok(analysis))) => analysis.show(ui),
+                    Some((_, Err(e))) => {
+                        ui.label(e);
+                    }
+                    None => (),
+                }
+            });
+            ui.separator();
+
             if let StateManager::Classical(csm) = &mut self.state_manager {
                 csm.set_transition_matrix_from(&cme.matrix);
             } else {
@@ -78,6 +140,60 @@ impl EditorsContainer {
                     Err(e) => eprintln!("Error converting matrix to state manager: {}", e),
                 }
             }
+
+            Self::show_classical_initial_state(
+                ui,
+                &mut self.initial_state_editor,
+                &mut self.state_manager,
+                &cme.matrix,
+            );
+            Self::show_classical_spectrum(
+                ui,
+                &mut self.spectrum_viewer,
+                &mut self.state_manager,
+                &cme.matrix,
+            );
+        }
+
+        self.show_state_details(ui);
+        self.show_state_buttons(ui);
+    }
+
+    pub fn show_continuous_classical_editors(&mut self, ui: &mut egui::Ui, node_count: usize) {
+        if !self.matrix_editor.is_rate_matrix() {
+            self.matrix_editor = MatrixEditor::RateMatrix(RateMatrixEditor::new(node_count));
+        }
+
+        if let MatrixEditor::RateMatrix(rme) = &mut self.matrix_editor {
+            if rme.rates.matrix.nrows() < node_count {
+                rme.rates.resize_matrix(node_count);
+            }
+            rme.show(ui);
+
+            if let StateManager::Classical(csm) = &mut self.state_manager {
+                if rme.is_matrix_update_ready() {
+                    csm.set_transition_matrix_from(&rme.matrix);
+                    rme.on_matrix_updated();
+                }
+            } else {
+                match ClassicalStateManager::try_from(&rme.matrix) {
+                    Ok(csm) => self.state_manager = StateManager::Classical(csm.into()),
+                    Err(e) => eprintln!("Error converting matrix to state manager: {}", e),
+                }
+            }
+
+            Self::show_classical_initial_state(
+                ui,
+                &mut self.initial_state_editor,
+                &mut self.state_manager,
+                &rme.matrix,
+            );
+            Self::show_classical_spectrum(
+                ui,
+                &mut self.spectrum_viewer,
+                &mut self.state_manager,
+                &rme.matrix,
+            );
         }
 
         self.show_state_details(ui);
@@ -88,10 +204,11 @@ impl EditorsContainer {
         &mut self,
         ui: &mut egui::Ui,
         options: &Options,
-        edges: &[(usize, usize)],
+        node_count: usize,
+        arcs: &[(usize, usize)],
     ) {
         if !self.matrix_editor.is_complex() {
-            self.matrix_editor = MatrixEditor::Complex(ComplexMatrixEditor::new(edges));
+            self.matrix_editor = MatrixEditor::Complex(ComplexMatrixEditor::new(arcs));
         }
 
         let MatrixEditor::Complex(cme) = &mut self.matrix_editor else {
@@ -117,13 +234,294 @@ impl EditorsContainer {
             }
         }
 
+        Self::show_complex_initial_state(
+            ui,
+            &mut self.initial_state_editor,
+            &mut self.state_manager,
+            cme.get_labels(),
+        );
         self.state_manager
             .show(ui, options, cme.get_adjacency_list(), cme.get_labels());
+        Self::show_complex_spectrum(
+            ui,
+            &mut self.spectrum_viewer,
+            &mut self.state_manager,
+            cme.get_adjacency_list(),
+            cme.get_labels(),
+        );
+        ui.collapsing("Parameter Sweep", |ui| {
+            self.parameter_sweep_view.show(
+                ui,
+                cme,
+                node_count,
+                options.generic.start_node_idx,
+                &options.specific.quantum.target_node_indexes,
+            );
+        });
 
         self.show_state_details(ui);
         self.show_state_buttons(ui);
     }
 
+    pub fn show_continuous_quantum_editors(
+        &mut self,
+        ui: &mut egui::Ui,
+        options: &Options,
+        node_count: usize,
+        arcs: &[(usize, usize, f64)],
+    ) {
+        if !self.matrix_editor.is_hamiltonian() {
+            self.matrix_editor =
+                MatrixEditor::Hamiltonian(HamiltonianEditor::new(node_count, arcs));
+        }
+
+        let MatrixEditor::Hamiltonian(he) = &mut self.matrix_editor else {
+            panic!();
+        };
+
+        he.show(ui);
+
+        match &mut self.state_manager {
+            StateManager::Complex(ref mut csm) => {
+                csm.make_transition_matrix_compatible(he.get_combined_matrix())
+            }
+            _ => {
+                self.state_manager = StateManager::Complex(
+                    ComplexStateManager::new(
+                        he.get_combined_matrix(),
+                        he.get_labels(),
+                        options.generic.start_node_idx,
+                        options
+                            .specific
+                            .continuous_quantum
+                            .target_node_indexes
+                            .clone(),
+                    )
+                    .into(),
+                )
+            }
+        }
+
+        Self::show_complex_initial_state(
+            ui,
+            &mut self.initial_state_editor,
+            &mut self.state_manager,
+            he.get_labels(),
+        );
+        self.state_manager
+            .show(ui, options, he.get_adjacency_list(), he.get_labels());
+        Self::show_complex_spectrum(
+            ui,
+            &mut self.spectrum_viewer,
+            &mut self.state_manager,
+            he.get_adjacency_list(),
+            he.get_labels(),
+        );
+
+        self.show_state_details(ui);
+        self.show_state_buttons(ui);
+    }
+
+    pub fn show_szegedy_editors(
+        &mut self,
+        ui: &mut egui::Ui,
+        options: &Options,
+        node_count: usize,
+    ) {
+        if !self.matrix_editor.is_szegedy() {
+            self.matrix_editor = MatrixEditor::Szegedy(SzegedyEditor::new(node_count));
+        }
+
+        let MatrixEditor::Szegedy(se) = &mut self.matrix_editor else {
+            panic!();
+        };
+
+        if se.classical.matrix.nrows() < node_count {
+            se.classical.resize_matrix(node_count);
+        }
+        se.show(ui);
+
+        match &mut self.state_manager {
+            StateManager::Complex(ref mut csm) => {
+                if se.is_matrix_update_ready() {
+                    csm.set_transition_matrix_from(se.get_combined_matrix());
+                    csm.set_labels(se.get_labels());
+                    csm.reset_state(se.get_labels());
+                    se.on_matrix_updated();
+                }
+            }
+            _ => {
+                self.state_manager = StateManager::Complex(
+                    ComplexStateManager::new(
+                        se.get_combined_matrix(),
+                        se.get_labels(),
+                        options.generic.start_node_idx,
+                        options.specific.szegedy.target_node_indexes.clone(),
+                    )
+                    .into(),
+                );
+                se.on_matrix_updated();
+            }
+        }
+
+        Self::show_complex_initial_state(
+            ui,
+            &mut self.initial_state_editor,
+            &mut self.state_manager,
+            se.get_labels(),
+        );
+        self.state_manager
+            .show(ui, options, se.get_adjacency_list(), se.get_labels());
+        Self::show_complex_spectrum(
+            ui,
+            &mut self.spectrum_viewer,
+            &mut self.state_manager,
+            se.get_adjacency_list(),
+            se.get_labels(),
+        );
+
+        self.show_state_details(ui);
+        self.show_state_buttons(ui);
+    }
+
+    /// Runs a classical and a quantum walk on the canvas graph side by side,
+    /// rebuilding both whenever the graph, start node or targets change.
+    pub fn show_comparison_editors(
+        &mut self,
+        ui: &mut egui::Ui,
+        options: &Options,
+        node_count: usize,
+        arcs: &[(usize, usize, f64)],
+    ) {
+        self.comparison_view.sync(
+            node_count,
+            arcs,
+            options.generic.start_node_idx,
+            &options.specific.comparison.target_node_indexes,
+        );
+        self.comparison_view.show(ui);
+    }
+
+    /// Shows the initial distribution of a classical chain, starting the walk
+    /// from it whenever it is enabled and not already in use.
+    fn show_classical_initial_state(
+        ui: &mut egui::Ui,
+        initial_state_editor: &mut InitialStateEditor,
+        state_manager: &mut StateManager,
+        matrix: &DMatrix<f64>,
+    ) {
+        let changed = ui
+            .collapsing("Initial State", |ui| {
+                initial_state_editor.show_weights(ui, matrix.nrows())
+            })
+            .body_returned
+            .unwrap_or(false);
+        let StateManager::Classical(csm) = state_manager else {
+            return;
+        };
+        if !initial_state_editor.enabled {
+            if changed {
+                csm.clear_initial_distribution(matrix);
+            }
+        } else if changed || !csm.has_initial_distribution() {
+            if let Some(distribution) = initial_state_editor.read_distribution(matrix.nrows()) {
+                csm.set_initial_distribution(distribution, matrix);
+            }
+        }
+    }
+
+    /// Shows the initial amplitudes of a quantum walk, starting the walk from
+    /// them whenever they are enabled and not already in use.
+    fn show_complex_initial_state(
+        ui: &mut egui::Ui,
+        initial_state_editor: &mut InitialStateEditor,
+        state_manager: &mut StateManager,
+        labels: &[(usize, usize)],
+    ) {
+        let changed = ui
+            .collapsing("Initial State", |ui| {
+                initial_state_editor.show_amplitudes(ui, labels)
+            })
+            .body_returned
+            .unwrap_or(false);
+        let StateManager::Complex(csm) = state_manager else {
+            return;
+        };
+        if !initial_state_editor.enabled {
+            if changed {
+                csm.clear_initial_state();
+            }
+        } else if changed || !csm.has_initial_state() {
+            if let Some(amplitudes) = initial_state_editor.read_amplitudes(labels) {
+                csm.set_initial_state(amplitudes);
+            }
+        }
+    }
+
+    /// Shows the spectrum of a classical chain, where picking an eigenvector
+    /// starts the walk from its normalized absolute values.
+    fn show_classical_spectrum(
+        ui: &mut egui::Ui,
+        spectrum_viewer: &mut SpectrumViewer,
+        state_manager: &mut StateManager,
+        matrix: &DMatrix<f64>,
+    ) {
+        let labels = (0..matrix.nrows())
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        let node_weights = |v: &DVector<Complex<f64>>| {
+            let weights = v.map(|x| x.norm());
+            &weights / weights.sum()
+        };
+
+        // only decomposed while the panel is open, as the matrix can change
+        // every step
+        let chosen = ui
+            .collapsing("Spectrum", |ui| {
+                spectrum_viewer.update_classical(matrix);
+                spectrum_viewer.show(ui, &labels, node_weights)
+            })
+            .body_returned
+            .flatten();
+        if let (Some(eigenvector), StateManager::Classical(csm)) = (chosen, state_manager) {
+            csm.set_initial_distribution(node_weights(&eigenvector), matrix);
+        }
+    }
+
+    /// Shows the spectrum of a quantum walk's normalized transition matrix,
+    /// where picking an eigenvector starts the walk in that state.
+    fn show_complex_spectrum(
+        ui: &mut egui::Ui,
+        spectrum_viewer: &mut SpectrumViewer,
+        state_manager: &mut StateManager,
+        adjacency_list: &HashMap<usize, Vec<usize>>,
+        labels: &[(usize, usize)],
+    ) {
+        let StateManager::Complex(csm) = state_manager else {
+            return;
+        };
+        let labels = labels
+            .iter()
+            .map(|(a, b)| format!("{}->{}", a, b))
+            .collect::<Vec<_>>();
+        let node_weights = |v: &DVector<Complex<f64>>| {
+            ComplexStateManager::collapse_to_nodes(&v.map(|x| x.norm_sqr()), adjacency_list)
+        };
+
+        // only decomposed while the panel is open, as a time dependent coin
+        // changes the operator every step
+        let chosen = ui
+            .collapsing("Spectrum", |ui| {
+                spectrum_viewer.update_unitary(csm.get_transition_matrix());
+                spectrum_viewer.show(ui, &labels, node_weights)
+            })
+            .body_returned
+            .flatten();
+        if let Some(eigenvector) = chosen {
+            csm.set_initial_state(eigenvector);
+        }
+    }
+
     fn show_state_details(&self, ui: &mut egui::Ui) {
         match &self.state_manager {
             StateManager::None => (),
@@ -141,12 +539,114 @@ impl EditorsContainer {
             if ui.button("Step").clicked() {
                 if let Err(e) = self.step_state_forward() {
                     eprintln!("Error stepping state forward, this normally happens when a user clicks Step without deselecting the matrix editor: {}", e);
+                    self.run_summary = Some(e.to_string());
+                }
+            }
+            if ui.button("Step Back").clicked() {
+                if let Err(e) = self.step_state_back() {
+                    self.run_summary = Some(e.to_string());
                 }
             }
             if ui.button("Reset").clicked() {
                 self.reset_state();
+                self.run_summary = None;
             }
         });
+
+        if let Some(range) = self.get_history_range() {
+            let mut step = self.get_step();
+            let response = ui.add(egui::Slider::new(&mut step, range).text("Timeline"));
+            if response.changed() && step != self.get_step() {
+                if let Err(e) = self.go_to_step(step) {
+                    self.run_summary = Some(e.to_string());
+                }
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Run").clicked() {
+                self.run(&RunUntil::steps(self.run_steps.max(1)));
+            }
+            ui.add(egui::DragValue::new(&mut self.run_steps).range(1..=100_000));
+            ui.label("steps");
+        });
+        let node_count = self.get_progress().map_or(0, |(p, _)| p.len());
+        let is_run_requested = ui
+            .collapsing("Run Until", |ui| self.run_until.show(ui, node_count))
+            .body_returned
+            .unwrap_or(false);
+        if is_run_requested {
+            self.run(&self.run_until.clone());
+        }
+        if let Some(summary) = &self.run_summary {
+            ui.label(summary);
+        }
+
+        // also catches resets made by the editors
+        self.record_progress();
+        let node_count = self.get_progress().map_or(0, |(p, _)| p.len());
+        let step = self.get_step();
+        ui.collapsing("Probability Over Time", |ui| {
+            self.probability_plot.show(ui, node_count, step);
+        });
+    }
+
+    fn record_progress(&mut self) {
+        if let Some((probabilities, target_accumulation)) = self.get_progress() {
+            self.probability_plot
+                .record(self.get_step(), probabilities, target_accumulation);
+        }
+    }
+
+    /// Steps the walk until `run_until` stops it, recording why.
+    fn run(&mut self, run_until: &RunUntil) {
+        let Some((initial, _)) = self.get_progress() else {
+            return;
+        };
+        let res = run_until.run(initial, || {
+            self.step_state_forward()?;
+            self.get_progress()
+                .ok_or_else(|| anyhow::anyhow!("No state manager found"))
+        });
+        self.run_summary = Some(match res {
+            Ok((reason, steps)) => format!(
+                "Stopped at step {} after {} steps: {}",
+                self.get_step(),
+                steps,
+                reason
+            ),
+            Err(e) => format!("Stopped at step {}: {}", self.get_step(), e),
+        });
+    }
+
+    /// The node probabilities and target accumulation of the current state.
+    fn get_progress(&self) -> Option<(DVector<f64>, DVector<f64>)> {
+        match &self.state_manager {
+            StateManager::Classical(csm) => {
+                Some((csm.get_state_data(), csm.get_target_accumulation()))
+            }
+            StateManager::Complex(csm) => {
+                let adjacency_list = match &self.matrix_editor {
+                    MatrixEditor::Complex(cme) => cme.get_adjacency_list(),
+                    MatrixEditor::Hamiltonian(he) => he.get_adjacency_list(),
+                    MatrixEditor::Szegedy(se) => se.get_adjacency_list(),
+                    _ => return None,
+                };
+                Some((
+                    csm.get_node_probabilities(adjacency_list),
+                    csm.get_target_accumulation(),
+                ))
+            }
+            StateManager::None => None,
+        }
+    }
+
+    fn get_step(&self) -> usize {
+        match &self.state_manager {
+            StateManager::Classical(csm) => csm.get_step(),
+            StateManager::Complex(csm) => csm.get_step(),
+            StateManager::None => 0,
+        }
     }
 
     pub fn get_matrix_editor(&self) -> &MatrixEditor {
@@ -157,38 +657,122 @@ impl EditorsContainer {
         &mut self.matrix_editor
     }
 
+    pub fn get_initial_state_editor(&self) -> &InitialStateEditor {
+        &self.initial_state_editor
+    }
+
     pub fn remove_nodes(&mut self, node_indexes: Vec<usize>) {
         match &mut self.matrix_editor {
             MatrixEditor::Classical(matrix_editor) => matrix_editor.remove_node(node_indexes),
-            MatrixEditor::Complex(_) => self.matrix_editor = MatrixEditor::None,
+            MatrixEditor::RateMatrix(rme) => {
+                rme.rates.remove_node(node_indexes);
+                rme.refresh();
+            }
+            MatrixEditor::Szegedy(se) => {
+                se.classical.remove_node(node_indexes);
+                se.refresh();
+            }
+            MatrixEditor::Complex(_) | MatrixEditor::Hamiltonian(_) => {
+                self.matrix_editor = MatrixEditor::None
+            }
             _ => (),
         }
     }
 
     pub fn step_state_forward(&mut self) -> Result<()> {
+        self.update_time_dependent_matrix(self.get_step())?;
         match &mut self.state_manager {
-            StateManager::Classical(csm) => csm.step_forward(),
-            StateManager::Complex(csm) => {
-                csm.step_forward();
-                Ok(())
+            StateManager::Classical(csm) => csm.step_forward()?,
+            StateManager::Complex(csm) => csm.step_forward(),
+            StateManager::None => return Err(anyhow::anyhow!("No state manager found")),
+        }
+        self.record_progress();
+        Ok(())
+    }
+
+    /// Returns to the previous step, from the history or, for a quantum walk
+    /// that has stayed unitary, by undoing the step.
+    pub fn step_state_back(&mut self) -> Result<()> {
+        if let Some(step) = self.get_step().checked_sub(1) {
+            self.update_time_dependent_matrix(step)?;
+        }
+        match &mut self.state_manager {
+            StateManager::Classical(csm) => csm.step_back(),
+            StateManager::Complex(csm) => csm.step_back(),
+            StateManager::None => Err(anyhow::anyhow!("No state manager found")),
+        }
+    }
+
+    /// Evaluates coins that depend on the step at step `t`, so that the walk
+    /// from `t` uses them.
+    fn update_time_dependent_matrix(&mut self, t: usize) -> Result<()> {
+        if let (MatrixEditor::Complex(cme), StateManager::Complex(csm)) =
+            (&self.matrix_editor, &mut self.state_manager)
+        {
+            if cme.is_time_dependent() {
+                csm.set_transition_matrix_from(&cme.get_combined_matrix_at(t)?);
             }
+        }
+        Ok(())
+    }
+
+    pub fn go_to_step(&mut self, step: usize) -> Result<()> {
+        match &mut self.state_manager {
+            StateManager::Classical(csm) => csm.go_to_step(step),
+            StateManager::Complex(csm) => csm.go_to_step(step),
             StateManager::None => Err(anyhow::anyhow!("No state manager found")),
         }
     }
 
+    fn get_history_range(&self) -> Option<RangeInclusive<usize>> {
+        match &self.state_manager {
+            StateManager::Classical(csm) => csm.get_history_range(),
+            StateManager::Complex(csm) => csm.get_history_range(),
+            StateManager::None => None,
+        }
+    }
+
     pub fn clear_all(&mut self) {
         self.matrix_editor = MatrixEditor::None;
         self.state_manager = StateManager::None;
     }
 
-    pub(crate) fn get_state_data(&mut self, edges: &[(usize, usize)]) -> Option<DVector<f64>> {
+    /// The node probabilities drawn on the canvas, where `view` picks which
+    /// distribution of a quantum walk is shown.
+    pub(crate) fn get_state_data(
+        &mut self,
+        arcs: &[(usize, usize)],
+        view: DistributionView,
+    ) -> Option<DVector<f64>> {
+        if let Some(weights) = &self.spectrum_viewer.canvas_weights {
+            return Some(weights.clone());
+        }
+
         match &mut self.state_manager {
             StateManager::Classical(csm) => Some(csm.get_state_data()),
             StateManager::Complex(csm) => {
+                if let MatrixEditor::Hamiltonian(he) = &mut self.matrix_editor {
+                    if he.is_canvas_update_ready() {
+                        csm.set_transition_matrix_from(he.get_combined_matrix());
+                        he.on_canvas_updated();
+                    }
+                    return Some(csm.get_view_data(view, he.get_adjacency_list()));
+                }
+                if let MatrixEditor::Szegedy(se) = &mut self.matrix_editor {
+                    se.refresh();
+                    if se.is_matrix_update_ready() {
+                        csm.set_transition_matrix_from(se.get_combined_matrix());
+                        csm.set_labels(se.get_labels());
+                        csm.reset_state(se.get_labels());
+                        se.on_matrix_updated();
+                    }
+                    return Some(csm.get_view_data(view, se.get_adjacency_list()));
+                }
+
                 let cme = match self.matrix_editor {
                     MatrixEditor::Complex(ref mut cme) => cme,
                     _ => {
-                        self.matrix_editor = MatrixEditor::Complex(ComplexMatrixEditor::new(edges));
+                        self.matrix_editor = MatrixEditor::Complex(ComplexMatrixEditor::new(arcs));
                         let MatrixEditor::Complex(cme) = &mut self.matrix_editor else {
                             panic!();
                         };
@@ -200,50 +784,122 @@ impl EditorsContainer {
                     csm.set_transition_matrix_from(cme.get_combined_matrix());
                 }
 
-                Some(csm.get_state_data(cme.get_adjacency_list()))
+                Some(csm.get_view_data(view, cme.get_adjacency_list()))
             }
             _ => None,
         }
     }
 
+    /// The classical and quantum node probabilities drawn on the canvas when
+    /// comparing the two walks.
+    pub(crate) fn get_comparison_data(
+        &self,
+        options: &Options,
+    ) -> Option<(DVector<f64>, DVector<f64>)> {
+        match options.mode {
+            Mode::Comparison => self.comparison_view.get_state_data(),
+            _ => None,
+        }
+    }
+
+    /// Data drawn on the canvas alongside the state, currently the stationary
+    /// distribution of a classical chain when the overlay is enabled.
+    pub(crate) fn get_overlay_data(&self, options: &Options) -> Option<DVector<f64>> {
+        if options.mode != Mode::Classical || !options.specific.classical.show_stationary_overlay {
+            return None;
+        }
+        match &self.stationary_analysis {
+            Some((_, Ok(analysis))) => analysis.stationary.clone(),
+            _ => None,
+        }
+    }
+
     pub(crate) fn reset_state(&mut self) {
         match &mut self.state_manager {
-            StateManager::Classical(csm) => {
-                if let MatrixEditor::Classical(cme) = &self.matrix_editor {
-                    csm.reset_state(&cme.matrix);
-                } else {
-                    panic!("State manager is classical but matrix editor is not");
-                }
-            }
-            StateManager::Complex(csm) => {
-                if let MatrixEditor::Complex(cme) = &self.matrix_editor {
-                    csm.reset_state(cme.get_labels());
-                } else {
-                    csm.reset_state(&[]);
-                }
-            }
+            StateManager::Classical(csm) => match &self.matrix_editor {
+                MatrixEditor::Classical(cme) => csm.reset_state(&cme.matrix),
+                MatrixEditor::RateMatrix(rme) => csm.reset_state(&rme.matrix),
+                _ => panic!("State manager is classical but matrix editor is not"),
+            },
+            StateManager::Complex(csm) => match &self.matrix_editor {
+                MatrixEditor::Complex(cme) => csm.reset_state(cme.get_labels()),
+                MatrixEditor::Hamiltonian(he) => csm.reset_state(he.get_labels()),
+                MatrixEditor::Szegedy(se) => csm.reset_state(se.get_labels()),
+                _ => csm.reset_state(&[]),
+            },
             StateManager::None => (),
         }
     }
 
-    pub(crate) fn update_editor_from_edges(&mut self, edges: &[(usize, usize)]) {
-        if let MatrixEditor::Classical(me) = &mut self.matrix_editor {
-            me.update_from_canvas_edges(edges);
+    pub(crate) fn update_editor_from_edges(
+        &mut self,
+        arcs: &[(usize, usize, f64)],
+        node_count: usize,
+    ) {
+        match &mut self.matrix_editor {
+            MatrixEditor::Classical(me) => me.update_from_canvas_edges(arcs),
+            MatrixEditor::RateMatrix(rme) => {
+                rme.rates.update_from_canvas_edges(arcs);
+                rme.refresh();
+            }
+            MatrixEditor::Hamiltonian(he) => he.update_from_canvas(node_count, arcs),
+            MatrixEditor::Szegedy(se) => {
+                se.classical.update_from_canvas_edges(arcs);
+                se.refresh();
+            }
+            _ => (),
         }
     }
 
     pub(crate) fn sync_editors(
         &mut self,
         options: &Options,
-        edges: &[(usize, usize)],
+        arcs: &[(usize, usize, f64)],
         node_count: usize,
     ) {
+        if options.mode_change_data.is_some() {
+            self.spectrum_viewer = SpectrumViewer::default();
+        }
+
         // guarantees that if state was just changed, the matrix editor will be updated
         match (options.mode, options.mode_change_data) {
             (Mode::Edit, Some((_, Mode::Edit))) => {
                 self.matrix_editor = MatrixEditor::None;
                 self.state_manager = StateManager::None;
             }
+            (Mode::Comparison, Some((_, Mode::Comparison))) => {
+                self.matrix_editor = MatrixEditor::None;
+                self.state_manager = StateManager::None;
+                self.comparison_view = ComparisonView::default();
+            }
+            (Mode::Classical, Some((Mode::Szegedy, Mode::Classical))) => {
+                // keep the chain that was being quantized
+                let me = match std::mem::take(&mut self.matrix_editor) {
+                    MatrixEditor::Szegedy(se) => se.classical,
+                    _ => ClassicalMatrixEditor::new(node_count),
+                };
+                self.state_manager = StateManager::Classical(
+                    ClassicalStateManager::try_from(&me.matrix).unwrap().into(),
+                );
+                self.matrix_editor = MatrixEditor::Classical(me);
+            }
+            (Mode::Szegedy, Some((_, Mode::Szegedy))) => {
+                let se = match std::mem::take(&mut self.matrix_editor) {
+                    MatrixEditor::Classical(cme) => SzegedyEditor::from(cme),
+                    MatrixEditor::Szegedy(se) => se,
+                    _ => SzegedyEditor::new(node_count),
+                };
+                self.state_manager = StateManager::Complex(
+                    ComplexStateManager::new(
+                        se.get_combined_matrix(),
+                        se.get_labels(),
+                        options.generic.start_node_idx,
+                        options.specific.szegedy.target_node_indexes.clone(),
+                    )
+                    .into(),
+                );
+                self.matrix_editor = MatrixEditor::Szegedy(se);
+            }
             (Mode::Classical, Some((_, Mode::Classical))) => {
                 let me = ClassicalMatrixEditor::new(node_count);
                 self.state_manager = StateManager::Classical(
@@ -252,8 +908,17 @@ impl EditorsContainer {
                 self.matrix_editor =
                     MatrixEditor::Classical(ClassicalMatrixEditor::new(node_count));
             }
+            (Mode::ContinuousClassical, Some((_, Mode::ContinuousClassical))) => {
+                let rme = RateMatrixEditor::new(node_count);
+                self.state_manager = StateManager::Classical(
+                    ClassicalStateManager::try_from(&rme.matrix).unwrap().into(),
+                );
+                self.matrix_editor = MatrixEditor::RateMatrix(rme);
+            }
             (Mode::Quantum, Some((_, Mode::Quantum))) => {
-                let cme = ComplexMatrixEditor::new(edges);
+                let cme = ComplexMatrixEditor::new(
+                    &arcs.iter().map(|&(a, b, _)| (a, b)).collect::<Vec<_>>(),
+                );
                 self.state_manager = StateManager::Complex(
                     ComplexStateManager::new(
                         cme.get_combined_matrix(),
@@ -265,23 +930,72 @@ impl EditorsContainer {
                 );
                 self.matrix_editor = MatrixEditor::Complex(cme);
             }
+            (Mode::ContinuousQuantum, Some((_, Mode::ContinuousQuantum))) => {
+                let he = HamiltonianEditor::new(node_count, arcs);
+                self.state_manager = StateManager::Complex(
+                    ComplexStateManager::new(
+                        he.get_combined_matrix(),
+                        he.get_labels(),
+                        options.generic.start_node_idx,
+                        options
+                            .specific
+                            .continuous_quantum
+                            .target_node_indexes
+                            .clone(),
+                    )
+                    .into(),
+                );
+                self.matrix_editor = MatrixEditor::Hamiltonian(he);
+            }
             _ => (),
         };
 
         match (&mut self.matrix_editor, &mut self.state_manager) {
             (MatrixEditor::Classical(me), StateManager::Classical(csm)) => {
                 csm.set_start_node_idx(options.generic.start_node_idx);
+                csm.set_target_node_indexes(options.specific.classical.target_node_indexes.clone());
                 if me.is_canvas_update_ready()
                     || !csm.is_transition_matrix_sized_correctly(node_count)
                 {
                     csm.set_transition_matrix_from(&me.matrix);
                 }
             }
+            (MatrixEditor::RateMatrix(rme), StateManager::Classical(csm)) => {
+                csm.set_start_node_idx(options.generic.start_node_idx);
+                csm.set_target_node_indexes(
+                    options
+                        .specific
+                        .continuous_classical
+                        .target_node_indexes
+                        .clone(),
+                );
+                if rme.is_matrix_update_ready()
+                    || !csm.is_transition_matrix_sized_correctly(node_count)
+                {
+                    csm.set_transition_matrix_from(&rme.matrix);
+                    rme.on_matrix_updated();
+                }
+            }
             (MatrixEditor::Complex(me), StateManager::Complex(csm)) => {
                 csm.set_start_node_idx(options.generic.start_node_idx);
                 csm.set_target_node_indexes(options.specific.quantum.target_node_indexes.clone());
                 csm.make_transition_matrix_compatible(me.get_combined_matrix());
             }
+            (MatrixEditor::Hamiltonian(he), StateManager::Complex(csm)) => {
+                csm.set_start_node_idx(options.generic.start_node_idx);
+                csm.set_target_node_indexes(
+                    options
+                        .specific
+                        .continuous_quantum
+                        .target_node_indexes
+                        .clone(),
+                );
+                csm.make_transition_matrix_compatible(he.get_combined_matrix());
+            }
+            (MatrixEditor::Szegedy(_), StateManager::Complex(csm)) => {
+                csm.set_start_node_idx(options.generic.start_node_idx);
+                csm.set_target_node_indexes(options.specific.szegedy.target_node_indexes.clone());
+            }
             _ => (),
         };
     }
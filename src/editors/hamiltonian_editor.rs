@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+
+use egui::Color32;
+use evalexpr::{context_map, eval_with_context, HashMapContext, Value};
+use nalgebra::{Complex, DMatrix};
+use strum::VariantArray as _;
+
+use crate::engine::Graph;
+
+use super::{ComplexTransitionMatrix, Editor, HamiltonianType};
+
+/// Edits the Hamiltonian `H` and time step `t` of a continuous-time quantum
+/// walk, exposing the resulting `exp(-iHt)` as the walk's combined matrix.
+#[derive(Debug, Clone)]
+pub struct HamiltonianEditor {
+    hamiltonian_type: HamiltonianType,
+    hamiltonian: DMatrix<Complex<f64>>,
+    evolution_matrix: DMatrix<Complex<f64>>,
+
+    graph: Graph,
+    adjacency_list: HashMap<usize, Vec<usize>>,
+    labels: Vec<(usize, usize)>,
+
+    math_constants: HashMapContext,
+
+    time_step: f64,
+    previous_time_step_text: String,
+    time_step_text: String,
+
+    /// row-major (real, imaginary) text fields of a custom NxN Hamiltonian
+    previous_text_fields: Vec<(String, String)>,
+    pub text_fields: Vec<(String, String)>,
+
+    text_fields_modified: bool,
+
+    is_hermitian: bool,
+
+    is_canvas_update_ready: bool,
+}
+
+impl Editor for HamiltonianEditor {
+    fn is_canvas_update_ready(&self) -> bool {
+        self.is_canvas_update_ready
+    }
+
+    fn on_canvas_updated(&mut self) {
+        self.is_canvas_update_ready = false;
+    }
+}
+
+impl HamiltonianEditor {
+    pub fn new(node_count: usize, arcs: &[(usize, usize, f64)]) -> Self {
+        let time_step = 0.1;
+        let text_fields = Self::new_text_fields(node_count);
+        let mut res = Self {
+            hamiltonian_type: HamiltonianType::Adjacency,
+            hamiltonian: DMatrix::from_element(0, 0, Complex::new(0.0, 0.0)),
+            evolution_matrix: DMatrix::from_element(0, 0, Complex::new(0.0, 0.0)),
+
+            graph: Graph::default(),
+            adjacency_list: HashMap::new(),
+            labels: Vec::new(),
+
+            math_constants: Self::get_math_constants(),
+
+            time_step,
+            previous_time_step_text: time_step.to_string(),
+            time_step_text: time_step.to_string(),
+
+            previous_text_fields: text_fields.clone(),
+            text_fields,
+
+            text_fields_modified: false,
+            is_hermitian: true,
+            is_canvas_update_ready: false,
+        };
+        res.set_graph(node_count, arcs);
+        res.rebuild();
+        res
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("Hamiltonian")
+            .selected_text(format!("{}", self.hamiltonian_type))
+            .show_ui(ui, |ui| {
+                for hamiltonian_type in HamiltonianType::VARIANTS {
+                    if ui.button(format!("{}", hamiltonian_type)).clicked() {
+                        self.set_hamiltonian_type(*hamiltonian_type);
+                    }
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.label("Time Step");
+            if ui
+                .text_edit_singleline(&mut self.time_step_text)
+                .lost_focus()
+            {
+                self.apply_time_step_text();
+            }
+        });
+
+        if self.hamiltonian_type == HamiltonianType::Custom {
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                egui::Grid::new("hamiltonian_editor_grid")
+                    .striped(true)
+                    .spacing([10.0, 10.0])
+                    .show(ui, |ui| {
+                        self.show_text_fields(ui);
+                    });
+            });
+            if self.text_fields_modified {
+                self.apply_text_fields();
+            }
+
+            if !self.is_hermitian {
+                ui.colored_label(
+                    Color32::from_rgb(255, 50, 50),
+                    "Matrix is not Hermitian, only its Hermitian part is used.",
+                );
+            }
+        }
+
+        ui.separator();
+        ui.heading("Preview");
+        ui.collapsing("Hamiltonian", |ui| {
+            self.display_matrix(ui, &self.hamiltonian, "hamiltonian");
+        });
+        ui.collapsing("Evolution Matrix", |ui| {
+            self.display_matrix(ui, &self.evolution_matrix, "evolution");
+        });
+        ui.separator();
+    }
+
+    fn show_text_fields(&mut self, ui: &mut egui::Ui) {
+        let n = self.graph.node_count;
+        if n == 0 {
+            return;
+        }
+
+        // column headers, each spanning a real and imaginary field
+        ui.label("");
+        for i in 0..n {
+            ui.label(format!("{}", i));
+            ui.label("");
+        }
+        ui.end_row();
+
+        for i in 0..n {
+            ui.label(format!("{}", i));
+            for field in self.text_fields[i * n..(i + 1) * n].iter_mut() {
+                if ui.text_edit_singleline(&mut field.0).lost_focus() {
+                    self.text_fields_modified = true;
+                }
+                if ui.text_edit_singleline(&mut field.1).lost_focus() {
+                    self.text_fields_modified = true;
+                }
+            }
+            ui.end_row();
+        }
+    }
+
+    fn set_hamiltonian_type(&mut self, hamiltonian_type: HamiltonianType) {
+        if hamiltonian_type == self.hamiltonian_type {
+            return;
+        }
+
+        // start a custom Hamiltonian from whichever one was being used
+        if hamiltonian_type == HamiltonianType::Custom {
+            self.text_fields = self
+                .hamiltonian
+                .transpose()
+                .iter()
+                .map(|x| (x.re.to_string(), x.im.to_string()))
+                .collect();
+            self.previous_text_fields.clone_from(&self.text_fields);
+        }
+
+        self.hamiltonian_type = hamiltonian_type;
+        self.rebuild();
+    }
+
+    fn apply_time_step_text(&mut self) {
+        match eval_with_context(&self.time_step_text, &self.math_constants) {
+            Ok(Value::Int(num)) => self.time_step = num as f64,
+            Ok(Value::Float(num)) => self.time_step = num,
+            _ => {
+                self.time_step_text
+                    .clone_from(&self.previous_time_step_text);
+                return;
+            }
+        }
+        self.previous_time_step_text
+            .clone_from(&self.time_step_text);
+        self.rebuild();
+    }
+
+    fn apply_text_fields(&mut self) {
+        for i in 0..self.text_fields.len() {
+            let re = eval_with_context(&self.text_fields[i].0, &self.math_constants);
+            let im = eval_with_context(&self.text_fields[i].1, &self.math_constants);
+            if !matches!(
+                (re, im),
+                (
+                    Ok(Value::Int(_) | Value::Float(_)),
+                    Ok(Value::Int(_) | Value::Float(_))
+                )
+            ) {
+                self.text_fields[i].clone_from(&self.previous_text_fields[i]);
+            }
+        }
+        self.previous_text_fields.clone_from(&self.text_fields);
+        self.text_fields_modified = false;
+        self.rebuild();
+    }
+
+    fn custom_hamiltonian(&self) -> DMatrix<Complex<f64>> {
+        let n = self.graph.node_count;
+        let eval = |text: &str| match eval_with_context(text, &self.math_constants) {
+            Ok(Value::Int(num)) => num as f64,
+            Ok(Value::Float(num)) => num,
+            _ => 0.0,
+        };
+        DMatrix::from_fn(n, n, |i, j| {
+            let (re, im) = &self.text_fields[i * n + j];
+            Complex::new(eval(re), eval(im))
+        })
+    }
+
+    /// Recomputes the Hamiltonian and evolution matrix from the current type,
+    /// time step and graph.
+    fn rebuild(&mut self) {
+        self.hamiltonian = match self.hamiltonian_type {
+            HamiltonianType::Adjacency => self.graph.adjacency_matrix().map(Complex::from),
+            HamiltonianType::Laplacian => self.graph.laplacian().map(Complex::from),
+            HamiltonianType::Custom => self.custom_hamiltonian(),
+        };
+        self.is_hermitian = (&self.hamiltonian - self.hamiltonian.adjoint()).norm() < 1e-10;
+        self.evolution_matrix =
+            ComplexTransitionMatrix::evolution_operator(&self.hamiltonian, self.time_step);
+        self.is_canvas_update_ready = true;
+    }
+
+    fn set_graph(&mut self, node_count: usize, arcs: &[(usize, usize, f64)]) {
+        self.graph = Graph::from_weighted_arcs(node_count, arcs).unwrap_or_else(|_| Graph {
+            node_count,
+            ..Default::default()
+        });
+        self.labels = self.graph.node_labels();
+        self.adjacency_list = self.labels.iter().map(|&(i, j)| (i, vec![j])).collect();
+    }
+
+    /// Keeps the Hamiltonian in step with the canvas, retaining any custom
+    /// entries for nodes that still exist.
+    pub(crate) fn update_from_canvas(&mut self, node_count: usize, arcs: &[(usize, usize, f64)]) {
+        if self.graph.node_count == node_count && self.graph.weighted_arcs() == arcs {
+            return;
+        }
+
+        let old_size = self.graph.node_count;
+        if old_size != node_count {
+            let old_text_fields = self.text_fields.clone();
+            self.text_fields = Self::new_text_fields(node_count);
+            for i in 0..node_count.min(old_size) {
+                for j in 0..node_count.min(old_size) {
+                    self.text_fields[i * node_count + j]
+                        .clone_from(&old_text_fields[i * old_size + j]);
+                }
+            }
+            self.previous_text_fields.clone_from(&self.text_fields);
+        }
+
+        self.set_graph(node_count, arcs);
+        self.rebuild();
+    }
+
+    fn new_text_fields(node_count: usize) -> Vec<(String, String)> {
+        vec![(String::from("0"), String::from("0")); node_count * node_count]
+    }
+
+    fn display_matrix(
+        &self,
+        ui: &mut egui::Ui,
+        matrix: &DMatrix<Complex<f64>>,
+        preview_id_prefix: &'static str,
+    ) {
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            egui::Grid::new(format!("{}_matrix_preview", preview_id_prefix))
+                .striped(true)
+                .spacing([10.0, 10.0])
+                .show(ui, |ui| {
+                    // column headers
+                    ui.label(""); // empty label to pad for row headers
+                    for j in 0..matrix.ncols() {
+                        ui.label(egui::RichText::new(format!("{}", j)).strong());
+                    }
+                    ui.end_row();
+
+                    // row headers and values
+                    for i in 0..matrix.nrows() {
+                        ui.label(egui::RichText::new(format!("{}", i)).strong());
+                        for j in 0..matrix.ncols() {
+                            if matrix[(i, j)].l1_norm() < 1e-3 {
+                                ui.label("-");
+                            } else {
+                                ui.label(format!("{:.03}", matrix[(i, j)]));
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    fn get_math_constants() -> HashMapContext {
+        context_map! {
+            "pi" => std::f64::consts::PI,
+            "e" => std::f64::consts::E,
+            "tau" => std::f64::consts::TAU,
+        }
+        .unwrap()
+    }
+
+    /// The evolution matrix `exp(-iHt)` applied at each step.
+    pub fn get_combined_matrix(&self) -> &DMatrix<Complex<f64>> {
+        &self.evolution_matrix
+    }
+
+    pub fn get_hamiltonian(&self) -> &DMatrix<Complex<f64>> {
+        &self.hamiltonian
+    }
+
+    pub fn get_time_step(&self) -> f64 {
+        self.time_step
+    }
+
+    pub fn get_labels(&self) -> &[(usize, usize)] {
+        &self.labels
+    }
+
+    pub fn get_adjacency_list(&self) -> &HashMap<usize, Vec<usize>> {
+        &self.adjacency_list
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct SerializedHamiltonianEditor {
+    hamiltonian_type: HamiltonianType,
+    node_count: usize,
+    edges: Vec<(usize, usize)>,
+    #[serde(default)]
+    directed_edges: Vec<(usize, usize)>,
+    #[serde(default)]
+    weights: Vec<(usize, usize, f64)>,
+    time_step_text: String,
+    text_fields: Vec<(String, String)>,
+}
+impl From<HamiltonianEditor> for SerializedHamiltonianEditor {
+    fn from(m: HamiltonianEditor) -> Self {
+        Self {
+            hamiltonian_type: m.hamiltonian_type,
+            node_count: m.graph.node_count,
+            edges: m.graph.edges,
+            directed_edges: m.graph.directed_edges,
+            weights: m.graph.weights,
+            time_step_text: m.previous_time_step_text,
+            text_fields: m.text_fields,
+        }
+    }
+}
+impl From<SerializedHamiltonianEditor> for HamiltonianEditor {
+    fn from(m: SerializedHamiltonianEditor) -> Self {
+        let graph = Graph {
+            node_count: m.node_count,
+            edges: m.edges,
+            directed_edges: m.directed_edges,
+            weights: m.weights,
+        };
+        let mut res = Self::new(m.node_count, &graph.weighted_arcs());
+        res.hamiltonian_type = m.hamiltonian_type;
+        if m.text_fields.len() == res.text_fields.len() {
+            res.previous_text_fields.clone_from(&m.text_fields);
+            res.text_fields = m.text_fields;
+        }
+        res.time_step_text = m.time_step_text;
+        res.apply_time_step_text();
+        res.rebuild();
+        res
+    }
+}
+impl serde::Serialize for HamiltonianEditor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedHamiltonianEditor::from(self.clone()).serialize(serializer)
+    }
+}
+impl<'de> serde::Deserialize<'de> for HamiltonianEditor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SerializedHamiltonianEditor::deserialize(deserializer).map(Self::from)
+    }
+}
@@ -0,0 +1,10 @@
+use strum::{Display, VariantArray};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Display, VariantArray, serde::Serialize, serde::Deserialize,
+)]
+pub enum HamiltonianType {
+    Adjacency,
+    Laplacian,
+    Custom,
+}
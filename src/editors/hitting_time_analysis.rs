@@ -0,0 +1,184 @@
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{anyhow, Result};
+use nalgebra::{DMatrix, DVector};
+
+/// Exact hitting behaviour of a classical chain started at one node and
+/// absorbed at a set of target nodes, found through the chain's fundamental
+/// matrix `N = (I - Q)^-1` where `Q` holds the transitions between non-target
+/// nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HittingTimeAnalysis {
+    /// Expected number of steps until a target is reached, or `None` when the
+    /// walker may never reach one.
+    pub expected_time: Option<f64>,
+    /// Variance of the number of steps until a target is reached, or `None`
+    /// when the walker may never reach one.
+    pub variance: Option<f64>,
+    /// Probability of being absorbed at each target, sorted by target index.
+    pub absorption: Vec<(usize, f64)>,
+}
+
+impl HittingTimeAnalysis {
+    /// Analyses the chain where entry `(i, j)` of `stochastic` is the weight of
+    /// moving from node `j` to node `i`. Columns are normalized, and a column
+    /// of zeros is a node that loses the walker, as it does when stepping.
+    pub fn new(
+        stochastic: &DMatrix<f64>,
+        start_node_idx: usize,
+        target_node_indexes: &HashSet<usize>,
+    ) -> Result<Self> {
+        if !stochastic.is_square() {
+            return Err(anyhow!("Matrix is not square"));
+        }
+        let n = stochastic.nrows();
+        if start_node_idx >= n {
+            return Err(anyhow!(
+                "Start node {} is outside of the {} node chain",
+                start_node_idx,
+                n
+            ));
+        }
+        let mut targets = target_node_indexes
+            .iter()
+            .copied()
+            .filter(|i| *i < n)
+            .collect::<Vec<_>>();
+        targets.sort_unstable();
+        if targets.is_empty() {
+            return Err(anyhow!("No target nodes to hit"));
+        }
+
+        let mut chain = stochastic.clone();
+        for mut column in chain.column_iter_mut() {
+            let sum = column.sum();
+            if sum != 0.0 {
+                column /= sum;
+            }
+        }
+
+        if targets.contains(&start_node_idx) {
+            return Ok(Self {
+                expected_time: Some(0.0),
+                variance: Some(0.0),
+                absorption: targets
+                    .iter()
+                    .map(|t| (*t, if *t == start_node_idx { 1.0 } else { 0.0 }))
+                    .collect(),
+            });
+        }
+
+        // nodes that can never reach a target are dropped, so that `I - Q` is
+        // invertible, and any probability flowing into them is never absorbed
+        let transient = Self::nodes_reaching(&chain, &targets)
+            .into_iter()
+            .filter(|i| !targets.contains(i))
+            .collect::<Vec<_>>();
+        let Some(start) = transient.iter().position(|i| *i == start_node_idx) else {
+            return Ok(Self {
+                expected_time: None,
+                variance: None,
+                absorption: targets.iter().map(|t| (*t, 0.0)).collect(),
+            });
+        };
+
+        // row convention from here on, entry (a, b) moves from a to b
+        let m = transient.len();
+        let q = DMatrix::from_fn(m, m, |a, b| chain[(transient[b], transient[a])]);
+        let fundamental = (DMatrix::identity(m, m) - q)
+            .try_inverse()
+            .ok_or_else(|| anyhow!("The fundamental matrix of the chain is singular"))?;
+
+        let absorption = targets
+            .iter()
+            .map(|&t| {
+                let r = DVector::from_fn(m, |a, _| chain[(t, transient[a])]);
+                (t, (fundamental.row(start) * r)[0])
+            })
+            .collect::<Vec<_>>();
+
+        let total = absorption.iter().map(|(_, p)| p).sum::<f64>();
+        let (expected_time, variance) = if (total - 1.0).abs() < 1e-9 {
+            let times = fundamental.column_sum();
+            let second = (&fundamental * 2.0 - DMatrix::identity(m, m)) * &times;
+            (
+                Some(times[start]),
+                Some(second[start] - times[start].powi(2)),
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(Self {
+            expected_time,
+            variance,
+            absorption,
+        })
+    }
+
+    /// Every node with a path of nonzero transitions to one of `targets`.
+    fn nodes_reaching(chain: &DMatrix<f64>, targets: &[usize]) -> Vec<usize> {
+        let n = chain.nrows();
+        let mut reached = vec![false; n];
+        let mut queue = targets.iter().copied().collect::<VecDeque<_>>();
+        targets.iter().for_each(|t| reached[*t] = true);
+        while let Some(to) = queue.pop_front() {
+            for from in 0..n {
+                if !reached[from] && chain[(to, from)] > 0.0 {
+                    reached[from] = true;
+                    queue.push_back(from);
+                }
+            }
+        }
+        (0..n).filter(|i| reached[*i]).collect()
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) {
+        match (self.expected_time, self.variance) {
+            (Some(time), Some(variance)) => {
+                ui.label(format!("Expected hitting time: {:.4}", time));
+                ui.label(format!("Hitting time variance: {:.4}", variance));
+            }
+            _ => {
+                ui.label("Expected hitting time: infinite, a target may never be reached");
+            }
+        }
+        for (target, probability) in self.absorption.iter() {
+            ui.label(format!(
+                "Absorption probability at node {}: {:.4}",
+                target, probability
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    fn path_chain() -> DMatrix<f64> {
+        DMatrix::from_row_slice(3, 3, &[0.0, 0.5, 0.0, 1.0, 0.0, 1.0, 0.0, 0.5, 0.0])
+    }
+
+    #[test]
+    fn test_path_hitting_times() {
+        let analysis = HittingTimeAnalysis::new(&path_chain(), 0, &HashSet::from([2])).unwrap();
+        assert_abs_diff_eq!(analysis.expected_time.unwrap(), 4.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(analysis.variance.unwrap(), 8.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(analysis.absorption[0].1, 1.0, epsilon = 1e-12);
+
+        let analysis = HittingTimeAnalysis::new(&path_chain(), 1, &HashSet::from([0, 2])).unwrap();
+        assert_abs_diff_eq!(analysis.expected_time.unwrap(), 1.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(analysis.variance.unwrap(), 0.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(analysis.absorption[0].1, 0.5, epsilon = 1e-12);
+        assert_abs_diff_eq!(analysis.absorption[1].1, 0.5, epsilon = 1e-12);
+
+        // node 0 becomes a sink, so the walker is lost half of the time
+        let mut chain = path_chain();
+        chain.column_mut(0).fill(0.0);
+        let analysis = HittingTimeAnalysis::new(&chain, 1, &HashSet::from([2])).unwrap();
+        assert_eq!(analysis.expected_time, None);
+        assert_abs_diff_eq!(analysis.absorption[0].1, 0.5, epsilon = 1e-12);
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Result};
+use evalexpr::{eval_with_context, HashMapContext, Value};
+use nalgebra::{Complex, DVector};
+
+use super::ComplexMatrixEditor;
+
+/// How far the total probability of the input can be from 1 before a warning
+/// is shown.
+const NORMALIZATION_TOLERANCE: f64 = 1e-9;
+
+/// Lets the walk start from any state instead of the start node: complex
+/// amplitudes on each half-edge for quantum walks, or weights on each node
+/// for classical walks. Every entry may be an expression using `pi`, `e` and
+/// `tau`, and the result is normalized before it is used.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct InitialStateEditor {
+    pub enabled: bool,
+    /// `(re, im)` text of the amplitude on each half-edge.
+    amplitude_fields: Vec<((usize, usize), (String, String))>,
+    /// Text of the weight on each node.
+    weight_fields: Vec<String>,
+
+    #[serde(skip)]
+    error: Option<String>,
+    /// The total probability of the input before it was normalized.
+    #[serde(skip)]
+    total: Option<f64>,
+}
+
+impl InitialStateEditor {
+    /// The normalized amplitudes on each half-edge in `labels`, where
+    /// half-edges without an entry start empty.
+    pub fn get_amplitudes(&self, labels: &[(usize, usize)]) -> Result<DVector<Complex<f64>>> {
+        let context = ComplexMatrixEditor::get_math_constants();
+        let mut res = DVector::from_element(labels.len(), Complex::new(0.0, 0.0));
+        for (i, label) in labels.iter().enumerate() {
+            if let Some((_, (re, im))) = self.amplitude_fields.iter().find(|(l, _)| l == label) {
+                res[i] = Complex::new(Self::eval(re, &context)?, Self::eval(im, &context)?);
+            }
+        }
+        let norm = res.norm();
+        if norm == 0.0 {
+            return Err(anyhow!("Every amplitude is zero"));
+        }
+        Ok(res / Complex::from(norm))
+    }
+
+    /// The normalized distribution over `node_count` nodes.
+    pub fn get_distribution(&self, node_count: usize) -> Result<DVector<f64>> {
+        let context = ComplexMatrixEditor::get_math_constants();
+        let mut res = DVector::from_element(node_count, 0.0);
+        for (i, text) in self.weight_fields.iter().enumerate().take(node_count) {
+            let weight = Self::eval(text, &context)?;
+            if weight < 0.0 {
+                return Err(anyhow!("Node {} has a negative weight", i));
+            }
+            res[i] = weight;
+        }
+        let sum = res.sum();
+        if sum == 0.0 {
+            return Err(anyhow!("Every weight is zero"));
+        }
+        Ok(res / sum)
+    }
+
+    /// Evaluates the amplitudes, recording any error and how far the input
+    /// was from normalized.
+    pub(crate) fn read_amplitudes(
+        &mut self,
+        labels: &[(usize, usize)],
+    ) -> Option<DVector<Complex<f64>>> {
+        let res = self.get_amplitudes(labels);
+        self.total = self.sum_of_amplitudes(labels).ok();
+        self.record_error(res)
+    }
+
+    /// Evaluates the distribution, recording any error and how far the input
+    /// was from normalized.
+    pub(crate) fn read_distribution(&mut self, node_count: usize) -> Option<DVector<f64>> {
+        let res = self.get_distribution(node_count);
+        self.total = self.sum_of_weights(node_count).ok();
+        self.record_error(res)
+    }
+
+    fn record_error<T>(&mut self, res: Result<T>) -> Option<T> {
+        match res {
+            Ok(x) => {
+                self.error = None;
+                Some(x)
+            }
+            Err(e) => {
+                self.error = Some(e.to_string());
+                None
+            }
+        }
+    }
+
+    fn sum_of_amplitudes(&self, labels: &[(usize, usize)]) -> Result<f64> {
+        let context = ComplexMatrixEditor::get_math_constants();
+        let mut res = 0.0;
+        for (label, (re, im)) in self.amplitude_fields.iter() {
+            if labels.contains(label) {
+                res +=
+                    Complex::new(Self::eval(re, &context)?, Self::eval(im, &context)?).norm_sqr();
+            }
+        }
+        Ok(res)
+    }
+
+    fn sum_of_weights(&self, node_count: usize) -> Result<f64> {
+        let context = ComplexMatrixEditor::get_math_constants();
+        self.weight_fields
+            .iter()
+            .take(node_count)
+            .map(|text| Self::eval(text, &context))
+            .sum()
+    }
+
+    fn eval(text: &str, context: &HashMapContext) -> Result<f64> {
+        match eval_with_context(text, context) {
+            Ok(Value::Int(num)) => Ok(num as f64),
+            Ok(Value::Float(num)) => Ok(num),
+            _ => Err(anyhow!("Could not read \"{}\" as a number", text)),
+        }
+    }
+
+    /// Shows a field for the amplitude on each half-edge, returning whether
+    /// the initial state should be applied or cleared.
+    pub fn show_amplitudes(&mut self, ui: &mut egui::Ui, labels: &[(usize, usize)]) -> bool {
+        for label in labels.iter() {
+            if !self.amplitude_fields.iter().any(|(l, _)| l == label) {
+                self.amplitude_fields
+                    .push((*label, (String::from("0"), String::from("0"))));
+            }
+        }
+
+        let mut changed = ui
+            .checkbox(&mut self.enabled, "Start from a custom state")
+            .changed();
+        if !self.enabled {
+            return changed;
+        }
+
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            egui::Grid::new("initial_amplitudes")
+                .striped(true)
+                .spacing([10.0, 4.0])
+                .show(ui, |ui| {
+                    ui.label(egui::RichText::new("Half-edge").strong());
+                    ui.label(egui::RichText::new("Re").strong());
+                    ui.label(egui::RichText::new("Im").strong());
+                    ui.end_row();
+                    for (label, (re, im)) in self
+                        .amplitude_fields
+                        .iter_mut()
+                        .filter(|(l, _)| labels.contains(l))
+                    {
+                        ui.label(format!("{}->{}", label.0, label.1));
+                        ui.add(egui::TextEdit::singleline(re).desired_width(60.0));
+                        ui.add(egui::TextEdit::singleline(im).desired_width(60.0));
+                        ui.end_row();
+                    }
+                });
+        });
+        changed |= ui.button("Apply Initial State").clicked();
+        self.show_status(ui);
+
+        changed
+    }
+
+    /// Shows a field for the weight on each node, returning whether the
+    /// initial state should be applied or cleared.
+    pub fn show_weights(&mut self, ui: &mut egui::Ui, node_count: usize) -> bool {
+        if self.weight_fields.len() < node_count {
+            self.weight_fields.resize(node_count, String::from("0"));
+        }
+
+        let mut changed = ui
+            .checkbox(&mut self.enabled, "Start from a custom distribution")
+            .changed();
+        if !self.enabled {
+            return changed;
+        }
+
+        egui::Grid::new("initial_weights")
+            .striped(true)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new("Node").strong());
+                ui.label(egui::RichText::new("Weight").strong());
+                ui.end_row();
+                for (i, text) in self.weight_fields.iter_mut().enumerate().take(node_count) {
+                    ui.label(format!("{}", i));
+                    ui.add(egui::TextEdit::singleline(text).desired_width(60.0));
+                    ui.end_row();
+                }
+            });
+        changed |= ui.button("Apply Initial Distribution").clicked();
+        self.show_status(ui);
+
+        changed
+    }
+
+    fn show_status(&self, ui: &mut egui::Ui) {
+        if let Some(e) = &self.error {
+            ui.colored_label(egui::Color32::from_rgb(255, 50, 50), e);
+        } else if let Some(total) = self
+            .total
+            .filter(|x| (x - 1.0).abs() > NORMALIZATION_TOLERANCE)
+        {
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 200, 50),
+                format!(
+                    "The input has total probability {:.04}, so it was normalized",
+                    total
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_initial_state_normalization() {
+        let labels = [(0, 1), (0, 2), (1, 0), (2, 0)];
+        let mut editor = InitialStateEditor {
+            amplitude_fields: vec![
+                ((0, 1), (String::from("1"), String::from("0"))),
+                ((1, 0), (String::from("0"), String::from("3^0.5"))),
+                ((5, 6), (String::from("100"), String::from("0"))),
+            ],
+            ..Default::default()
+        };
+        let amplitudes = editor.read_amplitudes(&labels).unwrap();
+        assert_abs_diff_eq!(amplitudes[0].re, 0.5, epsilon = 1e-12);
+        assert_abs_diff_eq!(amplitudes[2].im, 0.75_f64.sqrt(), epsilon = 1e-12);
+        assert_eq!(amplitudes[1], Complex::new(0.0, 0.0));
+        assert_abs_diff_eq!(editor.total.unwrap(), 4.0, epsilon = 1e-12);
+
+        editor.weight_fields = vec![String::from("1"), String::from("pi"), String::from("x")];
+        assert!(editor.read_distribution(3).is_none());
+        assert!(editor.error.is_some());
+        let distribution = editor.read_distribution(2).unwrap();
+        assert_abs_diff_eq!(distribution.sum(), 1.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(
+            distribution[1] / distribution[0],
+            std::f64::consts::PI,
+            epsilon = 1e-12
+        );
+
+        editor.weight_fields = vec![String::from("0"), String::from("-1")];
+        assert!(editor.get_distribution(2).is_err());
+    }
+}
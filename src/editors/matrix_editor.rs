@@ -1,4 +1,6 @@
-use super::{ClassicalMatrixEditor, ComplexMatrixEditor};
+use super::{
+    ClassicalMatrixEditor, ComplexMatrixEditor, HamiltonianEditor, RateMatrixEditor, SzegedyEditor,
+};
 
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 pub enum MatrixEditor {
@@ -6,6 +8,9 @@ pub enum MatrixEditor {
     None,
     Classical(ClassicalMatrixEditor),
     Complex(ComplexMatrixEditor),
+    Hamiltonian(HamiltonianEditor),
+    RateMatrix(RateMatrixEditor),
+    Szegedy(SzegedyEditor),
 }
 
 impl MatrixEditor {
@@ -20,4 +25,16 @@ impl MatrixEditor {
     pub fn is_complex(&self) -> bool {
         matches!(self, Self::Complex(_))
     }
+
+    pub fn is_hamiltonian(&self) -> bool {
+        matches!(self, Self::Hamiltonian(_))
+    }
+
+    pub fn is_rate_matrix(&self) -> bool {
+        matches!(self, Self::RateMatrix(_))
+    }
+
+    pub fn is_szegedy(&self) -> bool {
+        matches!(self, Self::Szegedy(_))
+    }
 }
@@ -0,0 +1,165 @@
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use strum::{Display, VariantArray};
+
+/// When the walker's position is measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, VariantArray)]
+pub enum MeasurementSchedule {
+    /// After each step with a fixed probability.
+    #[default]
+    #[strum(to_string = "With probability p")]
+    Probability,
+    /// After every `k`th step.
+    #[strum(to_string = "Every k steps")]
+    Period,
+}
+
+/// What a measurement does to the state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, VariantArray)]
+pub enum MeasurementOutcome {
+    /// Collapses onto one node, sampled from a seeded random number generator.
+    #[default]
+    #[strum(to_string = "Sampled (seeded)")]
+    Sampled,
+    /// Averages over every outcome, leaving a mixed state.
+    #[strum(to_string = "Ensemble average")]
+    Ensemble,
+}
+
+/// Intermittent measurement of a quantum walker's position. While enabled,
+/// target nodes only absorb the walker when it is measured.
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub enabled: bool,
+    pub schedule: MeasurementSchedule,
+    pub probability: f64,
+    pub period: usize,
+    pub outcome: MeasurementOutcome,
+    pub seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl Default for Measurement {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            schedule: MeasurementSchedule::default(),
+            probability: 0.5,
+            period: 1,
+            outcome: MeasurementOutcome::default(),
+            seed: 0,
+            rng: ChaCha8Rng::seed_from_u64(0),
+        }
+    }
+}
+
+impl Measurement {
+    /// Measures after every `period`th step.
+    pub fn every(period: usize, outcome: MeasurementOutcome, seed: u64) -> Self {
+        Self {
+            enabled: true,
+            schedule: MeasurementSchedule::Period,
+            period: period.max(1),
+            outcome,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            ..Default::default()
+        }
+    }
+
+    /// Measures after each step with `probability`.
+    pub fn with_probability(probability: f64, outcome: MeasurementOutcome, seed: u64) -> Self {
+        Self {
+            enabled: true,
+            schedule: MeasurementSchedule::Probability,
+            probability: probability.clamp(0.0, 1.0),
+            outcome,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            ..Default::default()
+        }
+    }
+
+    /// Reseeds the random number generator so a restarted walk repeats.
+    pub fn restart(&mut self) {
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+    }
+
+    pub fn is_ensemble(&self) -> bool {
+        self.enabled && self.outcome == MeasurementOutcome::Ensemble
+    }
+
+    /// How strongly the position is measured after `step`, 0 for no
+    /// measurement and 1 for a full one. Ensemble measurements with a
+    /// probability are partial, with that probability as their weight.
+    pub fn weight(&mut self, step: usize) -> f64 {
+        if !self.enabled {
+            return 0.0;
+        }
+        let is_measured = match (self.schedule, self.outcome) {
+            (MeasurementSchedule::Period, _) => step % self.period.max(1) == 0,
+            (MeasurementSchedule::Probability, MeasurementOutcome::Ensemble) => {
+                return self.probability;
+            }
+            (MeasurementSchedule::Probability, MeasurementOutcome::Sampled) => {
+                self.sample_uniform() < self.probability
+            }
+        };
+        if is_measured {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// A uniform sample from `[0, 1)`.
+    pub fn sample_uniform(&mut self) -> f64 {
+        (self.rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Shows the measurement settings, returning whether any were changed.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.enabled, "Measure the position intermittently")
+            .changed();
+        if !self.enabled {
+            return changed;
+        }
+
+        egui::ComboBox::from_label("Schedule")
+            .selected_text(format!("{}", self.schedule))
+            .show_ui(ui, |ui| {
+                for schedule in MeasurementSchedule::VARIANTS {
+                    changed |= ui
+                        .selectable_value(&mut self.schedule, *schedule, format!("{}", schedule))
+                        .changed();
+                }
+            });
+        changed |= match self.schedule {
+            MeasurementSchedule::Probability => ui
+                .add(egui::Slider::new(&mut self.probability, 0.0..=1.0).text("p"))
+                .changed(),
+            MeasurementSchedule::Period => ui
+                .add(egui::Slider::new(&mut self.period, 1..=50).text("k"))
+                .changed(),
+        };
+
+        egui::ComboBox::from_label("Outcome")
+            .selected_text(format!("{}", self.outcome))
+            .show_ui(ui, |ui| {
+                for outcome in MeasurementOutcome::VARIANTS {
+                    changed |= ui
+                        .selectable_value(&mut self.outcome, *outcome, format!("{}", outcome))
+                        .changed();
+                }
+            });
+        if self.outcome == MeasurementOutcome::Sampled {
+            ui.horizontal(|ui| {
+                ui.label("Seed");
+                changed |= ui.add(egui::DragValue::new(&mut self.seed)).changed();
+            });
+        }
+
+        changed
+    }
+}
@@ -1,25 +1,63 @@
 mod classical_matrix_editor;
 mod classical_state_manager;
 mod classical_transition_matrix;
+mod coin_preset;
+mod coin_target;
+mod comparison_view;
 mod complex_matrix_editor;
 mod complex_state_manager;
 mod complex_transition_matrix;
+mod decoherence;
 mod editor;
 mod editors_container;
+mod hamiltonian_editor;
+mod hamiltonian_type;
+mod hitting_time_analysis;
+mod initial_state_editor;
 mod matrix_editor;
+mod measurement;
+mod parameter_sweep_view;
+mod probability_plot;
 mod propagation_method;
+mod rate_matrix_editor;
+mod run_until;
+mod sparse_matrix;
+mod spectrum_viewer;
 mod state_manager;
+mod stationary_analysis;
+mod step_history;
+mod szegedy_editor;
 mod transition_matrix_correction_type;
+mod two_particle_walk;
 
 pub use classical_matrix_editor::ClassicalMatrixEditor;
 pub use classical_state_manager::ClassicalStateManager;
 pub use classical_transition_matrix::ClassicalTransitionMatrix;
+pub use coin_preset::CoinPreset;
+pub use coin_target::CoinTarget;
+pub use comparison_view::ComparisonView;
 pub use complex_matrix_editor::ComplexMatrixEditor;
 pub use complex_state_manager::ComplexStateManager;
 pub use complex_transition_matrix::ComplexTransitionMatrix;
+pub use decoherence::{Decoherence, DecoherenceChannel};
 pub use editor::Editor;
 pub use editors_container::EditorsContainer;
+pub use hamiltonian_editor::HamiltonianEditor;
+pub use hamiltonian_type::HamiltonianType;
+pub use hitting_time_analysis::HittingTimeAnalysis;
+pub use initial_state_editor::InitialStateEditor;
 pub use matrix_editor::MatrixEditor;
+pub use measurement::{Measurement, MeasurementOutcome, MeasurementSchedule};
+pub use parameter_sweep_view::ParameterSweepView;
+pub use probability_plot::ProbabilityPlot;
 pub use propagation_method::PropagationMethod;
+pub use rate_matrix_editor::RateMatrixEditor;
+pub use run_until::{RunUntil, StopReason};
+pub use sparse_matrix::SparseMatrix;
+pub use spectrum_viewer::SpectrumViewer;
 pub use state_manager::StateManager;
+pub use stationary_analysis::StationaryAnalysis;
+pub use step_history::StepHistory;
+pub use szegedy_editor::SzegedyEditor;
 pub use transition_matrix_correction_type::TransitionMatrixCorrectionType;
+pub use two_particle_walk::{ParticleStatistics, TwoParticleWalk};
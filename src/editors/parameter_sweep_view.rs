@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use egui_plot::{Line, Plot, PlotPoints, Points};
+use strum::VariantArray as _;
+
+use crate::engine::{Coin, Graph, ParameterSweep, SweepMetric, SweepPoint};
+
+use super::{ComplexMatrixEditor, PropagationMethod};
+
+/// Reruns the quantum walk with a coin parameter set to each of a range of
+/// values, plotting a metric of the walk against the parameter.
+#[derive(Debug, Clone)]
+pub struct ParameterSweepView {
+    parameter: String,
+    from: f64,
+    to: f64,
+    count: usize,
+    steps: usize,
+    /// The target probability that counts as the walker having hit.
+    threshold: f64,
+    metric: SweepMetric,
+    /// The parameter the points were swept over, and the points.
+    result: Option<(String, Vec<SweepPoint>)>,
+    error: Option<String>,
+}
+
+impl Default for ParameterSweepView {
+    fn default() -> Self {
+        Self {
+            parameter: String::new(),
+            from: 0.0,
+            to: std::f64::consts::PI,
+            count: 50,
+            steps: 20,
+            threshold: 0.5,
+            metric: SweepMetric::TargetProbability,
+            result: None,
+            error: None,
+        }
+    }
+}
+
+impl ParameterSweepView {
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        cme: &ComplexMatrixEditor,
+        node_count: usize,
+        start_node_idx: usize,
+        target_node_indexes: &HashSet<usize>,
+    ) {
+        let parameters = cme.get_parameters();
+        if parameters.is_empty() {
+            ui.label("Add a parameter to the coin to sweep over it");
+            return;
+        }
+        if !parameters.iter().any(|(name, _)| *name == self.parameter) {
+            self.parameter.clone_from(&parameters[0].0);
+        }
+
+        egui::ComboBox::from_label("Parameter")
+            .selected_text(&self.parameter)
+            .show_ui(ui, |ui| {
+                for (name, _) in parameters {
+                    ui.selectable_value(&mut self.parameter, name.clone(), name);
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.label("From");
+            ui.add(egui::DragValue::new(&mut self.from).speed(0.01));
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut self.to).speed(0.01));
+            ui.label("in");
+            ui.add(egui::DragValue::new(&mut self.count).range(2..=10_000));
+            ui.label("values");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Steps");
+            ui.add(egui::DragValue::new(&mut self.steps).range(1..=100_000));
+            ui.label("Hitting threshold");
+            ui.add(
+                egui::DragValue::new(&mut self.threshold)
+                    .range(0.0..=1.0)
+                    .speed(0.01),
+            );
+        });
+
+        if ui.button("Sweep").clicked() {
+            match self.sweep(cme, node_count, start_node_idx, target_node_indexes) {
+                Ok(points) => {
+                    self.result = Some((self.parameter.clone(), points));
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        if let Some(e) = &self.error {
+            ui.colored_label(egui::Color32::from_rgb(255, 50, 50), e);
+        }
+
+        let Some((parameter, points)) = &self.result else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Metric")
+                .selected_text(format!("{}", self.metric))
+                .show_ui(ui, |ui| {
+                    for metric in SweepMetric::VARIANTS {
+                        ui.selectable_value(&mut self.metric, *metric, format!("{}", metric));
+                    }
+                });
+            if ui.button("Copy CSV").clicked() {
+                ui.output_mut(|o| o.copied_text = ParameterSweep::to_csv(parameter, points));
+            }
+            if ui.button("Copy JSON").clicked() {
+                if let Ok(json) = serde_json::to_string_pretty(points) {
+                    ui.output_mut(|o| o.copied_text = json);
+                }
+            }
+        });
+
+        let series = points
+            .iter()
+            .filter_map(|point| Some([point.value, point.metric(self.metric)?]))
+            .collect::<Vec<_>>();
+        let metric = self.metric;
+        Plot::new("parameter_sweep_plot")
+            .height(250.0)
+            .x_axis_label(parameter.clone())
+            .y_axis_label(format!("{}", metric))
+            .include_y(0.0)
+            .label_formatter(|_, point| format!("{:.4}\n{:.4}", point.x, point.y))
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(PlotPoints::from(series.clone())));
+                plot_ui.points(Points::new(PlotPoints::from(series)).radius(2.0_f32));
+            });
+    }
+
+    /// Runs the walk the editor describes once for each parameter value. The
+    /// walk is rebuilt from the coins alone, so needs the example propagation.
+    fn sweep(
+        &self,
+        cme: &ComplexMatrixEditor,
+        node_count: usize,
+        start_node_idx: usize,
+        target_node_indexes: &HashSet<usize>,
+    ) -> Result<Vec<SweepPoint>> {
+        if cme.get_propagation_method() != PropagationMethod::ExampleMatrix {
+            return Err(anyhow!(
+                "Sweeps need the {} propagation method",
+                PropagationMethod::ExampleMatrix
+            ));
+        }
+
+        let arcs = cme
+            .get_adjacency_list()
+            .iter()
+            .flat_map(|(from, to)| to.iter().map(move |to| (*from, *to)))
+            .collect::<Vec<_>>();
+        let graph = Graph::from_arcs(node_count, &arcs)?;
+        let sweep = ParameterSweep::new(
+            graph,
+            start_node_idx,
+            target_node_indexes.clone(),
+            self.steps,
+        )?
+        .with_threshold(self.threshold)
+        .with_time_dependent_coin(cme.is_time_dependent());
+
+        let values = ParameterSweep::values(self.from, self.to, self.count);
+        sweep.run(&values, |value, t| {
+            cme.evaluate_coins_with(&self.parameter, value, t)
+                .map(Coin::Custom)
+        })
+    }
+}
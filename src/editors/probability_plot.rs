@@ -0,0 +1,213 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use egui_plot::{Legend, Line, Plot, PlotPoints, VLine};
+use nalgebra::DVector;
+
+/// The most steps kept, dropping the earliest once a run grows past it.
+const MAX_RECORDS: usize = 10_000;
+
+/// Probabilities below this are drawn at this value on a log scale.
+const LOG_FLOOR: f64 = 1e-12;
+
+/// Two recordings of a step closer than this are treated as the same run.
+const RECORD_TOLERANCE: f64 = 1e-9;
+
+/// Node probabilities and target accumulation against step, recorded as a
+/// walk runs.
+#[derive(Debug, Clone, Default)]
+pub struct ProbabilityPlot {
+    /// The step, node probabilities and target accumulation of consecutive
+    /// steps.
+    records: VecDeque<(usize, DVector<f64>, DVector<f64>)>,
+    selected_nodes: BTreeSet<usize>,
+    show_targets: bool,
+    show_removed: bool,
+    log_scale: bool,
+}
+
+impl ProbabilityPlot {
+    /// Records the walk at `step`. Returning to a recorded step keeps the
+    /// steps after it, unless the walk differs there, as after a reset or a
+    /// changed matrix, in which case they are forgotten.
+    pub fn record(
+        &mut self,
+        step: usize,
+        probabilities: DVector<f64>,
+        target_accumulation: DVector<f64>,
+    ) {
+        let first = self.records.front().map_or(step, |(s, _, _)| *s);
+        match step.checked_sub(first).filter(|i| *i < self.records.len()) {
+            Some(i) => {
+                let (_, p, a) = &self.records[i];
+                let is_same = |x: &DVector<f64>, y: &DVector<f64>| {
+                    x.len() == y.len() && (x - y).amax() < RECORD_TOLERANCE
+                };
+                if is_same(p, &probabilities) && is_same(a, &target_accumulation) {
+                    return;
+                }
+                self.records.truncate(i);
+            }
+            None if step != first + self.records.len() => self.records.clear(),
+            None => (),
+        }
+
+        if self.records.is_empty() && self.selected_nodes.is_empty() {
+            // plot every node of a small graph to begin with
+            self.selected_nodes = (0..probabilities.len().min(8)).collect();
+        }
+        self.records
+            .push_back((step, probabilities, target_accumulation));
+        if self.records.len() > MAX_RECORDS {
+            self.records.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// The probability of `node` at each recorded step.
+    pub fn node_series(&self, node: usize) -> Vec<[f64; 2]> {
+        self.series(|(_, p, _)| p.get(node).copied())
+    }
+
+    /// The probability accumulated at `node` by each recorded step.
+    pub fn target_series(&self, node: usize) -> Vec<[f64; 2]> {
+        self.series(|(_, _, a)| a.get(node).copied())
+    }
+
+    /// The probability removed by every target by each recorded step.
+    pub fn removed_series(&self) -> Vec<[f64; 2]> {
+        self.series(|(_, _, a)| Some(a.sum()))
+    }
+
+    fn series(
+        &self,
+        value: impl Fn(&(usize, DVector<f64>, DVector<f64>)) -> Option<f64>,
+    ) -> Vec<[f64; 2]> {
+        self.records
+            .iter()
+            .filter_map(|record| Some([record.0 as f64, value(record)?]))
+            .collect()
+    }
+
+    fn to_plot_points(&self, series: Vec<[f64; 2]>) -> PlotPoints {
+        if self.log_scale {
+            series
+                .into_iter()
+                .map(|[x, y]| [x, y.max(LOG_FLOOR).log10()])
+                .collect()
+        } else {
+            series.into()
+        }
+    }
+
+    /// Shows the plot with the current step marked, alongside which series to
+    /// draw.
+    pub fn show(&mut self, ui: &mut egui::Ui, node_count: usize, current_step: usize) {
+        let targets = self
+            .records
+            .back()
+            .map(|(_, _, a)| {
+                a.iter()
+                    .enumerate()
+                    .filter(|(_, x)| **x > 0.0)
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Nodes:");
+            for node in 0..node_count {
+                let mut is_selected = self.selected_nodes.contains(&node);
+                if ui.checkbox(&mut is_selected, node.to_string()).changed() {
+                    if is_selected {
+                        self.selected_nodes.insert(node);
+                    } else {
+                        self.selected_nodes.remove(&node);
+                    }
+                }
+            }
+            if ui.button("All").clicked() {
+                self.selected_nodes = (0..node_count).collect();
+            }
+            if ui.button("None").clicked() {
+                self.selected_nodes.clear();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_targets, "Target accumulation");
+            ui.checkbox(&mut self.show_removed, "Total removed");
+            ui.checkbox(&mut self.log_scale, "Log scale");
+        });
+
+        let log_scale = self.log_scale;
+        let as_probability = move |y: f64| if log_scale { 10f64.powf(y) } else { y };
+        let mut plot = Plot::new("probability_plot")
+            .height(250.0)
+            .legend(Legend::default())
+            .x_axis_label("Step")
+            .y_axis_label("Probability")
+            .include_x(0.0)
+            .label_formatter(move |name, point| {
+                let step = point.x.round();
+                let probability = as_probability(point.y);
+                if name.is_empty() {
+                    format!("step {}\n{:.4e}", step, probability)
+                } else {
+                    format!("{}\nstep {}\n{:.6}", name, step, probability)
+                }
+            });
+        plot = if log_scale {
+            plot.y_axis_formatter(|mark, _| format!("1e{}", mark.value))
+        } else {
+            plot.include_y(0.0).include_y(1.0)
+        };
+
+        plot.show(ui, |plot_ui| {
+            for node in self.selected_nodes.iter().filter(|x| **x < node_count) {
+                let points = self.to_plot_points(self.node_series(*node));
+                plot_ui.line(Line::new(points).name(format!("Node {}", node)));
+            }
+            if self.show_targets {
+                for node in targets.iter() {
+                    let points = self.to_plot_points(self.target_series(*node));
+                    plot_ui.line(Line::new(points).name(format!("Target {}", node)));
+                }
+            }
+            if self.show_removed {
+                let points = self.to_plot_points(self.removed_series());
+                plot_ui.line(Line::new(points).name("Total removed"));
+            }
+            plot_ui.vline(VLine::new(current_step as f64).color(egui::Color32::GRAY));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probability_plot_records() {
+        let mut plot = ProbabilityPlot::default();
+        let step = |p: f64| DVector::from_vec(vec![1.0 - p, p]);
+        for i in 0..4 {
+            plot.record(i, step(i as f64 / 4.0), DVector::from_element(2, 0.0));
+        }
+        assert_eq!(plot.node_series(1).len(), 4);
+
+        // returning to a recorded step keeps the steps after it
+        plot.record(1, step(0.25), DVector::from_element(2, 0.0));
+        assert_eq!(plot.node_series(1).len(), 4);
+
+        // a different walk at a recorded step replaces the steps after it
+        plot.record(1, step(0.5), DVector::from_vec(vec![0.0, 0.1]));
+        assert_eq!(plot.node_series(1), vec![[0.0, 0.0], [1.0, 0.5]]);
+        assert_eq!(plot.removed_series(), vec![[0.0, 0.0], [1.0, 0.1]]);
+
+        plot.record(5, step(1.0), DVector::from_element(2, 0.0));
+        assert_eq!(plot.node_series(0), vec![[5.0, 0.0]]);
+    }
+}
@@ -1,6 +1,8 @@
 use strum::{Display, VariantArray};
 
-#[derive(Debug, Clone, Copy, Display, VariantArray, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Display, VariantArray, serde::Serialize, serde::Deserialize,
+)]
 pub enum PropagationMethod {
     Blank,
     ExampleMatrix,
@@ -0,0 +1,201 @@
+use evalexpr::{context_map, eval_with_context, HashMapContext, Value};
+use nalgebra::DMatrix;
+
+use super::{ClassicalMatrixEditor, ClassicalTransitionMatrix, Editor};
+
+/// Edits the transition rates and time step of a continuous-time classical
+/// walk, exposing `exp(Qt)` as the walk's stochastic matrix.
+#[derive(Debug, Clone)]
+pub struct RateMatrixEditor {
+    /// Off-diagonal entry `(i, j)` is the rate of moving from node `j` to
+    /// node `i`. Diagonal entries are ignored.
+    pub rates: ClassicalMatrixEditor,
+
+    applied_rates: DMatrix<f64>,
+    generator: DMatrix<f64>,
+    pub matrix: DMatrix<f64>,
+
+    math_constants: HashMapContext,
+
+    time_step: f64,
+    previous_time_step_text: String,
+    time_step_text: String,
+
+    is_matrix_update_ready: bool,
+}
+
+impl Editor for RateMatrixEditor {
+    fn is_canvas_update_ready(&self) -> bool {
+        self.rates.is_canvas_update_ready()
+    }
+
+    fn on_canvas_updated(&mut self) {
+        self.rates.on_canvas_updated();
+    }
+}
+
+impl RateMatrixEditor {
+    pub fn new(node_count: usize) -> Self {
+        let time_step = 0.1;
+        let mut res = Self {
+            rates: ClassicalMatrixEditor::new(node_count),
+            applied_rates: DMatrix::from_element(0, 0, 0.0),
+            generator: DMatrix::from_element(0, 0, 0.0),
+            matrix: DMatrix::from_element(0, 0, 0.0),
+            math_constants: Self::get_math_constants(),
+            time_step,
+            previous_time_step_text: time_step.to_string(),
+            time_step_text: time_step.to_string(),
+            is_matrix_update_ready: false,
+        };
+        res.rebuild();
+        res
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Time Step");
+            if ui
+                .text_edit_singleline(&mut self.time_step_text)
+                .lost_focus()
+            {
+                self.apply_time_step_text();
+            }
+        });
+
+        ui.label("Transition rates, the diagonal is set so that each column sums to zero.");
+        self.rates.show(ui);
+        self.refresh();
+
+        ui.separator();
+        ui.heading("Preview");
+        ui.collapsing("Generator", |ui| {
+            Self::display_matrix(ui, &self.generator, "generator");
+        });
+        ui.collapsing("Transition Matrix", |ui| {
+            Self::display_matrix(ui, &self.matrix, "rate_transition");
+        });
+        ui.separator();
+    }
+
+    fn apply_time_step_text(&mut self) {
+        match eval_with_context(&self.time_step_text, &self.math_constants) {
+            Ok(Value::Int(num)) => self.time_step = num as f64,
+            Ok(Value::Float(num)) => self.time_step = num,
+            _ => {
+                self.time_step_text
+                    .clone_from(&self.previous_time_step_text);
+                return;
+            }
+        }
+        self.previous_time_step_text
+            .clone_from(&self.time_step_text);
+        self.rebuild();
+    }
+
+    /// Recomputes the transition matrix if the rates have changed since it was
+    /// last built, either from the text fields or from the canvas.
+    pub(crate) fn refresh(&mut self) {
+        if self.applied_rates != self.rates.matrix {
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.applied_rates.clone_from(&self.rates.matrix);
+        self.generator = ClassicalTransitionMatrix::generator_from_rates(&self.applied_rates);
+        self.matrix =
+            ClassicalTransitionMatrix::evolution_operator(&self.generator, self.time_step);
+        self.is_matrix_update_ready = true;
+    }
+
+    /// Whether the transition matrix changed since the state manager last read
+    /// it.
+    pub(crate) fn is_matrix_update_ready(&self) -> bool {
+        self.is_matrix_update_ready
+    }
+
+    pub(crate) fn on_matrix_updated(&mut self) {
+        self.is_matrix_update_ready = false;
+    }
+
+    fn display_matrix(ui: &mut egui::Ui, matrix: &DMatrix<f64>, preview_id_prefix: &'static str) {
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            egui::Grid::new(format!("{}_matrix_preview", preview_id_prefix))
+                .striped(true)
+                .spacing([10.0, 10.0])
+                .show(ui, |ui| {
+                    // column headers
+                    ui.label(""); // empty label to pad for row headers
+                    for j in 0..matrix.ncols() {
+                        ui.label(egui::RichText::new(format!("{}", j)).strong());
+                    }
+                    ui.end_row();
+
+                    // row headers and values
+                    for i in 0..matrix.nrows() {
+                        ui.label(egui::RichText::new(format!("{}", i)).strong());
+                        for j in 0..matrix.ncols() {
+                            if matrix[(i, j)].abs() < 1e-3 {
+                                ui.label("-");
+                            } else {
+                                ui.label(format!("{:.03}", matrix[(i, j)]));
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    fn get_math_constants() -> HashMapContext {
+        context_map! {
+            "pi" => std::f64::consts::PI,
+            "e" => std::f64::consts::E,
+            "tau" => std::f64::consts::TAU,
+        }
+        .unwrap()
+    }
+
+    pub fn get_generator(&self) -> &DMatrix<f64> {
+        &self.generator
+    }
+
+    pub fn get_time_step(&self) -> f64 {
+        self.time_step
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct SerializedRateMatrixEditor {
+    rates: ClassicalMatrixEditor,
+    time_step_text: String,
+}
+impl From<RateMatrixEditor> for SerializedRateMatrixEditor {
+    fn from(m: RateMatrixEditor) -> Self {
+        Self {
+            rates: m.rates,
+            time_step_text: m.previous_time_step_text,
+        }
+    }
+}
+impl From<SerializedRateMatrixEditor> for RateMatrixEditor {
+    fn from(m: SerializedRateMatrixEditor) -> Self {
+        let mut res = Self::new(m.rates.matrix.nrows());
+        res.rates = m.rates;
+        res.time_step_text = m.time_step_text;
+        res.apply_time_step_text();
+        res.rebuild();
+        res
+    }
+}
+impl serde::Serialize for RateMatrixEditor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedRateMatrixEditor::from(self.clone()).serialize(serializer)
+    }
+}
+impl<'de> serde::Deserialize<'de> for RateMatrixEditor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SerializedRateMatrixEditor::deserialize(deserializer).map(Self::from)
+    }
+}
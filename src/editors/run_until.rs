@@ -0,0 +1,170 @@
+use anyhow::Result;
+use nalgebra::DVector;
+
+/// Why a run stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    /// The probability accumulated over every target node passed the
+    /// threshold.
+    TargetAccumulation(f64),
+    /// The probability of the watched node passed the threshold.
+    NodeProbability(usize, f64),
+    /// The total variation distance between consecutive steps fell below
+    /// epsilon.
+    Converged(f64),
+    /// The maximum number of steps was taken.
+    MaxSteps,
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TargetAccumulation(x) => write!(f, "target accumulation reached {:.04}", x),
+            Self::NodeProbability(node, x) => {
+                write!(f, "node {} reached probability {:.04}", node, x)
+            }
+            Self::Converged(x) => write!(f, "change between steps fell to {:.2e}", x),
+            Self::MaxSteps => write!(f, "maximum step count reached"),
+        }
+    }
+}
+
+/// Conditions that stop a walk stepping forward, checked after every step.
+/// The maximum step count always applies so that a run ends.
+#[derive(Debug, Clone)]
+pub struct RunUntil {
+    pub max_steps: usize,
+    pub use_target_threshold: bool,
+    pub target_threshold: f64,
+    pub use_node_threshold: bool,
+    pub node: usize,
+    pub node_threshold: f64,
+    pub use_convergence: bool,
+    pub epsilon: f64,
+}
+
+impl Default for RunUntil {
+    fn default() -> Self {
+        Self {
+            max_steps: 1000,
+            use_target_threshold: true,
+            target_threshold: 0.5,
+            use_node_threshold: false,
+            node: 0,
+            node_threshold: 0.5,
+            use_convergence: false,
+            epsilon: 1e-6,
+        }
+    }
+}
+
+impl RunUntil {
+    /// Stops after `max_steps` steps and nothing else.
+    pub fn steps(max_steps: usize) -> Self {
+        Self {
+            max_steps,
+            use_target_threshold: false,
+            ..Default::default()
+        }
+    }
+
+    /// Half the l1 distance between two distributions.
+    pub fn total_variation(a: &DVector<f64>, b: &DVector<f64>) -> f64 {
+        if a.len() != b.len() {
+            return f64::INFINITY;
+        }
+        (a - b).abs().sum() / 2.0
+    }
+
+    /// Checks the stop conditions after `steps_taken` steps, given the node
+    /// probabilities before and after the last step and the accumulation at
+    /// each node.
+    pub fn check(
+        &self,
+        steps_taken: usize,
+        previous: &DVector<f64>,
+        probabilities: &DVector<f64>,
+        target_accumulation: &DVector<f64>,
+    ) -> Option<StopReason> {
+        let accumulated = target_accumulation.sum();
+        if self.use_target_threshold && accumulated >= self.target_threshold {
+            return Some(StopReason::TargetAccumulation(accumulated));
+        }
+        if self.use_node_threshold {
+            if let Some(p) = probabilities.get(self.node) {
+                if *p >= self.node_threshold {
+                    return Some(StopReason::NodeProbability(self.node, *p));
+                }
+            }
+        }
+        if self.use_convergence {
+            let change = Self::total_variation(previous, probabilities);
+            if change < self.epsilon {
+                return Some(StopReason::Converged(change));
+            }
+        }
+        (steps_taken >= self.max_steps).then_some(StopReason::MaxSteps)
+    }
+
+    /// Calls `step` until a condition is met, returning why the run stopped
+    /// and how many steps were taken. `step` moves the walk forward once and
+    /// returns the node probabilities and target accumulation, and `initial`
+    /// is the node probabilities before the first step.
+    pub fn run(
+        &self,
+        initial: DVector<f64>,
+        mut step: impl FnMut() -> Result<(DVector<f64>, DVector<f64>)>,
+    ) -> Result<(StopReason, usize)> {
+        let mut previous = initial;
+        let mut steps_taken = 0;
+        loop {
+            if steps_taken >= self.max_steps {
+                return Ok((StopReason::MaxSteps, steps_taken));
+            }
+            let (probabilities, target_accumulation) = step()?;
+            steps_taken += 1;
+            if let Some(reason) =
+                self.check(steps_taken, &previous, &probabilities, &target_accumulation)
+            {
+                return Ok((reason, steps_taken));
+            }
+            previous = probabilities;
+        }
+    }
+
+    /// Shows the stop conditions, returning whether a run was requested.
+    pub fn show(&mut self, ui: &mut egui::Ui, node_count: usize) -> bool {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.use_target_threshold, "Target accumulation ≥");
+            ui.add(
+                egui::DragValue::new(&mut self.target_threshold)
+                    .range(0.0..=1.0)
+                    .speed(0.01),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.use_node_threshold, "Probability of node");
+            ui.add(egui::DragValue::new(&mut self.node).range(0..=node_count.saturating_sub(1)));
+            ui.label("≥");
+            ui.add(
+                egui::DragValue::new(&mut self.node_threshold)
+                    .range(0.0..=1.0)
+                    .speed(0.01),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.use_convergence, "Change between steps <");
+            ui.add(
+                egui::DragValue::new(&mut self.epsilon)
+                    .range(0.0..=1.0)
+                    .speed(1e-6)
+                    .custom_formatter(|x, _| format!("{:.1e}", x)),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Maximum steps");
+            ui.add(egui::DragValue::new(&mut self.max_steps).range(1..=1_000_000));
+        });
+        ui.button("Run Until").clicked()
+    }
+}
@@ -0,0 +1,196 @@
+use nalgebra::{ComplexField, DMatrix, DVector};
+
+/// A matrix in compressed sparse row form, storing only its non-zero entries,
+/// so that walk operators on large graphs can be kept and applied without
+/// their mostly zero dense form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseMatrix<T> {
+    nrows: usize,
+    ncols: usize,
+    /// Where each row starts in `col_indices` and `values`, followed by the
+    /// number of stored entries.
+    row_offsets: Vec<usize>,
+    col_indices: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T: ComplexField + Copy> SparseMatrix<T> {
+    /// Builds a matrix from `(row, column, value)` entries, summing repeated
+    /// entries and dropping zeros.
+    pub fn from_triplets(
+        nrows: usize,
+        ncols: usize,
+        triplets: impl IntoIterator<Item = (usize, usize, T)>,
+    ) -> Self {
+        let mut triplets = triplets
+            .into_iter()
+            .filter(|(i, j, _)| *i < nrows && *j < ncols)
+            .collect::<Vec<_>>();
+        triplets.sort_unstable_by_key(|(i, j, _)| (*i, *j));
+
+        let mut res = Self {
+            nrows,
+            ncols,
+            row_offsets: vec![0; nrows + 1],
+            col_indices: Vec::with_capacity(triplets.len()),
+            values: Vec::with_capacity(triplets.len()),
+        };
+        let mut entries = triplets.into_iter().peekable();
+        for i in 0..nrows {
+            while let Some((_, j, mut value)) = entries.next_if(|(row, _, _)| *row == i) {
+                while let Some((_, _, x)) = entries.next_if(|(row, col, _)| *row == i && *col == j)
+                {
+                    value += x;
+                }
+                if value != T::zero() {
+                    res.col_indices.push(j);
+                    res.values.push(value);
+                }
+            }
+            res.row_offsets[i + 1] = res.values.len();
+        }
+        res
+    }
+
+    pub fn from_dense(matrix: &DMatrix<T>) -> Self {
+        Self::from_triplets(
+            matrix.nrows(),
+            matrix.ncols(),
+            (0..matrix.ncols())
+                .flat_map(|j| (0..matrix.nrows()).map(move |i| (i, j)))
+                .map(|(i, j)| (i, j, matrix[(i, j)])),
+        )
+    }
+
+    pub fn to_dense(&self) -> DMatrix<T> {
+        let mut res = DMatrix::from_element(self.nrows, self.ncols, T::zero());
+        for (i, j, value) in self.iter() {
+            res[(i, j)] = value;
+        }
+        res
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    /// The number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Every stored entry as `(row, column, value)`, in row order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, T)> + '_ {
+        (0..self.nrows).flat_map(move |i| {
+            (self.row_offsets[i]..self.row_offsets[i + 1])
+                .map(move |k| (i, self.col_indices[k], self.values[k]))
+        })
+    }
+
+    /// Applies `f` to every stored entry, given its row and column.
+    pub fn map_entries(&mut self, mut f: impl FnMut(usize, usize, T) -> T) {
+        for i in 0..self.nrows {
+            for k in self.row_offsets[i]..self.row_offsets[i + 1] {
+                self.values[k] = f(i, self.col_indices[k], self.values[k]);
+            }
+        }
+    }
+
+    /// The conjugate transpose.
+    pub fn adjoint(&self) -> Self {
+        Self::from_triplets(
+            self.ncols,
+            self.nrows,
+            self.iter().map(|(i, j, x)| (j, i, x.conjugate())),
+        )
+    }
+
+    pub fn mul_vector(&self, vector: &DVector<T>) -> DVector<T> {
+        assert_eq!(self.ncols, vector.len(), "Matrix dimensions do not match");
+        DVector::from_fn(self.nrows, |i, _| {
+            (self.row_offsets[i]..self.row_offsets[i + 1]).fold(T::zero(), |acc, k| {
+                acc + self.values[k] * vector[self.col_indices[k]]
+            })
+        })
+    }
+
+    pub fn mul_matrix(&self, matrix: &DMatrix<T>) -> DMatrix<T> {
+        assert_eq!(self.ncols, matrix.nrows(), "Matrix dimensions do not match");
+        let mut res = DMatrix::from_element(self.nrows, matrix.ncols(), T::zero());
+        for c in 0..matrix.ncols() {
+            let column = matrix.column(c);
+            for i in 0..self.nrows {
+                res[(i, c)] = (self.row_offsets[i]..self.row_offsets[i + 1])
+                    .fold(T::zero(), |acc, k| {
+                        acc + self.values[k] * column[self.col_indices[k]]
+                    });
+            }
+        }
+        res
+    }
+
+    pub fn mul_sparse(&self, other: &Self) -> Self {
+        assert_eq!(self.ncols, other.nrows, "Matrix dimensions do not match");
+        let mut triplets = Vec::with_capacity(self.nnz().max(other.nnz()));
+        for (i, k, a) in self.iter() {
+            for m in other.row_offsets[k]..other.row_offsets[k + 1] {
+                triplets.push((i, other.col_indices[m], a * other.values[m]));
+            }
+        }
+        Self::from_triplets(self.nrows, other.ncols, triplets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Complex;
+
+    #[test]
+    fn test_sparse_products_match_dense() {
+        let a = DMatrix::from_fn(4, 3, |i, j| {
+            if (i + j) % 2 == 0 {
+                Complex::new(i as f64, j as f64 - 1.0)
+            } else {
+                Complex::new(0.0, 0.0)
+            }
+        });
+        let b = DMatrix::from_fn(3, 5, |i, j| Complex::new((i * j) as f64 % 3.0, 0.5));
+        let sparse_a = SparseMatrix::from_dense(&a);
+        assert_eq!(sparse_a.nnz(), 6);
+        assert_eq!(sparse_a.to_dense(), a);
+
+        let v = DVector::from_fn(3, |i, _| Complex::new(1.0, i as f64));
+        assert_eq!(sparse_a.mul_vector(&v), &a * &v);
+        assert_eq!(sparse_a.adjoint().to_dense(), a.adjoint());
+        assert_eq!(sparse_a.mul_matrix(&b), &a * &b);
+        assert_eq!(
+            sparse_a
+                .mul_sparse(&SparseMatrix::from_dense(&b))
+                .to_dense(),
+            &a * &b
+        );
+
+        // repeated entries are summed and cancelled entries dropped
+        let sparse = SparseMatrix::from_triplets(
+            2,
+            2,
+            [
+                (1, 0, 2.0),
+                (0, 1, 1.0),
+                (1, 0, 3.0),
+                (0, 0, 1.0),
+                (0, 0, -1.0),
+            ],
+        );
+        assert_eq!(sparse.nnz(), 2);
+        assert_eq!(
+            sparse.to_dense(),
+            DMatrix::from_row_slice(2, 2, &[0.0, 1.0, 5.0, 0.0])
+        );
+    }
+}
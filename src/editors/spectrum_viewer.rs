@@ -0,0 +1,325 @@
+use anyhow::{anyhow, Result};
+use egui_plot::{Line, Plot, PlotPoints, Points};
+use nalgebra::{Complex, DMatrix, DVector, Schur};
+
+use super::ComplexTransitionMatrix;
+
+/// Eigenvalues closer than this are treated as the same eigenvalue.
+const EIGENVALUE_TOLERANCE: f64 = 1e-6;
+
+/// Shows the eigenvalues and eigenvectors of a walk's transition matrix, and
+/// lets one eigenvector be picked to start the walk from or colour the canvas.
+#[derive(Debug, Clone, Default)]
+pub struct SpectrumViewer {
+    /// The matrix the spectrum was last computed from.
+    source: Option<DMatrix<Complex<f64>>>,
+    is_unitary: bool,
+
+    /// Each distinct eigenvalue with its algebraic multiplicity and the
+    /// indexes of its eigenvectors.
+    eigenspaces: Vec<(Complex<f64>, usize, Vec<usize>)>,
+    eigenvectors: Vec<DVector<Complex<f64>>>,
+    error: Option<String>,
+
+    selected: Option<usize>,
+
+    /// Node-wise weight of the chosen eigenvector, drawn on the canvas in
+    /// place of the state while set.
+    pub canvas_weights: Option<DVector<f64>>,
+}
+
+impl SpectrumViewer {
+    /// Recomputes the spectrum of a unitary walk if its matrix has changed.
+    pub(crate) fn update_unitary(&mut self, transition_matrix: &ComplexTransitionMatrix) {
+        let matrix = transition_matrix.get_complex_matrix();
+        if self.source.as_ref() == Some(&*matrix) {
+            return;
+        }
+
+        self.clear(Some(matrix.into_owned()), true);
+        match transition_matrix.unitary_eigen() {
+            Ok((eigenvalues, eigenvectors)) => {
+                let eigenvalues = eigenvalues.iter().copied().collect::<Vec<_>>();
+                self.eigenvectors = eigenvectors.column_iter().map(|v| v.into_owned()).collect();
+                for (i, eigenvalue) in eigenvalues.iter().enumerate() {
+                    match self.find_eigenspace(eigenvalue) {
+                        Some((_, multiplicity, indexes)) => {
+                            *multiplicity += 1;
+                            indexes.push(i);
+                        }
+                        None => self.eigenspaces.push((*eigenvalue, 1, vec![i])),
+                    }
+                }
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    /// Recomputes the spectrum of a classical chain, where entry `(i, j)` of
+    /// `matrix` is the weight of moving from node `j` to node `i`, if it has
+    /// changed. Columns are normalized first. Only one eigenvector is found
+    /// for each distinct eigenvalue.
+    pub(crate) fn update_classical(&mut self, matrix: &DMatrix<f64>) {
+        let mut chain = matrix.map(|x| Complex::new(x, 0.0));
+        for mut column in chain.column_iter_mut() {
+            let sum = column.sum();
+            if sum.norm() != 0.0 {
+                column /= sum;
+            }
+        }
+        if self.source.as_ref() == Some(&chain) {
+            return;
+        }
+
+        self.clear(Some(chain.clone()), false);
+        let eigenvalues = match Self::eigenvalues(&chain) {
+            Ok(eigenvalues) => eigenvalues,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
+            }
+        };
+        for eigenvalue in eigenvalues.iter() {
+            if let Some((_, multiplicity, _)) = self.find_eigenspace(eigenvalue) {
+                *multiplicity += 1;
+                continue;
+            }
+            match Self::inverse_iteration(&chain, *eigenvalue) {
+                Ok(eigenvector) => {
+                    self.eigenspaces
+                        .push((*eigenvalue, 1, vec![self.eigenvectors.len()]));
+                    self.eigenvectors.push(eigenvector);
+                }
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    return;
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self, source: Option<DMatrix<Complex<f64>>>, is_unitary: bool) {
+        *self = Self {
+            source,
+            is_unitary,
+            ..Default::default()
+        };
+    }
+
+    fn find_eigenspace(
+        &mut self,
+        eigenvalue: &Complex<f64>,
+    ) -> Option<&mut (Complex<f64>, usize, Vec<usize>)> {
+        self.eigenspaces
+            .iter_mut()
+            .find(|(x, _, _)| (x - eigenvalue).norm() < EIGENVALUE_TOLERANCE)
+    }
+
+    /// Eigenvalues from a Schur decomposition of the lazy matrix `(M + I) / 2`,
+    /// which converges where periodic chains with eigenvalues of equal modulus
+    /// may not.
+    fn eigenvalues(matrix: &DMatrix<Complex<f64>>) -> Result<Vec<Complex<f64>>> {
+        let n = matrix.nrows();
+        let lazy = (matrix + DMatrix::identity(n, n)) * Complex::new(0.5, 0.0);
+        let eigenvalues = Schur::try_new(lazy, 1e-12, 10_000)
+            .and_then(|schur| schur.eigenvalues())
+            .ok_or_else(|| anyhow!("The eigenvalues did not converge"))?;
+        Ok(eigenvalues.iter().map(|x| x * 2.0 - 1.0).collect())
+    }
+
+    /// Finds the eigenvector of `eigenvalue` by repeatedly solving against the
+    /// matrix shifted to just off the eigenvalue.
+    fn inverse_iteration(
+        matrix: &DMatrix<Complex<f64>>,
+        eigenvalue: Complex<f64>,
+    ) -> Result<DVector<Complex<f64>>> {
+        let n = matrix.nrows();
+        let shift = eigenvalue + Complex::new(1e-10, 1e-10);
+        let lu = (matrix - DMatrix::identity(n, n) * shift).lu();
+        let mut vector = DVector::from_fn(n, |i, _| Complex::new(1.0, 0.1 * i as f64));
+        for _ in 0..3 {
+            vector = lu
+                .solve(&vector)
+                .ok_or_else(|| anyhow!("Could not find an eigenvector for {:.3}", eigenvalue))?;
+            vector /= Complex::from(vector.norm());
+        }
+        Ok(vector)
+    }
+
+    /// Shows the spectrum, returning an eigenvector when it is chosen as the
+    /// initial state. `labels` names each entry of an eigenvector and
+    /// `node_weights` turns an eigenvector into a weight for each node.
+    pub(crate) fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        labels: &[String],
+        node_weights: impl Fn(&DVector<Complex<f64>>) -> DVector<f64>,
+    ) -> Option<DVector<Complex<f64>>> {
+        if let Some(e) = &self.error {
+            ui.label(e);
+            return None;
+        }
+
+        self.show_plot(ui);
+
+        let mut res = None;
+        for (eigenvalue, multiplicity, indexes) in self.eigenspaces.iter() {
+            let phase = eigenvalue.arg() / std::f64::consts::PI;
+            ui.label(format!(
+                "λ = {:.3}, |λ| = {:.3}, phase = {:.3}π, multiplicity {}",
+                eigenvalue,
+                eigenvalue.norm(),
+                phase,
+                multiplicity
+            ));
+            ui.horizontal_wrapped(|ui| {
+                for i in indexes.iter() {
+                    if ui
+                        .selectable_label(self.selected == Some(*i), format!("v{}", i))
+                        .clicked()
+                    {
+                        self.selected = Some(*i);
+                    }
+                }
+            });
+        }
+
+        let selected = self.selected?;
+        let eigenvector = &self.eigenvectors[selected];
+
+        ui.separator();
+        ui.label(format!("Eigenvector v{}", selected));
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            egui::Grid::new("spectrum_eigenvector")
+                .striped(true)
+                .spacing([10.0, 10.0])
+                .show(ui, |ui| {
+                    for label in labels.iter() {
+                        ui.label(egui::RichText::new(label).strong());
+                    }
+                    ui.end_row();
+                    for x in eigenvector.iter() {
+                        if x.norm() < 1e-3 {
+                            ui.label("-");
+                        } else {
+                            ui.label(format!("{:.03}", x));
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Use as initial state").clicked() {
+                res = Some(eigenvector.clone());
+            }
+            if ui.button("Colour canvas by weight").clicked() {
+                self.canvas_weights = Some(node_weights(eigenvector));
+            }
+            if self.canvas_weights.is_some() && ui.button("Clear canvas colour").clicked() {
+                self.canvas_weights = None;
+            }
+        });
+
+        res
+    }
+
+    fn show_plot(&mut self, ui: &mut egui::Ui) {
+        let eigenvalues = self
+            .eigenspaces
+            .iter()
+            .map(|(x, _, _)| [x.re, x.im])
+            .collect::<Vec<_>>();
+        let selected = self
+            .selected
+            .and_then(|i| self.eigenspaces.iter().find(|(_, _, v)| v.contains(&i)))
+            .map(|(x, _, _)| [x.re, x.im]);
+
+        let response = Plot::new("spectrum_plot")
+            .data_aspect(1.0)
+            .height(250.0)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                if self.is_unitary {
+                    let circle = PlotPoints::from_parametric_callback(
+                        |t| (t.cos(), t.sin()),
+                        0.0..=std::f64::consts::TAU,
+                        100,
+                    );
+                    plot_ui.line(Line::new(circle).color(egui::Color32::GRAY));
+                }
+                plot_ui.points(
+                    Points::new(eigenvalues.clone())
+                        .radius(4.0_f32)
+                        .color(egui::Color32::LIGHT_BLUE),
+                );
+                if let Some(selected) = selected {
+                    plot_ui.points(
+                        Points::new(vec![selected])
+                            .radius(6.0_f32)
+                            .color(egui::Color32::YELLOW),
+                    );
+                }
+                (plot_ui.response().clicked(), plot_ui.pointer_coordinate())
+            });
+
+        // clicking an eigenvalue selects its first eigenvector
+        if let (true, Some(pointer)) = response.inner {
+            let closest = self.eigenspaces.iter().min_by(|(a, _, _), (b, _, _)| {
+                let distance = |x: &Complex<f64>| (x - Complex::new(pointer.x, pointer.y)).norm();
+                distance(a).total_cmp(&distance(b))
+            });
+            if let Some((x, _, indexes)) = closest {
+                if (x - Complex::new(pointer.x, pointer.y)).norm() < 0.1 {
+                    self.selected = indexes.first().copied();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_classical_spectrum() {
+        // the walk on a 4 cycle has eigenvalues 1, 0 (twice) and -1
+        let cycle = DMatrix::from_row_slice(
+            4,
+            4,
+            &[
+                0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0,
+            ],
+        );
+        let mut viewer = SpectrumViewer::default();
+        viewer.update_classical(&cycle);
+        assert_eq!(viewer.error, None);
+
+        let mut eigenvalues = viewer
+            .eigenspaces
+            .iter()
+            .map(|(x, multiplicity, _)| (x.re, *multiplicity))
+            .collect::<Vec<_>>();
+        eigenvalues.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        assert_eq!(eigenvalues.len(), 3);
+        assert_abs_diff_eq!(eigenvalues[0].0, -1.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(eigenvalues[1].0, 0.0, epsilon = 1e-9);
+        assert_eq!(eigenvalues[1].1, 2);
+        assert_abs_diff_eq!(eigenvalues[2].0, 1.0, epsilon = 1e-9);
+
+        // the eigenvector of 1 is the uniform distribution
+        let (_, _, indexes) = viewer
+            .eigenspaces
+            .iter()
+            .find(|(x, _, _)| (x.re - 1.0).abs() < 1e-9)
+            .unwrap();
+        let stationary = viewer.eigenvectors[indexes[0]].map(|x| x.norm());
+        assert_abs_diff_eq!(
+            (&stationary / stationary.sum()).as_slice(),
+            [0.25; 4].as_slice(),
+            epsilon = 1e-9
+        );
+    }
+}
@@ -21,7 +21,9 @@ impl StateManager {
         adjacency_list: &HashMap<usize, Vec<usize>>,
         labels: &[(usize, usize)],
     ) {
-        if let (Self::Complex(csm), Mode::Quantum) = (self, options.mode) {
+        if let (Self::Complex(csm), Mode::Quantum | Mode::ContinuousQuantum | Mode::Szegedy) =
+            (self, options.mode)
+        {
             csm.show(ui, adjacency_list, labels)
         }
     }
@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Result};
+use nalgebra::{DMatrix, DVector, Schur};
+
+/// Long run behaviour of a classical chain: where it converges, whether it
+/// can converge at all, and how quickly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationaryAnalysis {
+    /// The unique distribution left unchanged by a step, or `None` when the
+    /// chain has more than one closed class and so no unique distribution.
+    pub stationary: Option<DVector<f64>>,
+    /// Whether every node can reach every other node.
+    pub is_irreducible: bool,
+    /// The period of an irreducible chain, 1 when it is aperiodic.
+    pub period: Option<usize>,
+    /// `1 - |λ2|` where `λ2` is the eigenvalue with the second largest modulus,
+    /// or `None` if the eigenvalues could not be found.
+    pub spectral_gap: Option<f64>,
+    /// Steps until every start is within [`Self::MIXING_EPSILON`] of the
+    /// stationary distribution in total variation, or `None` when the chain
+    /// does not mix within [`Self::MAX_MIXING_STEPS`].
+    pub mixing_time: Option<usize>,
+}
+
+impl StationaryAnalysis {
+    pub const MIXING_EPSILON: f64 = 0.25;
+    pub const MAX_MIXING_STEPS: usize = 1000;
+
+    /// Analyses the chain where entry `(i, j)` of `stochastic` is the weight of
+    /// moving from node `j` to node `i`. Columns are normalized, and a node
+    /// with no outgoing weight is an error since the walker leaks out there.
+    pub fn new(stochastic: &DMatrix<f64>) -> Result<Self> {
+        if !stochastic.is_square() {
+            return Err(anyhow!("Matrix is not square"));
+        }
+        let n = stochastic.nrows();
+        if n == 0 {
+            return Err(anyhow!("The chain has no nodes"));
+        }
+
+        let mut chain = stochastic.clone();
+        for (j, mut column) in chain.column_iter_mut().enumerate() {
+            let sum = column.sum();
+            if sum == 0.0 {
+                return Err(anyhow!(
+                    "Node {} cannot be left, so the walker leaks out of the chain there",
+                    j
+                ));
+            }
+            column /= sum;
+        }
+
+        let is_irreducible = Self::reachable(&chain, 0, false)
+            .iter()
+            .all(|x| x.is_some())
+            && Self::reachable(&chain, 0, true).iter().all(|x| x.is_some());
+        let period = is_irreducible.then(|| Self::period(&chain));
+
+        let stationary = Self::stationary(&chain);
+
+        let spectral_gap = Self::spectral_gap(&chain);
+
+        let mixing_time = match (&stationary, period) {
+            (Some(stationary), Some(1)) => Self::mixing_time(&chain, stationary),
+            _ => None,
+        };
+
+        Ok(Self {
+            stationary,
+            is_irreducible,
+            period,
+            spectral_gap,
+            mixing_time,
+        })
+    }
+
+    /// Breadth first distances from `start`, following transitions backwards
+    /// when `reverse` is set.
+    fn reachable(chain: &DMatrix<f64>, start: usize, reverse: bool) -> Vec<Option<usize>> {
+        let n = chain.nrows();
+        let mut distances = vec![None; n];
+        distances[start] = Some(0);
+        let mut queue = VecDeque::from([start]);
+        while let Some(from) = queue.pop_front() {
+            for to in 0..n {
+                let weight = if reverse {
+                    chain[(from, to)]
+                } else {
+                    chain[(to, from)]
+                };
+                if weight > 0.0 && distances[to].is_none() {
+                    distances[to] = distances[from].map(|d| d + 1);
+                    queue.push_back(to);
+                }
+            }
+        }
+        distances
+    }
+
+    /// The gcd of the lengths of all cycles, found from the breadth first
+    /// levels of an irreducible chain.
+    fn period(chain: &DMatrix<f64>) -> usize {
+        fn gcd(a: usize, b: usize) -> usize {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+
+        let levels = Self::reachable(chain, 0, false);
+        let mut period = 0;
+        for from in 0..chain.ncols() {
+            for to in 0..chain.nrows() {
+                if let (true, Some(a), Some(b)) =
+                    (chain[(to, from)] > 0.0, levels[from], levels[to])
+                {
+                    period = gcd(period, (a + 1).abs_diff(b));
+                }
+            }
+        }
+        period
+    }
+
+    /// The Schur decomposition can fail to converge on periodic chains whose
+    /// eigenvalues share a modulus, so the eigenvalues are found for the lazy
+    /// chain `(P + I) / 2` instead and mapped back.
+    fn spectral_gap(chain: &DMatrix<f64>) -> Option<f64> {
+        let n = chain.nrows();
+        let lazy = (chain + DMatrix::identity(n, n)) * 0.5;
+        let schur = Schur::try_new(lazy, 1e-12, 10_000)?;
+        let mut moduli = schur
+            .complex_eigenvalues()
+            .iter()
+            .map(|x| (x * 2.0 - 1.0).norm())
+            .collect::<Vec<_>>();
+        moduli.sort_unstable_by(|a, b| b.total_cmp(a));
+        Some(1.0 - moduli.get(1).copied().unwrap_or(0.0))
+    }
+
+    /// Solves `(P - I)π = 0` with one equation replaced by `sum(π) = 1`.
+    fn stationary(chain: &DMatrix<f64>) -> Option<DVector<f64>> {
+        let n = chain.nrows();
+        let mut system = chain - DMatrix::identity(n, n);
+        system.row_mut(n - 1).fill(1.0);
+        let mut rhs = DVector::zeros(n);
+        rhs[n - 1] = 1.0;
+
+        let stationary = system.lu().solve(&rhs)?;
+        let residual = (chain * &stationary - &stationary).norm();
+        (residual < 1e-9 && stationary.iter().all(|x| *x > -1e-9))
+            .then(|| stationary.map(|x| x.max(0.0)))
+    }
+
+    fn mixing_time(chain: &DMatrix<f64>, stationary: &DVector<f64>) -> Option<usize> {
+        // column j of `distributions` is the walk started at node j
+        let n = chain.nrows();
+        let mut distributions = DMatrix::identity(n, n);
+        for step in 0..=Self::MAX_MIXING_STEPS {
+            let distance = distributions
+                .column_iter()
+                .map(|column| 0.5 * (column - stationary).abs().sum())
+                .fold(0.0, f64::max);
+            if distance <= Self::MIXING_EPSILON {
+                return Some(step);
+            }
+            distributions = chain * distributions;
+        }
+        None
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) {
+        ui.label(if self.is_irreducible {
+            "Irreducible"
+        } else {
+            "Reducible"
+        });
+        match self.period {
+            Some(1) => ui.label("Aperiodic"),
+            Some(period) => ui.label(format!("Periodic with period {}", period)),
+            None => ui.label("Period undefined for a reducible chain"),
+        };
+        match self.spectral_gap {
+            Some(gap) => ui.label(format!("Spectral gap: {:.4}", gap)),
+            None => ui.label("Spectral gap: eigenvalues did not converge"),
+        };
+        match self.mixing_time {
+            Some(steps) => ui.label(format!(
+                "Mixing time (epsilon = {}): {} steps",
+                Self::MIXING_EPSILON,
+                steps
+            )),
+            None => ui.label(format!(
+                "Mixing time (epsilon = {}): does not mix within {} steps",
+                Self::MIXING_EPSILON,
+                Self::MAX_MIXING_STEPS
+            )),
+        };
+        match &self.stationary {
+            Some(stationary) => {
+                for (i, p) in stationary.iter().enumerate() {
+                    ui.label(format!("Stationary probability at node {}: {:.4}", i, p));
+                }
+            }
+            None => {
+                ui.label("No unique stationary distribution");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_stationary_analysis() {
+        // a lazy walk on a path converges to the degree distribution
+        let path = DMatrix::from_row_slice(3, 3, &[0.0, 0.5, 0.0, 1.0, 0.0, 1.0, 0.0, 0.5, 0.0]);
+        let lazy = (&path + DMatrix::identity(3, 3)) * 0.5;
+        let analysis = StationaryAnalysis::new(&lazy).unwrap();
+        assert!(analysis.is_irreducible);
+        assert_eq!(analysis.period, Some(1));
+        assert_abs_diff_eq!(
+            analysis.stationary.unwrap().as_slice(),
+            [0.25, 0.5, 0.25].as_slice(),
+            epsilon = 1e-12
+        );
+        assert_abs_diff_eq!(analysis.spectral_gap.unwrap(), 0.5, epsilon = 1e-9);
+        assert!(analysis.mixing_time.is_some());
+
+        // the path itself is bipartite, so never mixes
+        let analysis = StationaryAnalysis::new(&path).unwrap();
+        assert_eq!(analysis.period, Some(2));
+        assert_abs_diff_eq!(analysis.spectral_gap.unwrap(), 0.0, epsilon = 1e-9);
+        assert_eq!(analysis.mixing_time, None);
+
+        // two separate self loops have no unique stationary distribution
+        let analysis = StationaryAnalysis::new(&DMatrix::identity(2, 2)).unwrap();
+        assert!(!analysis.is_irreducible);
+        assert_eq!(analysis.stationary, None);
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+/// The number of steps kept by default, enough to scrub back through a typical
+/// run without holding on to every state of a long one.
+const DEFAULT_CAPACITY: usize = 256;
+
+/// A bounded record of the state of a walk at consecutive steps, evicting the
+/// oldest step once full.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepHistory<T> {
+    capacity: usize,
+    entries: VecDeque<(usize, T)>,
+}
+
+impl<T> Default for StepHistory<T> {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            entries: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> StepHistory<T> {
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Keeps at most `capacity` steps, and at least one.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Records the state at `step`, forgetting any recorded from `step` on as
+    /// they belong to a run that has been stepped back from.
+    pub fn record(&mut self, step: usize, snapshot: T) {
+        while self.entries.back().is_some_and(|(s, _)| *s >= step) {
+            self.entries.pop_back();
+        }
+        if self.entries.back().is_some_and(|(s, _)| *s + 1 != step) {
+            self.entries.clear();
+        }
+        self.entries.push_back((step, snapshot));
+        if self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn get(&self, step: usize) -> Option<&T> {
+        let first = self.entries.front()?.0;
+        self.entries
+            .get(step.checked_sub(first)?)
+            .map(|(_, snapshot)| snapshot)
+    }
+
+    /// The steps that can be returned to.
+    pub fn range(&self) -> Option<RangeInclusive<usize>> {
+        Some(self.entries.front()?.0..=self.entries.back()?.0)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_history() {
+        let mut history = StepHistory::default();
+        history.set_capacity(3);
+        for step in 0..5 {
+            history.record(step, step * 10);
+        }
+        assert_eq!(history.range(), Some(2..=4));
+        assert_eq!(history.get(1), None);
+        assert_eq!(history.get(3), Some(&30));
+
+        // stepping forward again from step 3 replaces step 4
+        history.record(4, 41);
+        assert_eq!(history.get(4), Some(&41));
+        history.record(3, 31);
+        assert_eq!(history.range(), Some(2..=3));
+
+        // a step that does not follow on starts the history again
+        history.record(9, 90);
+        assert_eq!(history.range(), Some(9..=9));
+    }
+}
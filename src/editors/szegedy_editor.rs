@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use nalgebra::{Complex, DMatrix};
+
+use super::{ClassicalMatrixEditor, ComplexTransitionMatrix, Editor};
+
+/// Edits the classical chain of a Szegedy walk, exposing its quantization as
+/// the walk's combined matrix.
+#[derive(Debug, Clone)]
+pub struct SzegedyEditor {
+    /// The chain being quantized, shared with the classical mode so switching
+    /// between the two keeps any edits.
+    pub classical: ClassicalMatrixEditor,
+
+    applied_matrix: DMatrix<f64>,
+    walk_operator: DMatrix<Complex<f64>>,
+
+    adjacency_list: HashMap<usize, Vec<usize>>,
+    labels: Vec<(usize, usize)>,
+
+    is_matrix_update_ready: bool,
+}
+
+impl Editor for SzegedyEditor {
+    fn is_canvas_update_ready(&self) -> bool {
+        self.classical.is_canvas_update_ready()
+    }
+
+    fn on_canvas_updated(&mut self) {
+        self.classical.on_canvas_updated();
+    }
+}
+
+impl From<ClassicalMatrixEditor> for SzegedyEditor {
+    fn from(classical: ClassicalMatrixEditor) -> Self {
+        let mut res = Self {
+            classical,
+            applied_matrix: DMatrix::from_element(0, 0, 0.0),
+            walk_operator: DMatrix::from_element(0, 0, Complex::new(0.0, 0.0)),
+            adjacency_list: HashMap::new(),
+            labels: Vec::new(),
+            is_matrix_update_ready: false,
+        };
+        res.rebuild();
+        res
+    }
+}
+
+impl SzegedyEditor {
+    pub fn new(node_count: usize) -> Self {
+        Self::from(ClassicalMatrixEditor::new(node_count))
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.label("Classical transition matrix, each column is normalized before quantizing.");
+        self.classical.show(ui);
+        self.refresh();
+
+        ui.separator();
+        ui.heading("Preview");
+        ui.collapsing("Walk Operator", |ui| {
+            self.display_matrix(ui, &self.walk_operator);
+        });
+        ui.separator();
+    }
+
+    /// Recomputes the walk operator if the classical matrix has changed since
+    /// it was last built, either from the text fields or from the canvas.
+    pub(crate) fn refresh(&mut self) {
+        if self.applied_matrix != self.classical.matrix {
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.applied_matrix.clone_from(&self.classical.matrix);
+        let (walk_operator, labels) =
+            ComplexTransitionMatrix::szegedy_operator(&self.applied_matrix);
+        self.walk_operator = walk_operator;
+        self.adjacency_list = (0..self.applied_matrix.nrows())
+            .map(|x| {
+                let to = labels.iter().filter(|l| l.0 == x).map(|l| l.1).collect();
+                (x, to)
+            })
+            .collect();
+        self.labels = labels;
+        self.is_matrix_update_ready = true;
+    }
+
+    /// Whether the walk operator changed since the state manager last read it.
+    pub(crate) fn is_matrix_update_ready(&self) -> bool {
+        self.is_matrix_update_ready
+    }
+
+    pub(crate) fn on_matrix_updated(&mut self) {
+        self.is_matrix_update_ready = false;
+    }
+
+    fn display_matrix(&self, ui: &mut egui::Ui, matrix: &DMatrix<Complex<f64>>) {
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            egui::Grid::new("szegedy_matrix_preview")
+                .striped(true)
+                .spacing([10.0, 10.0])
+                .show(ui, |ui| {
+                    // column headers
+                    ui.label(""); // empty label to pad for row headers
+                    for l in self.labels.iter() {
+                        ui.label(egui::RichText::new(format!("{}->{}", l.0, l.1)).strong());
+                    }
+                    ui.end_row();
+
+                    // row headers and values
+                    for (i, l) in self.labels.iter().enumerate() {
+                        ui.label(egui::RichText::new(format!("{}->{}", l.0, l.1)).strong());
+                        for j in 0..self.labels.len() {
+                            if matrix[(i, j)].l1_norm() < 1e-3 {
+                                ui.label("-");
+                            } else {
+                                ui.label(format!("{:.03}", matrix[(i, j)]));
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    /// The Szegedy walk operator applied at each step.
+    pub fn get_combined_matrix(&self) -> &DMatrix<Complex<f64>> {
+        &self.walk_operator
+    }
+
+    pub fn get_labels(&self) -> &[(usize, usize)] {
+        &self.labels
+    }
+
+    pub fn get_adjacency_list(&self) -> &HashMap<usize, Vec<usize>> {
+        &self.adjacency_list
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct SerializedSzegedyEditor {
+    classical: ClassicalMatrixEditor,
+}
+impl From<SzegedyEditor> for SerializedSzegedyEditor {
+    fn from(m: SzegedyEditor) -> Self {
+        Self {
+            classical: m.classical,
+        }
+    }
+}
+impl From<SerializedSzegedyEditor> for SzegedyEditor {
+    fn from(m: SerializedSzegedyEditor) -> Self {
+        Self::from(m.classical)
+    }
+}
+impl serde::Serialize for SzegedyEditor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedSzegedyEditor::from(self.clone()).serialize(serializer)
+    }
+}
+impl<'de> serde::Deserialize<'de> for SzegedyEditor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SerializedSzegedyEditor::deserialize(deserializer).map(Self::from)
+    }
+}
@@ -0,0 +1,244 @@
+use egui::{Color32, Sense};
+use nalgebra::{Complex, DMatrix, DVector};
+use strum::{Display, VariantArray};
+
+use super::ComplexTransitionMatrix;
+
+/// How exchanging the two walkers changes the state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, VariantArray)]
+pub enum ParticleStatistics {
+    /// The walkers can be told apart, so the state is a plain product.
+    #[default]
+    Distinguishable,
+    /// The state is symmetric under exchange.
+    Bosonic,
+    /// The state is antisymmetric under exchange, so the walkers can never
+    /// share a half-edge.
+    Fermionic,
+}
+
+/// Two walkers evolving together under the same walk operator, on the tensor
+/// product of the half-edge space. The pair ignores decoherence, measurement
+/// and target nodes.
+#[derive(Debug, Clone)]
+pub struct TwoParticleWalk {
+    pub enabled: bool,
+    pub statistics: ParticleStatistics,
+    pub second_start_node_idx: usize,
+    /// Phase, as a multiple of pi, picked up each step the walkers share a
+    /// node.
+    pub interaction_phase: f64,
+    /// Entry `(a, b)` is the amplitude of the first walker on half-edge `a`
+    /// and the second on half-edge `b`.
+    state: DMatrix<Complex<f64>>,
+    error: Option<String>,
+}
+
+impl Default for TwoParticleWalk {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            statistics: ParticleStatistics::default(),
+            second_start_node_idx: 1,
+            interaction_phase: 0.0,
+            state: DMatrix::zeros(0, 0),
+            error: None,
+        }
+    }
+}
+
+impl TwoParticleWalk {
+    pub fn new(
+        statistics: ParticleStatistics,
+        second_start_node_idx: usize,
+        interaction_phase: f64,
+    ) -> Self {
+        Self {
+            enabled: true,
+            statistics,
+            second_start_node_idx,
+            interaction_phase,
+            ..Default::default()
+        }
+    }
+
+    /// Starts the pair from the single walker states `first` and `second`,
+    /// symmetrised or antisymmetrised to match the statistics.
+    pub(crate) fn reset(&mut self, first: &DVector<Complex<f64>>, second: &DVector<Complex<f64>>) {
+        let product = first * second.transpose();
+        let state = match self.statistics {
+            ParticleStatistics::Distinguishable => product,
+            ParticleStatistics::Bosonic => &product + product.transpose(),
+            ParticleStatistics::Fermionic => &product - product.transpose(),
+        };
+
+        let norm = state.norm();
+        if norm < 1e-12 {
+            self.state = DMatrix::zeros(first.len(), second.len());
+            self.error = Some(match self.statistics {
+                ParticleStatistics::Fermionic => {
+                    "Fermions cannot both start in the same state".to_string()
+                }
+                _ => "The walkers have no state to start from".to_string(),
+            });
+            return;
+        }
+        self.state = state / Complex::from(norm);
+        self.error = None;
+    }
+
+    /// Steps both walkers, then applies the interaction phase wherever they
+    /// share a node.
+    pub(crate) fn step(
+        &mut self,
+        transition_matrix: &ComplexTransitionMatrix,
+        labels: &[(usize, usize)],
+    ) {
+        if self.state.nrows() != transition_matrix.dimension() {
+            return;
+        }
+        self.state = transition_matrix.apply_to_pair(&self.state);
+
+        if self.interaction_phase == 0.0 || labels.len() != self.state.nrows() {
+            return;
+        }
+        let phase = Complex::new(0.0, self.interaction_phase * std::f64::consts::PI).exp();
+        for a in 0..labels.len() {
+            for b in 0..labels.len() {
+                if labels[a].0 == labels[b].0 {
+                    self.state[(a, b)] *= phase;
+                }
+            }
+        }
+    }
+
+    pub fn get_state(&self) -> &DMatrix<Complex<f64>> {
+        &self.state
+    }
+
+    pub fn get_error(&self) -> Option<&String> {
+        self.error.as_ref()
+    }
+
+    /// Entry `(i, j)` is the probability of finding the first walker at node
+    /// `i` and the second at node `j`.
+    pub(crate) fn get_correlations(
+        &self,
+        labels: &[(usize, usize)],
+        node_count: usize,
+    ) -> DMatrix<f64> {
+        let mut res = DMatrix::zeros(node_count, node_count);
+        if labels.len() != self.state.nrows() {
+            return res;
+        }
+        for (a, (i, _)) in labels.iter().enumerate() {
+            for (b, (j, _)) in labels.iter().enumerate() {
+                if *i < node_count && *j < node_count {
+                    res[(*i, *j)] += self.state[(a, b)].norm_sqr();
+                }
+            }
+        }
+        res
+    }
+
+    /// The probability of finding the first and the second walker at each
+    /// node, which are equal unless the walkers are distinguishable.
+    pub(crate) fn get_marginals(correlations: &DMatrix<f64>) -> (DVector<f64>, DVector<f64>) {
+        (
+            correlations.column_sum(),
+            correlations.row_sum().transpose(),
+        )
+    }
+
+    /// Shows the two particle settings, returning whether any were changed.
+    pub fn show(&mut self, ui: &mut egui::Ui, node_count: usize) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.enabled, "Walk a second particle alongside")
+            .changed();
+        if !self.enabled {
+            return changed;
+        }
+
+        egui::ComboBox::from_label("Statistics")
+            .selected_text(format!("{}", self.statistics))
+            .show_ui(ui, |ui| {
+                for statistics in ParticleStatistics::VARIANTS {
+                    changed |= ui
+                        .selectable_value(
+                            &mut self.statistics,
+                            *statistics,
+                            format!("{}", statistics),
+                        )
+                        .changed();
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.label("Second start node");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.second_start_node_idx)
+                        .range(0..=node_count.saturating_sub(1)),
+                )
+                .changed();
+        });
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.interaction_phase, -1.0..=1.0)
+                    .text("On-site interaction phase (π)"),
+            )
+            .changed();
+
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::from_rgb(255, 50, 50), e);
+        }
+
+        changed
+    }
+
+    /// Draws `correlations` as a heatmap, brightest where the pair is most
+    /// likely to be found.
+    pub(crate) fn show_correlations(ui: &mut egui::Ui, correlations: &DMatrix<f64>) {
+        let n = correlations.nrows();
+        if n == 0 {
+            return;
+        }
+        let max = correlations.max().max(f64::EPSILON);
+        let cell_size = (240.0 / n as f32).clamp(4.0, 24.0);
+        let (response, painter) = ui.allocate_painter(
+            egui::vec2(cell_size * n as f32, cell_size * n as f32),
+            Sense::hover(),
+        );
+        let origin = response.rect.min;
+        for i in 0..n {
+            for j in 0..n {
+                let intensity = (correlations[(i, j)] / max).sqrt();
+                let rect = egui::Rect::from_min_size(
+                    origin + egui::vec2(j as f32 * cell_size, i as f32 * cell_size),
+                    egui::vec2(cell_size, cell_size),
+                );
+                painter.rect_filled(
+                    rect,
+                    0.0,
+                    Color32::from_rgb(
+                        (255.0 * intensity) as u8,
+                        (160.0 * intensity) as u8,
+                        (40.0 * intensity) as u8,
+                    ),
+                );
+            }
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let i = ((pos.y - origin.y) / cell_size) as usize;
+            let j = ((pos.x - origin.x) / cell_size) as usize;
+            if i < n && j < n {
+                response.on_hover_text(format!(
+                    "First at {}, second at {}: {:.04}",
+                    i,
+                    j,
+                    correlations[(i, j)]
+                ));
+            }
+        }
+    }
+}
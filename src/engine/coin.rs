@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use anyhow::{anyhow, Result};
+use nalgebra::{Complex, DMatrix};
+
+/// How far a coin may be from unitary, as the norm of `C^dagger C - I`, before
+/// walking with it fails.
+pub(crate) const MAX_UNITARY_ERROR: f64 = 1e-8;
+
+/// The coin applied at each node of a coined quantum walk. A node with `d`
+/// neighbours uses a `d` by `d` coin acting on its outgoing half-edges.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Coin {
+    /// Grover diffusion coin, `2/d - I`.
+    Grover,
+    /// Discrete Fourier transform coin.
+    Fourier,
+    /// Explicit coin for every node with edges, keyed by node index.
+    Custom(HashMap<usize, DMatrix<Complex<f64>>>),
+}
+
+impl Coin {
+    /// The coin of `node`, failing for custom coins that are missing, the
+    /// wrong size or not unitary. Custom coins are never corrected to be
+    /// unitary, so that a walk behaves the same whatever the size of its graph.
+    pub fn matrix_for(&self, node: usize, degree: usize) -> Result<DMatrix<Complex<f64>>> {
+        match self {
+            Coin::Grover => Ok(DMatrix::from_fn(degree, degree, |i, j| {
+                let diagonal = if i == j { 1.0 } else { 0.0 };
+                Complex::new(2.0 / degree as f64 - diagonal, 0.0)
+            })),
+            Coin::Fourier => {
+                let scale = 1.0 / (degree as f64).sqrt();
+                Ok(DMatrix::from_fn(degree, degree, |i, j| {
+                    let x = (i * j) as f64 * TAU / degree as f64;
+                    Complex::new(x.cos() * scale, x.sin() * scale)
+                }))
+            }
+            Coin::Custom(coins) => {
+                let coin = coins
+                    .get(&node)
+                    .ok_or_else(|| anyhow!("No coin given for node {}", node))?;
+                if coin.nrows() != degree || coin.ncols() != degree {
+                    return Err(anyhow!(
+                        "Coin for node {} is {}x{} but the node has {} edges",
+                        node,
+                        coin.nrows(),
+                        coin.ncols(),
+                        degree
+                    ));
+                }
+                if !Self::is_unitary(coin) {
+                    return Err(anyhow!("Coin for node {} is not unitary", node));
+                }
+                Ok(coin.clone())
+            }
+        }
+    }
+    /// Whether every entry of `coin` is finite and `coin` is unitary to within
+    /// [`MAX_UNITARY_ERROR`].
+    pub(crate) fn is_unitary(coin: &DMatrix<Complex<f64>>) -> bool {
+        coin.iter().all(|x| x.re.is_finite() && x.im.is_finite())
+            && coin.is_square()
+            && (coin.adjoint() * coin - DMatrix::identity(coin.nrows(), coin.ncols())).norm()
+                <= MAX_UNITARY_ERROR
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::editors::RunUntil;
+
+use super::{Coin, Graph, Simulation, SimulationMode};
+
+/// A classical and a coined quantum walk on the same graph, from the same
+/// start node and with the same targets, stepped in lockstep.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    classical: Simulation,
+    quantum: Simulation,
+    /// The total variation distance between the two node distributions at
+    /// each step.
+    distances: Vec<f64>,
+    /// The total target accumulation of the classical and quantum walk at
+    /// each step.
+    accumulation: Vec<(f64, f64)>,
+}
+
+impl Comparison {
+    /// The classical walk moves to each neighbour in proportion to the weight
+    /// of the edge to it, and the quantum walk uses `coin` at every node.
+    pub fn new(
+        graph: Graph,
+        coin: Coin,
+        start_node_idx: usize,
+        target_node_indexes: HashSet<usize>,
+    ) -> Result<Self> {
+        let matrix = graph.uniform_transition_matrix();
+        let classical = Simulation::new(
+            graph.clone(),
+            SimulationMode::Classical(matrix),
+            start_node_idx,
+            target_node_indexes.clone(),
+        )?;
+        let quantum = Simulation::new(
+            graph,
+            SimulationMode::Quantum(coin),
+            start_node_idx,
+            target_node_indexes,
+        )?;
+
+        let mut res = Self {
+            classical,
+            quantum,
+            distances: Vec::new(),
+            accumulation: Vec::new(),
+        };
+        res.record();
+        Ok(res)
+    }
+
+    fn record(&mut self) {
+        self.distances.push(RunUntil::total_variation(
+            &self.classical.get_node_probabilities(),
+            &self.quantum.get_node_probabilities(),
+        ));
+        self.accumulation.push((
+            self.classical.get_target_accumulation().sum(),
+            self.quantum.get_target_accumulation().sum(),
+        ));
+    }
+
+    pub fn step(&mut self) -> Result<()> {
+        self.classical.step()?;
+        self.quantum.step()?;
+        self.record();
+        Ok(())
+    }
+
+    pub fn run(&mut self, steps: usize) -> Result<()> {
+        for _ in 0..steps {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        self.classical.reset();
+        self.quantum.reset();
+        self.distances.clear();
+        self.accumulation.clear();
+        self.record();
+    }
+
+    pub fn get_step(&self) -> usize {
+        self.classical.get_step()
+    }
+
+    pub fn get_classical(&self) -> &Simulation {
+        &self.classical
+    }
+
+    pub fn get_quantum(&self) -> &Simulation {
+        &self.quantum
+    }
+
+    /// The total variation distance between the walks at every step so far.
+    pub fn get_distances(&self) -> &[f64] {
+        &self.distances
+    }
+
+    /// The first step at which the classical and quantum walk had each
+    /// accumulated at least `threshold` at the targets, if they have yet.
+    pub fn time_to_threshold(&self, threshold: f64) -> (Option<usize>, Option<usize>) {
+        (
+            self.accumulation.iter().position(|(c, _)| *c >= threshold),
+            self.accumulation.iter().position(|(_, q)| *q >= threshold),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_comparison() {
+        let graph = Graph::new(6, (0..6).map(|i| (i, (i + 1) % 6)).collect()).unwrap();
+        let mut comparison = Comparison::new(graph, Coin::Grover, 0, HashSet::from([3])).unwrap();
+        comparison.run(2).unwrap();
+
+        // the Grover coin on a cycle moves the walker ballistically, while the
+        // classical walker stays spread around the start
+        assert_eq!(comparison.get_step(), 2);
+        assert_abs_diff_eq!(comparison.get_distances()[0], 0.0);
+        assert_abs_diff_eq!(comparison.get_distances()[1], 0.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(comparison.get_distances()[2], 0.5, epsilon = 1e-9);
+
+        // both halves of the quantum walker reach the target together
+        comparison.step().unwrap();
+        assert_eq!(comparison.time_to_threshold(0.5), (None, Some(3)));
+        assert_abs_diff_eq!(
+            comparison.get_quantum().get_target_accumulation()[3],
+            1.0,
+            epsilon = 1e-9
+        );
+
+        comparison.reset();
+        assert_eq!(comparison.get_step(), 0);
+        assert_eq!(comparison.get_distances().len(), 1);
+    }
+}
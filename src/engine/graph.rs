@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use nalgebra::DMatrix;
+
+use crate::editors::ComplexMatrixEditor;
+
+/// A graph described purely by its node count and the node index pairs of its
+/// edges, as built from [`crate::canvas::Canvas::get_weighted_arcs`] by
+/// [`Graph::from_weighted_arcs`].
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Graph {
+    pub node_count: usize,
+    /// Undirected edges, which can be traversed in both directions.
+    pub edges: Vec<(usize, usize)>,
+    /// Directed edges, which can only be traversed from the first node to the
+    /// second.
+    #[serde(default)]
+    pub directed_edges: Vec<(usize, usize)>,
+    /// `(from, to, weight)` for every arc whose weight is not 1.
+    #[serde(default)]
+    pub weights: Vec<(usize, usize, f64)>,
+}
+
+impl Graph {
+    pub fn new(node_count: usize, edges: Vec<(usize, usize)>) -> Result<Self> {
+        Self::check_edges(node_count, &edges)?;
+        Ok(Self {
+            node_count,
+            edges,
+            directed_edges: Vec::new(),
+            weights: Vec::new(),
+        })
+    }
+
+    /// Adds edges that can only be traversed from the first node to the second.
+    pub fn with_directed_edges(mut self, directed_edges: Vec<(usize, usize)>) -> Result<Self> {
+        Self::check_edges(self.node_count, &directed_edges)?;
+        self.directed_edges = directed_edges;
+        Ok(self)
+    }
+
+    /// Builds a graph from `(from, to)` arcs, as returned by
+    /// [`crate::canvas::Canvas::get_arcs_as_idx_tuples`]. Arcs that appear in
+    /// both directions become a single undirected edge.
+    pub fn from_arcs(node_count: usize, arcs: &[(usize, usize)]) -> Result<Self> {
+        let arc_set = arcs.iter().copied().collect::<HashSet<_>>();
+        let (mut edges, mut directed_edges) = (Vec::new(), Vec::new());
+        for &(a, b) in arc_set.iter() {
+            if !arc_set.contains(&(b, a)) {
+                directed_edges.push((a, b));
+            } else if a <= b {
+                edges.push((a, b));
+            }
+        }
+        edges.sort_unstable();
+        directed_edges.sort_unstable();
+        Self::new(node_count, edges)?.with_directed_edges(directed_edges)
+    }
+
+    /// Same as [`Self::from_arcs`] but also keeps the weight of each arc, as
+    /// returned by [`crate::canvas::Canvas::get_weighted_arcs`].
+    pub fn from_weighted_arcs(node_count: usize, arcs: &[(usize, usize, f64)]) -> Result<Self> {
+        let mut res = Self::from_arcs(
+            node_count,
+            &arcs.iter().map(|&(a, b, _)| (a, b)).collect::<Vec<_>>(),
+        )?;
+        res.weights = arcs
+            .iter()
+            .copied()
+            .filter(|(_, _, weight)| *weight != 1.0)
+            .collect();
+        Ok(res)
+    }
+
+    fn check_edges(node_count: usize, edges: &[(usize, usize)]) -> Result<()> {
+        if let Some((a, b)) = edges
+            .iter()
+            .find(|(a, b)| *a >= node_count || *b >= node_count)
+        {
+            return Err(anyhow!(
+                "Edge {}->{} references a node outside of the {} node graph",
+                a,
+                b,
+                node_count
+            ));
+        }
+        Ok(())
+    }
+
+    /// Every direction the walker can move in as sorted `(from, to)` pairs.
+    pub fn arcs(&self) -> Vec<(usize, usize)> {
+        let mut arcs = self
+            .edges
+            .iter()
+            .flat_map(|&(a, b)| [(a, b), (b, a)])
+            .chain(self.directed_edges.iter().copied())
+            .collect::<Vec<_>>();
+        arcs.sort_unstable();
+        arcs.dedup();
+        arcs
+    }
+
+    /// The weight of moving from one node to another, which is 1 unless set.
+    pub fn weight(&self, from: usize, to: usize) -> f64 {
+        self.weights
+            .iter()
+            .find(|(a, b, _)| *a == from && *b == to)
+            .map_or(1.0, |(_, _, weight)| *weight)
+    }
+
+    /// [`Self::arcs`] with the weight of each.
+    pub fn weighted_arcs(&self) -> Vec<(usize, usize, f64)> {
+        // the first weight given for an arc wins, as in `Self::weight`
+        let weights = self
+            .weights
+            .iter()
+            .rev()
+            .map(|&(a, b, weight)| ((a, b), weight))
+            .collect::<HashMap<_, _>>();
+        self.arcs()
+            .into_iter()
+            .map(|(a, b)| (a, b, weights.get(&(a, b)).copied().unwrap_or(1.0)))
+            .collect()
+    }
+
+    pub fn is_directed(&self) -> bool {
+        !self.directed_edges.is_empty()
+    }
+
+    /// Adjacency list of outgoing neighbours, sorted. Every node has an entry,
+    /// so isolated nodes map to an empty list.
+    pub fn adjacency_list(&self) -> HashMap<usize, Vec<usize>> {
+        let mut adjacency_list = ComplexMatrixEditor::new_adjacency_list(&self.arcs());
+        for i in 0..self.node_count {
+            adjacency_list.entry(i).or_default();
+        }
+        adjacency_list
+    }
+
+    /// Half-edge labels `(from, to)` in the order used by the quantum walk.
+    pub fn half_edge_labels(&self) -> Vec<(usize, usize)> {
+        ComplexMatrixEditor::new_labels(&self.adjacency_list())
+    }
+
+    /// Labels for walks that live on the nodes rather than the half-edges, with
+    /// every node pointing at itself.
+    pub fn node_labels(&self) -> Vec<(usize, usize)> {
+        (0..self.node_count).map(|i| (i, i)).collect()
+    }
+
+    /// Adjacency matrix where entry `(i, j)` is the weight of moving from node
+    /// `j` to node `i`, which is symmetric for undirected graphs.
+    pub fn adjacency_matrix(&self) -> DMatrix<f64> {
+        let mut matrix = DMatrix::from_element(self.node_count, self.node_count, 0.0);
+        for (a, b, weight) in self.weighted_arcs() {
+            matrix[(b, a)] = weight;
+        }
+        matrix
+    }
+
+    /// Graph Laplacian `D - A`, where `D` holds the weighted outgoing node
+    /// degrees.
+    pub fn laplacian(&self) -> DMatrix<f64> {
+        let adjacency_matrix = self.adjacency_matrix();
+        // column j holds the arcs leaving node j
+        let degrees = adjacency_matrix.row_sum_tr();
+        DMatrix::from_diagonal(&degrees) - adjacency_matrix
+    }
+
+    /// The NxN matrix the classical editor fills in when edges are drawn, where
+    /// each edge is taken with probability weight / weighted degree.
+    pub fn uniform_transition_matrix(&self) -> DMatrix<f64> {
+        let mut matrix = self.adjacency_matrix();
+        for mut column in matrix.column_iter_mut() {
+            let weighted_degree = column.sum();
+            if weighted_degree != 0.0 {
+                column /= weighted_degree;
+            }
+        }
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directed_laplacian() {
+        // 0 -> 1 <-> 2, with the arc 0 -> 1 weighted, so that only node 1 has
+        // more arcs arriving than leaving
+        let graph = Graph::from_weighted_arcs(3, &[(0, 1, 2.0), (1, 2, 1.0), (2, 1, 1.0)]).unwrap();
+        let laplacian = graph.laplacian();
+        assert_eq!(laplacian.diagonal().as_slice(), [2.0, 1.0, 1.0].as_slice());
+        assert_eq!(laplacian[(1, 0)], -2.0);
+        assert_eq!(laplacian[(0, 1)], 0.0);
+        // every column sums to zero, so the walk generated by -L conserves
+        // probability
+        for column in laplacian.column_iter() {
+            assert_eq!(column.sum(), 0.0);
+        }
+    }
+}
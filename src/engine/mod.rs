@@ -0,0 +1,13 @@
+mod coin;
+mod comparison;
+mod graph;
+mod parameter_sweep;
+mod simulation;
+mod simulation_mode;
+
+pub use coin::Coin;
+pub use comparison::Comparison;
+pub use graph::Graph;
+pub use parameter_sweep::{ParameterSweep, SweepMetric, SweepPoint};
+pub use simulation::Simulation;
+pub use simulation_mode::SimulationMode;
@@ -0,0 +1,339 @@
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{anyhow, Result};
+use strum::{Display, VariantArray};
+
+use super::{Coin, Graph, Simulation, SimulationMode};
+
+/// The quantity plotted against the swept parameter.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Display, VariantArray, serde::Serialize, serde::Deserialize,
+)]
+pub enum SweepMetric {
+    #[strum(to_string = "Target probability")]
+    TargetProbability,
+    #[strum(to_string = "Hitting step")]
+    HittingStep,
+    #[strum(to_string = "Spread")]
+    Spread,
+}
+
+/// Every metric measured for one value of the swept parameter.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SweepPoint {
+    pub value: f64,
+    /// The probability accumulated at the targets once every step has run.
+    pub target_probability: f64,
+    /// The first step at which the target probability reached the threshold,
+    /// if it did within the steps run.
+    pub hitting_step: Option<usize>,
+    /// The root mean square graph distance of the walker from the start node
+    /// once every step has run.
+    pub spread: f64,
+}
+
+impl SweepPoint {
+    pub fn metric(&self, metric: SweepMetric) -> Option<f64> {
+        match metric {
+            SweepMetric::TargetProbability => Some(self.target_probability),
+            SweepMetric::HittingStep => self.hitting_step.map(|x| x as f64),
+            SweepMetric::Spread => Some(self.spread),
+        }
+    }
+}
+
+/// Reruns a coined quantum walk for a fixed number of steps with a coin built
+/// from each value of a parameter.
+#[derive(Debug, Clone)]
+pub struct ParameterSweep {
+    graph: Graph,
+    start_node_idx: usize,
+    target_node_indexes: HashSet<usize>,
+    steps: usize,
+    /// The target probability that counts as the walker having hit.
+    threshold: f64,
+    /// Whether the coin is built again before every step rather than once.
+    is_time_dependent: bool,
+}
+
+impl ParameterSweep {
+    pub fn new(
+        graph: Graph,
+        start_node_idx: usize,
+        target_node_indexes: HashSet<usize>,
+        steps: usize,
+    ) -> Result<Self> {
+        if start_node_idx >= graph.node_count {
+            return Err(anyhow!(
+                "Start node {} is outside of the {} node graph",
+                start_node_idx,
+                graph.node_count
+            ));
+        }
+        Ok(Self {
+            graph,
+            start_node_idx,
+            target_node_indexes,
+            steps,
+            threshold: 0.5,
+            is_time_dependent: false,
+        })
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Builds the coin for every step from the step it is walked from, rather
+    /// than only for the first.
+    pub fn with_time_dependent_coin(mut self, is_time_dependent: bool) -> Self {
+        self.is_time_dependent = is_time_dependent;
+        self
+    }
+
+    /// `count` values evenly spaced from `from` to `to` inclusive.
+    pub fn values(from: f64, to: f64, count: usize) -> Vec<f64> {
+        match count {
+            0 => Vec::new(),
+            1 => vec![from],
+            _ => (0..count)
+                .map(|i| from + (to - from) * i as f64 / (count - 1) as f64)
+                .collect(),
+        }
+    }
+
+    /// Runs the walk once for each of `values`, using the coin `coin_for`
+    /// builds from the value and the step walked from.
+    pub fn run(
+        &self,
+        values: &[f64],
+        mut coin_for: impl FnMut(f64, usize) -> Result<Coin>,
+    ) -> Result<Vec<SweepPoint>> {
+        let distances = self.distances_from_start();
+        values
+            .iter()
+            .map(|&value| {
+                self.run_once(value, |t| coin_for(value, t), &distances)
+                    .map_err(|e| anyhow!("Parameter value {}: {}", value, e))
+            })
+            .collect()
+    }
+
+    fn run_once(
+        &self,
+        value: f64,
+        mut coin_for: impl FnMut(usize) -> Result<Coin>,
+        distances: &[Option<usize>],
+    ) -> Result<SweepPoint> {
+        let mut simulation = Simulation::new(
+            self.graph.clone(),
+            SimulationMode::Quantum(coin_for(0)?),
+            self.start_node_idx,
+            self.target_node_indexes.clone(),
+        )?;
+
+        let has_hit =
+            |simulation: &Simulation| simulation.get_target_accumulation().sum() >= self.threshold;
+        let mut hitting_step = has_hit(&simulation).then_some(0);
+        for step in 1..=self.steps {
+            if self.is_time_dependent && step > 1 {
+                simulation.set_coin(coin_for(step - 1)?)?;
+            }
+            simulation.step()?;
+            if hitting_step.is_none() && has_hit(&simulation) {
+                hitting_step = Some(step);
+            }
+        }
+
+        let spread = simulation
+            .get_node_probabilities()
+            .iter()
+            .zip(distances)
+            .filter_map(|(p, d)| d.map(|d| p * (d * d) as f64))
+            .sum::<f64>()
+            .sqrt();
+        Ok(SweepPoint {
+            value,
+            target_probability: simulation.get_target_accumulation().sum(),
+            hitting_step,
+            spread,
+        })
+    }
+
+    /// The number of arcs from the start node to each node, if it can be
+    /// reached at all.
+    fn distances_from_start(&self) -> Vec<Option<usize>> {
+        let adjacency_list = self.graph.adjacency_list();
+        let mut distances = vec![None; self.graph.node_count];
+        distances[self.start_node_idx] = Some(0);
+        let mut queue = VecDeque::from([self.start_node_idx]);
+        while let Some(node) = queue.pop_front() {
+            let distance = distances[node].unwrap();
+            for &to in adjacency_list[&node].iter() {
+                if distances[to].is_none() {
+                    distances[to] = Some(distance + 1);
+                    queue.push_back(to);
+                }
+            }
+        }
+        distances
+    }
+
+    /// The points as CSV with a header row, naming the parameter column after
+    /// `parameter`. Hitting steps that were never reached are left empty.
+    pub fn to_csv(parameter: &str, points: &[SweepPoint]) -> String {
+        let mut csv = format!("{},target_probability,hitting_step,spread\n", parameter);
+        for point in points {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                point.value,
+                point.target_probability,
+                point.hitting_step.map_or(String::new(), |x| x.to_string()),
+                point.spread
+            ));
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editors::ComplexMatrixEditor;
+    use approx::assert_abs_diff_eq;
+    use evalexpr::{Context, Value};
+    use nalgebra::{Complex, DMatrix};
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    fn cycle_graph() -> Graph {
+        Graph::new(8, (0..8).map(|i| (i, (i + 1) % 8)).collect()).unwrap()
+    }
+
+    /// A coin that swaps or keeps the two half-edges of every node of the
+    /// cycle by an amount set by `theta`.
+    fn coin_for(theta: f64, _: usize) -> Result<Coin> {
+        let (c, s) = (
+            Complex::new(theta.cos(), 0.0),
+            Complex::new(theta.sin(), 0.0),
+        );
+        let coin = DMatrix::from_row_slice(2, 2, &[c, s, s, -c]);
+        Ok(Coin::Custom((0..8).map(|i| (i, coin.clone())).collect()))
+    }
+
+    /// Like [`coin_for`], but with a coin that is the same whichever half-edge
+    /// of a node comes first, so it does not depend on the order of the arcs.
+    fn symmetric_coin_for(theta: f64, _: usize) -> Result<Coin> {
+        let (c, s) = (
+            Complex::new(theta.cos(), 0.0),
+            Complex::new(0.0, theta.sin()),
+        );
+        let coin = DMatrix::from_row_slice(2, 2, &[c, s, s, c]);
+        Ok(Coin::Custom((0..8).map(|i| (i, coin.clone())).collect()))
+    }
+
+    /// An editor on the cycle with [`symmetric_coin_for`] typed in for every
+    /// node.
+    fn editor_with_theta_coin() -> ComplexMatrixEditor {
+        let mut editor = ComplexMatrixEditor::new(&cycle_graph().arcs());
+        for field in editor.text_fields.iter_mut().flatten().flatten() {
+            *field = (
+                String::from("if(row == col, math::cos(theta), 0)"),
+                String::from("if(row == col, 0, math::sin(theta))"),
+            );
+        }
+        editor
+    }
+
+    #[test]
+    fn test_parameter_sweep() {
+        let sweep = ParameterSweep::new(cycle_graph(), 0, HashSet::from([4]), 6).unwrap();
+        let values = ParameterSweep::values(0.0, FRAC_PI_2, 3);
+        assert_eq!(values.len(), 3);
+        let points = sweep.run(&values, coin_for).unwrap();
+
+        // a coin that never turns the walker around moves it ballistically
+        // onto the target after four steps
+        assert_eq!(points[2].hitting_step, Some(4));
+        assert_abs_diff_eq!(points[2].target_probability, 1.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(points[2].spread, 4.0, epsilon = 1e-9);
+        // one that always turns it around keeps returning it to the start
+        assert_eq!(points[0].hitting_step, None);
+        assert_abs_diff_eq!(points[0].target_probability, 0.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(points[0].spread, 0.0, epsilon = 1e-9);
+        assert_eq!(points[0].metric(SweepMetric::HittingStep), None);
+
+        let csv = ParameterSweep::to_csv("theta", &points);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "theta,target_probability,hitting_step,spread");
+        assert_eq!(lines[1].split(',').nth(2), Some(""));
+        assert_eq!(lines[3].split(',').nth(2), Some("4"));
+    }
+
+    #[test]
+    fn test_parameter_names() {
+        let mut editor = editor_with_theta_coin();
+        editor.add_parameter("theta", 0.3);
+        editor.add_parameter("t", 1.0);
+        editor.add_parameter("pi", 3.0);
+        editor.add_parameter("theta", 0.5);
+        editor.add_parameter("2x", 0.0);
+        assert!(editor.check_parameter_name(0).is_ok());
+        for i in 1..5 {
+            assert!(editor.check_parameter_name(i).is_err());
+        }
+        assert!(editor
+            .check_parameter_name(1)
+            .unwrap_err()
+            .to_string()
+            .contains("reserved for the walk"));
+        assert!(editor
+            .check_parameter_name(3)
+            .unwrap_err()
+            .to_string()
+            .contains("already declared"));
+
+        // only the first declaration of theta reaches the expressions
+        let context = editor.get_context();
+        assert_eq!(context.get_value("theta"), Some(&Value::Float(0.3)));
+        assert_eq!(context.get_value("pi"), Some(&Value::Float(PI)));
+        assert_eq!(context.get_value("t"), None);
+    }
+
+    #[test]
+    fn test_sweep_over_editor_coins() {
+        let mut editor = editor_with_theta_coin();
+        // theta has to be declared for the typed in coins to be read
+        assert!(editor.evaluate_coins(&editor.get_context(), 0).is_err());
+        // unless it is the value being swept
+        assert!(editor.evaluate_coins_with("theta", 0.3, 0).is_ok());
+        editor.add_parameter("theta", 0.3);
+        assert!(editor.evaluate_coins(&editor.get_context(), 0).is_ok());
+
+        // the swept value replaces the declared one
+        let coins = editor.evaluate_coins_with("theta", FRAC_PI_2, 0).unwrap();
+        let Coin::Custom(expected) = symmetric_coin_for(FRAC_PI_2, 0).unwrap() else {
+            unreachable!()
+        };
+        for node in 0..8 {
+            assert!((&coins[&node] - &expected[&node]).norm() < 1e-12);
+        }
+
+        let sweep = ParameterSweep::new(cycle_graph(), 0, HashSet::from([4]), 6).unwrap();
+        let values = ParameterSweep::values(0.0, FRAC_PI_2, 5);
+        let from_editor = sweep
+            .run(&values, |value, t| {
+                editor
+                    .evaluate_coins_with("theta", value, t)
+                    .map(Coin::Custom)
+            })
+            .unwrap();
+        let from_matrices = sweep.run(&values, symmetric_coin_for).unwrap();
+        for (a, b) in from_editor.iter().zip(from_matrices.iter()) {
+            assert_eq!(a.hitting_step, b.hitting_step);
+            assert_abs_diff_eq!(a.target_probability, b.target_probability, epsilon = 1e-9);
+            assert_abs_diff_eq!(a.spread, b.spread, epsilon = 1e-9);
+        }
+    }
+}
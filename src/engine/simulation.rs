@@ -0,0 +1,1313 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use nalgebra::{Complex, DMatrix, DVector};
+
+use crate::editors::{
+    ClassicalStateManager, ComplexMatrixEditor, ComplexStateManager, ComplexTransitionMatrix,
+    Decoherence, Measurement, RunUntil, SparseMatrix, StateManager, StopReason, TwoParticleWalk,
+};
+
+use super::{Coin, Graph, SimulationMode};
+
+/// Runs a walk on a graph without any UI, reusing the same state managers as
+/// the editors.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    graph: Graph,
+    mode: SimulationMode,
+    start_node_idx: usize,
+    target_node_indexes: HashSet<usize>,
+    adjacency_list: HashMap<usize, Vec<usize>>,
+    labels: Vec<(usize, usize)>,
+    state_manager: StateManager,
+}
+
+impl Simulation {
+    pub fn new(
+        graph: Graph,
+        mode: SimulationMode,
+        start_node_idx: usize,
+        target_node_indexes: HashSet<usize>,
+    ) -> Result<Self> {
+        if start_node_idx >= graph.node_count {
+            return Err(anyhow!(
+                "Start node {} is outside of the {} node graph",
+                start_node_idx,
+                graph.node_count
+            ));
+        }
+        let mut targets = target_node_indexes.iter().collect::<Vec<_>>();
+        targets.sort_unstable();
+        if let Some(target) = targets.into_iter().find(|x| **x >= graph.node_count) {
+            return Err(anyhow!(
+                "Target node {} is outside of the {} node graph",
+                target,
+                graph.node_count
+            ));
+        }
+
+        // the Szegedy walk's labels come from building its operator, so it is
+        // only built once the chain is known to fit the graph
+        let mut szegedy_operator = match &mode {
+            SimulationMode::Szegedy(chain) => {
+                if chain.nrows() != graph.node_count || chain.ncols() != graph.node_count {
+                    return Err(anyhow!(
+                        "Transition matrix is {}x{} but the graph has {} nodes",
+                        chain.nrows(),
+                        chain.ncols(),
+                        graph.node_count
+                    ));
+                }
+                Some(ComplexTransitionMatrix::szegedy_operator(chain))
+            }
+            _ => None,
+        };
+
+        let (adjacency_list, labels) = match (&mode, &szegedy_operator) {
+            (SimulationMode::ContinuousQuantum { .. }, _) => {
+                let labels = graph.node_labels();
+                let adjacency_list = labels.iter().map(|&(i, j)| (i, vec![j])).collect();
+                (adjacency_list, labels)
+            }
+            (_, Some((_, labels))) => {
+                let labels = labels.clone();
+                let adjacency_list = (0..graph.node_count)
+                    .map(|x| {
+                        let to = labels.iter().filter(|l| l.0 == x).map(|l| l.1).collect();
+                        (x, to)
+                    })
+                    .collect();
+                (adjacency_list, labels)
+            }
+            (_, None) => (graph.adjacency_list(), graph.half_edge_labels()),
+        };
+
+        let state_manager = match &mode {
+            SimulationMode::Classical(_) | SimulationMode::ContinuousClassical { .. } => {
+                let matrix = mode.classical_transition_matrix().unwrap();
+                Self::new_classical_state_manager(
+                    &graph,
+                    &matrix,
+                    start_node_idx,
+                    &target_node_indexes,
+                )?
+            }
+            SimulationMode::Quantum(coin) => {
+                ComplexMatrixEditor::check_unitary_walk_exists(&adjacency_list)?;
+                if adjacency_list[&start_node_idx].is_empty() {
+                    return Err(anyhow!(
+                        "Start node {} has no edges for the walker to start on",
+                        start_node_idx
+                    ));
+                }
+                let matrix = Self::new_combined_matrix(&adjacency_list, &labels, coin)?;
+                StateManager::Complex(
+                    ComplexStateManager::from_transition_matrix(
+                        ComplexTransitionMatrix::from_sparse(matrix),
+                        &labels,
+                        start_node_idx,
+                        target_node_indexes.clone(),
+                    )
+                    .into(),
+                )
+            }
+            SimulationMode::ContinuousQuantum {
+                hamiltonian,
+                time_step,
+            } => {
+                if hamiltonian.nrows() != graph.node_count
+                    || hamiltonian.ncols() != graph.node_count
+                {
+                    return Err(anyhow!(
+                        "Hamiltonian is {}x{} but the graph has {} nodes",
+                        hamiltonian.nrows(),
+                        hamiltonian.ncols(),
+                        graph.node_count
+                    ));
+                }
+                let matrix = ComplexTransitionMatrix::evolution_operator(hamiltonian, *time_step);
+                StateManager::Complex(
+                    ComplexStateManager::new(
+                        &matrix,
+                        &labels,
+                        start_node_idx,
+                        target_node_indexes.clone(),
+                    )
+                    .into(),
+                )
+            }
+            SimulationMode::Szegedy(_) => {
+                let (matrix, _) = szegedy_operator
+                    .take()
+                    .ok_or_else(|| anyhow!("The Szegedy operator was not built"))?;
+                StateManager::Complex(
+                    ComplexStateManager::new(
+                        &matrix,
+                        &labels,
+                        start_node_idx,
+                        target_node_indexes.clone(),
+                    )
+                    .into(),
+                )
+            }
+        };
+
+        Ok(Self {
+            graph,
+            mode,
+            start_node_idx,
+            target_node_indexes,
+            adjacency_list,
+            labels,
+            state_manager,
+        })
+    }
+
+    /// Evolves a quantum walk as a density matrix with `decoherence` applied
+    /// after every step, restarting it.
+    pub fn with_decoherence(mut self, decoherence: Decoherence) -> Result<Self> {
+        match &mut self.state_manager {
+            StateManager::Complex(csm) => {
+                csm.set_decoherence(decoherence);
+                Ok(self)
+            }
+            _ => Err(anyhow!("Only quantum walks can decohere")),
+        }
+    }
+
+    /// Measures the position of a quantum walker intermittently, restarting
+    /// the walk. Target nodes then only absorb the walker when measured.
+    pub fn with_measurement(mut self, measurement: Measurement) -> Result<Self> {
+        match &mut self.state_manager {
+            StateManager::Complex(csm) => {
+                csm.set_measurement(measurement);
+                Ok(self)
+            }
+            _ => Err(anyhow!("Only quantum walks can be measured intermittently")),
+        }
+    }
+
+    /// Starts a quantum walk from `amplitudes`, ordered as [`Self::get_labels`]
+    /// and normalized, instead of the start node.
+    pub fn with_initial_state(mut self, amplitudes: DVector<Complex<f64>>) -> Result<Self> {
+        if amplitudes.len() != self.labels.len() {
+            return Err(anyhow!(
+                "The initial state has {} amplitudes but the walk has {} half-edges",
+                amplitudes.len(),
+                self.labels.len()
+            ));
+        }
+        if amplitudes.norm() == 0.0 {
+            return Err(anyhow!("Every amplitude of the initial state is zero"));
+        }
+        match &mut self.state_manager {
+            StateManager::Complex(csm) => {
+                csm.set_initial_state(amplitudes);
+                Ok(self)
+            }
+            _ => Err(anyhow!("Only quantum walks start from amplitudes")),
+        }
+    }
+
+    /// Starts a classical walk from `distribution`, normalized, instead of the
+    /// start node.
+    pub fn with_initial_distribution(mut self, distribution: DVector<f64>) -> Result<Self> {
+        if distribution.len() != self.graph.node_count {
+            return Err(anyhow!(
+                "The initial distribution has {} entries but the graph has {} nodes",
+                distribution.len(),
+                self.graph.node_count
+            ));
+        }
+        if distribution.iter().any(|x| *x < 0.0) || distribution.sum() <= 0.0 {
+            return Err(anyhow!(
+                "The initial distribution must be non-negative and not all zero"
+            ));
+        }
+        match (
+            &mut self.state_manager,
+            self.mode.classical_transition_matrix(),
+        ) {
+            (StateManager::Classical(csm), Some(matrix)) => {
+                csm.set_initial_distribution(distribution, &matrix);
+                Ok(self)
+            }
+            _ => Err(anyhow!("Only classical walks start from a distribution")),
+        }
+    }
+
+    /// Walks a second quantum walker alongside the first, restarting the walk.
+    pub fn with_two_particle_walk(mut self, two_particle_walk: TwoParticleWalk) -> Result<Self> {
+        if two_particle_walk.second_start_node_idx >= self.graph.node_count {
+            return Err(anyhow!(
+                "Second start node {} is outside of the {} node graph",
+                two_particle_walk.second_start_node_idx,
+                self.graph.node_count
+            ));
+        }
+        match &mut self.state_manager {
+            StateManager::Complex(csm) => {
+                csm.set_two_particle_walk(two_particle_walk);
+                match csm.get_two_particle_walk().and_then(|x| x.get_error()) {
+                    Some(e) => Err(anyhow!("{}", e)),
+                    None => Ok(self),
+                }
+            }
+            _ => Err(anyhow!("Only quantum walks can have two particles")),
+        }
+    }
+
+    fn new_classical_state_manager(
+        graph: &Graph,
+        matrix: &DMatrix<f64>,
+        start_node_idx: usize,
+        target_node_indexes: &HashSet<usize>,
+    ) -> Result<StateManager> {
+        if matrix.nrows() != graph.node_count || matrix.ncols() != graph.node_count {
+            return Err(anyhow!(
+                "Transition matrix is {}x{} but the graph has {} nodes",
+                matrix.nrows(),
+                matrix.ncols(),
+                graph.node_count
+            ));
+        }
+
+        let mut csm = ClassicalStateManager::try_from(matrix)?;
+        csm.set_start_node_idx(start_node_idx);
+        csm.set_target_node_indexes(target_node_indexes.clone());
+        csm.reset_state(matrix);
+        Ok(StateManager::Classical(csm.into()))
+    }
+
+    /// Combines a block diagonal scatter matrix of coins with the propagation
+    /// matrix that moves the walker across each edge, matching
+    /// [`ComplexMatrixEditor::get_combined_matrix`]. Built sparsely so that
+    /// large graphs never need the dense operator.
+    fn new_combined_matrix(
+        adjacency_list: &HashMap<usize, Vec<usize>>,
+        labels: &[(usize, usize)],
+        coin: &Coin,
+    ) -> Result<SparseMatrix<Complex<f64>>> {
+        let n = labels.len();
+        let mut scatter_entries = Vec::new();
+
+        let mut from_nodes = adjacency_list.keys().copied().collect::<Vec<_>>();
+        from_nodes.sort_unstable();
+
+        let mut past_adjacencies = 0;
+        for node in from_nodes {
+            let degree = adjacency_list[&node].len();
+            if degree == 0 {
+                continue;
+            }
+
+            let block = coin.matrix_for(node, degree)?;
+            for i in 0..degree {
+                for j in 0..degree {
+                    scatter_entries.push((
+                        past_adjacencies + i,
+                        past_adjacencies + j,
+                        block[(i, j)],
+                    ));
+                }
+            }
+            past_adjacencies += degree;
+        }
+
+        let scatter_matrix = SparseMatrix::from_triplets(n, n, scatter_entries);
+        let propagation_matrix = SparseMatrix::from_triplets(
+            n,
+            n,
+            ComplexMatrixEditor::propagation_pairs(labels)
+                .into_iter()
+                .map(|(i, j)| (i, j, Complex::new(1.0, 0.0))),
+        );
+        Ok(scatter_matrix.mul_sparse(&propagation_matrix))
+    }
+
+    pub fn step(&mut self) -> Result<()> {
+        match &mut self.state_manager {
+            StateManager::Classical(csm) => csm.step_forward(),
+            StateManager::Complex(csm) => {
+                csm.step_forward();
+                Ok(())
+            }
+            StateManager::None => Err(anyhow!("No state manager found")),
+        }
+    }
+
+    /// Swaps the coin of a coined quantum walk for the steps that follow,
+    /// keeping the current state, so that the coin can change over time. The
+    /// steps already walked are still undone with the coin they were walked
+    /// with.
+    pub fn set_coin(&mut self, coin: Coin) -> Result<()> {
+        let (SimulationMode::Quantum(_), StateManager::Complex(csm)) =
+            (&self.mode, &mut self.state_manager)
+        else {
+            return Err(anyhow!("Only coined quantum walks have a coin to set"));
+        };
+        let matrix = Self::new_combined_matrix(&self.adjacency_list, &self.labels, &coin)?;
+        csm.set_transition_matrix(ComplexTransitionMatrix::from_sparse(matrix));
+        self.mode = SimulationMode::Quantum(coin);
+        Ok(())
+    }
+
+    /// Returns to the previous step, from the history or, for a quantum walk
+    /// that has stayed unitary, by undoing the step with the adjoint.
+    pub fn step_back(&mut self) -> Result<()> {
+        match &mut self.state_manager {
+            StateManager::Classical(csm) => csm.step_back(),
+            StateManager::Complex(csm) => csm.step_back(),
+            StateManager::None => Err(anyhow!("No state manager found")),
+        }
+    }
+
+    /// Returns to a step still in the history.
+    pub fn go_to_step(&mut self, step: usize) -> Result<()> {
+        match &mut self.state_manager {
+            StateManager::Classical(csm) => csm.go_to_step(step),
+            StateManager::Complex(csm) => csm.go_to_step(step),
+            StateManager::None => Err(anyhow!("No state manager found")),
+        }
+    }
+
+    /// Keeps at most `capacity` steps in the history.
+    pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+        match &mut self.state_manager {
+            StateManager::Classical(csm) => csm.set_history_capacity(capacity),
+            StateManager::Complex(csm) => csm.set_history_capacity(capacity),
+            StateManager::None => (),
+        }
+        self
+    }
+
+    /// Steps the walk forward `steps` times.
+    pub fn run(&mut self, steps: usize) -> Result<()> {
+        for _ in 0..steps {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Steps the walk forward until a condition of `run_until` is met,
+    /// returning why it stopped.
+    pub fn run_until(&mut self, run_until: &RunUntil) -> Result<StopReason> {
+        let initial = self.get_node_probabilities();
+        let (reason, _) = run_until.run(initial, || {
+            self.step()?;
+            Ok((
+                self.get_node_probabilities(),
+                self.get_target_accumulation(),
+            ))
+        })?;
+        Ok(reason)
+    }
+
+    /// Returns the walk to step 0, clearing any target accumulation.
+    pub fn reset(&mut self) {
+        match &mut self.state_manager {
+            StateManager::Classical(csm) => {
+                if let Some(matrix) = self.mode.classical_transition_matrix() {
+                    csm.reset_state(&matrix)
+                }
+            }
+            StateManager::Complex(csm) => csm.reset_state(&self.labels),
+            StateManager::None => (),
+        }
+    }
+
+    pub fn get_step(&self) -> usize {
+        match &self.state_manager {
+            StateManager::Classical(csm) => csm.get_step(),
+            StateManager::Complex(csm) => csm.get_step(),
+            StateManager::None => 0,
+        }
+    }
+
+    /// Probability of finding the walker at each node. Probability absorbed by
+    /// a target node is reported at that target.
+    pub fn get_node_probabilities(&self) -> DVector<f64> {
+        let res = match &self.state_manager {
+            StateManager::Classical(csm) => csm.get_state_data(),
+            StateManager::Complex(csm) => csm.get_node_probabilities(&self.adjacency_list),
+            StateManager::None => DVector::from_element(0, 0.0),
+        };
+        self.pad_to_node_count(res)
+    }
+
+    /// Total probability absorbed by each node so far, zero for non-target
+    /// nodes.
+    pub fn get_target_accumulation(&self) -> DVector<f64> {
+        match &self.state_manager {
+            StateManager::Classical(csm) => self.pad_to_node_count(csm.get_target_accumulation()),
+            StateManager::Complex(csm) => {
+                let mut res = DVector::from_element(self.graph.node_count, 0.0);
+                for (i, v) in csm.get_target_node_accumulation() {
+                    if *i < res.len() {
+                        res[*i] = *v;
+                    }
+                }
+                res
+            }
+            StateManager::None => DVector::from_element(self.graph.node_count, 0.0),
+        }
+    }
+
+    /// The amplitudes of a quantum walk, ordered as [`Self::get_labels`].
+    /// Classical and decohering walks have no amplitudes.
+    pub fn get_amplitudes(&self) -> Option<&DVector<Complex<f64>>> {
+        match &self.state_manager {
+            StateManager::Complex(csm) if csm.get_density_matrix().is_none() => {
+                Some(csm.get_state())
+            }
+            _ => None,
+        }
+    }
+
+    /// Entry `(i, j)` is the probability of finding the first walker of a two
+    /// particle walk at node `i` and the second at node `j`.
+    pub fn get_two_particle_correlations(&self) -> Option<DMatrix<f64>> {
+        match &self.state_manager {
+            StateManager::Complex(csm) => csm
+                .get_two_particle_walk()
+                .map(|x| x.get_correlations(&self.labels, self.graph.node_count)),
+            _ => None,
+        }
+    }
+
+    /// The density matrix of a decohering quantum walk, ordered as
+    /// [`Self::get_labels`].
+    pub fn get_density_matrix(&self) -> Option<&DMatrix<Complex<f64>>> {
+        match &self.state_manager {
+            StateManager::Complex(csm) => csm.get_density_matrix(),
+            _ => None,
+        }
+    }
+
+    pub fn get_labels(&self) -> &[(usize, usize)] {
+        &self.labels
+    }
+
+    pub fn get_graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    pub fn get_mode(&self) -> &SimulationMode {
+        &self.mode
+    }
+
+    pub fn get_start_node_idx(&self) -> usize {
+        self.start_node_idx
+    }
+
+    pub fn get_target_node_indexes(&self) -> &HashSet<usize> {
+        &self.target_node_indexes
+    }
+
+    fn pad_to_node_count(&self, vector: DVector<f64>) -> DVector<f64> {
+        let mut res = DVector::from_element(self.graph.node_count, 0.0);
+        for (i, v) in vector.iter().enumerate().take(self.graph.node_count) {
+            res[i] = *v;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editors::{DecoherenceChannel, MeasurementOutcome, ParticleStatistics};
+    use approx::assert_abs_diff_eq;
+
+    fn path_graph() -> Graph {
+        Graph::new(3, vec![(0, 1), (1, 2)]).unwrap()
+    }
+
+    fn cycle_graph(n: usize) -> Graph {
+        Graph::new(n, (0..n).map(|i| (i, (i + 1) % n)).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_classical_path_walk() {
+        let graph = path_graph();
+        let matrix = graph.uniform_transition_matrix();
+        let mut sim =
+            Simulation::new(graph, SimulationMode::Classical(matrix), 0, HashSet::new()).unwrap();
+        assert_eq!(sim.get_node_probabilities().as_slice(), &[1.0, 0.0, 0.0]);
+
+        sim.step().unwrap();
+        assert_eq!(sim.get_node_probabilities().as_slice(), &[0.0, 1.0, 0.0]);
+
+        sim.step().unwrap();
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().as_slice(),
+            [0.5, 0.0, 0.5].as_slice(),
+            epsilon = 1e-12
+        );
+
+        sim.reset();
+        assert_eq!(sim.get_step(), 0);
+        assert_eq!(sim.get_node_probabilities().as_slice(), &[1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_classical_target_absorption() {
+        let graph = path_graph();
+        let matrix = graph.uniform_transition_matrix();
+        let mut sim = Simulation::new(
+            graph,
+            SimulationMode::Classical(matrix),
+            0,
+            HashSet::from([2]),
+        )
+        .unwrap();
+        sim.run(2).unwrap();
+        assert_abs_diff_eq!(sim.get_target_accumulation()[2], 0.5, epsilon = 1e-12);
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().iter().sum::<f64>(),
+            1.0,
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_quantum_walk_conserves_probability() {
+        let mut sim = Simulation::new(
+            cycle_graph(5),
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(sim.get_labels().len(), 10);
+        assert_abs_diff_eq!(sim.get_node_probabilities()[0], 1.0, epsilon = 1e-12);
+
+        sim.run(7).unwrap();
+        assert_eq!(sim.get_step(), 7);
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().iter().sum::<f64>(),
+            1.0,
+            epsilon = 1e-9
+        );
+        let amplitude_norm = sim
+            .get_amplitudes()
+            .unwrap()
+            .iter()
+            .map(|x| x.norm_sqr())
+            .sum::<f64>();
+        assert_abs_diff_eq!(amplitude_norm, 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_quantum_first_step_on_cycle() {
+        // with two neighbours the Grover coin only reflects, so a walker
+        // starting at node 0 on a 4-cycle splits evenly onto nodes 1 and 3
+        let mut sim = Simulation::new(
+            cycle_graph(4),
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        sim.step().unwrap();
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().as_slice(),
+            [0.0, 0.5, 0.0, 0.5].as_slice(),
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_time_dependent_coin() {
+        // the Grover coin carries the walker on around a cycle, while the flip
+        // coin sends it back the way it came, leaving it bouncing between two
+        // neighbouring nodes
+        let flip = DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex::new(1.0, 0.0),
+                Complex::new(0.0, 0.0),
+                Complex::new(0.0, 0.0),
+                Complex::new(-1.0, 0.0),
+            ],
+        );
+        let mut sim = Simulation::new(
+            cycle_graph(8),
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        sim.run(2).unwrap();
+        assert_abs_diff_eq!(sim.get_node_probabilities()[2], 0.5, epsilon = 1e-9);
+
+        sim.set_coin(Coin::Custom((0..8).map(|i| (i, flip.clone())).collect()))
+            .unwrap();
+        sim.run(2).unwrap();
+        assert_abs_diff_eq!(sim.get_node_probabilities()[2], 0.5, epsilon = 1e-9);
+        assert_abs_diff_eq!(sim.get_node_probabilities()[0], 0.0, epsilon = 1e-9);
+
+        // swapping the coin keeps the state it was swapped at
+        sim.set_coin(Coin::Grover).unwrap();
+        sim.step().unwrap();
+        assert_eq!(sim.get_step(), 5);
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().iter().sum::<f64>(),
+            1.0,
+            epsilon = 1e-9
+        );
+
+        // steps no longer in the history are undone with the coin they were
+        // walked with, not the one set since
+        let mut sim = Simulation::new(
+            cycle_graph(8),
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new(),
+        )
+        .unwrap()
+        .with_history_capacity(1);
+        let mut amplitudes = vec![sim.get_amplitudes().unwrap().clone()];
+        for _ in 0..2 {
+            sim.step().unwrap();
+            amplitudes.push(sim.get_amplitudes().unwrap().clone());
+        }
+        sim.set_coin(Coin::Custom((0..8).map(|i| (i, flip.clone())).collect()))
+            .unwrap();
+        sim.step().unwrap();
+        for step in (0..3).rev() {
+            sim.step_back().unwrap();
+            assert_eq!(sim.get_step(), step);
+            assert_abs_diff_eq!(
+                sim.get_amplitudes().unwrap().as_slice(),
+                amplitudes[step].as_slice(),
+                epsilon = 1e-9
+            );
+        }
+
+        let mut classical = Simulation::new(
+            cycle_graph(8),
+            SimulationMode::Classical(cycle_graph(8).uniform_transition_matrix()),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        assert!(classical.set_coin(Coin::Grover).is_err());
+    }
+
+    #[test]
+    fn test_sparse_quantum_walk() {
+        let graph = cycle_graph(60);
+        let adjacency_list = graph.adjacency_list();
+        let labels = graph.half_edge_labels();
+        let matrix =
+            Simulation::new_combined_matrix(&adjacency_list, &labels, &Coin::Grover).unwrap();
+        let dense = matrix.to_dense();
+        assert!(ComplexTransitionMatrix::from_sparse(matrix).is_sparse());
+
+        let mut sim = Simulation::new(
+            graph,
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        let mut expected = sim.get_amplitudes().unwrap().clone();
+        for _ in 0..20 {
+            sim.step().unwrap();
+            expected = &dense * expected;
+        }
+        assert_abs_diff_eq!(
+            sim.get_amplitudes().unwrap().as_slice(),
+            expected.as_slice(),
+            epsilon = 1e-9
+        );
+
+        // large enough that the dense operator would not be practical
+        let mut sim = Simulation::new(
+            cycle_graph(2000),
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::from([1000]),
+        )
+        .unwrap();
+        sim.run(500).unwrap();
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().iter().sum::<f64>(),
+            1.0,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_decoherent_quantum_walk() {
+        let new_sim = |decoherence| {
+            Simulation::new(
+                cycle_graph(5),
+                SimulationMode::Quantum(Coin::Grover),
+                0,
+                HashSet::new(),
+            )
+            .unwrap()
+            .with_decoherence(decoherence)
+            .unwrap()
+        };
+
+        // without any decoherence the density matrix follows the pure walk
+        let mut pure = new_sim(Decoherence::default());
+        let mut mixed = new_sim(Decoherence::new(DecoherenceChannel::PositionDephasing, 0.0));
+        pure.run(6).unwrap();
+        mixed.run(6).unwrap();
+        assert!(pure.get_density_matrix().is_none());
+        assert!(mixed.get_amplitudes().is_none());
+        assert_abs_diff_eq!(
+            pure.get_node_probabilities().as_slice(),
+            mixed.get_node_probabilities().as_slice(),
+            epsilon = 1e-9
+        );
+
+        for channel in [
+            DecoherenceChannel::PositionDephasing,
+            DecoherenceChannel::CoinDepolarisation,
+        ] {
+            let mut sim = new_sim(Decoherence::new(channel, 1.0));
+            sim.run(6).unwrap();
+            let density_matrix = sim.get_density_matrix().unwrap();
+            let purity = (density_matrix * density_matrix).trace().re;
+            assert!(purity < 1.0 - 1e-3);
+            assert_abs_diff_eq!(
+                sim.get_node_probabilities().iter().sum::<f64>(),
+                1.0,
+                epsilon = 1e-9
+            );
+        }
+
+        let classical = Simulation::new(
+            cycle_graph(5),
+            SimulationMode::Classical(cycle_graph(5).uniform_transition_matrix()),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        assert!(classical.with_decoherence(Decoherence::default()).is_err());
+    }
+
+    #[test]
+    fn test_intermittently_measured_quantum_walk() {
+        let new_sim = |measurement| {
+            Simulation::new(
+                cycle_graph(6),
+                SimulationMode::Quantum(Coin::Grover),
+                0,
+                HashSet::from([3]),
+            )
+            .unwrap()
+            .with_measurement(measurement)
+            .unwrap()
+        };
+
+        // sampled measurements leave any walker not yet absorbed at a single
+        // node, and repeat with the same seed
+        let mut a = new_sim(Measurement::every(1, MeasurementOutcome::Sampled, 7));
+        let mut b = new_sim(Measurement::every(1, MeasurementOutcome::Sampled, 7));
+        for _ in 0..5 {
+            a.step().unwrap();
+            b.step().unwrap();
+            let probabilities = a.get_node_probabilities();
+            let target = a.get_target_accumulation()[3];
+            assert_eq!(probabilities, b.get_node_probabilities());
+            assert!(
+                probabilities
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, p)| *i != 3 && **p > 1e-9)
+                    .count()
+                    <= 1
+            );
+            assert_abs_diff_eq!(probabilities.sum(), 1.0, epsilon = 1e-9);
+            assert_abs_diff_eq!(probabilities[3], target, epsilon = 1e-12);
+        }
+
+        // targets only absorb when the walker is measured
+        let mut sim = new_sim(Measurement::every(4, MeasurementOutcome::Ensemble, 0));
+        sim.run(3).unwrap();
+        assert_eq!(sim.get_target_accumulation()[3], 0.0);
+        let density_matrix = sim.get_density_matrix().unwrap();
+        let at_target = sim
+            .get_labels()
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.0 == 3)
+            .map(|(idx, _)| density_matrix[(idx, idx)].re)
+            .sum::<f64>();
+        assert!(at_target > 0.0);
+        sim.step().unwrap();
+        assert!(sim.get_target_accumulation()[3] > 0.0);
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().iter().sum::<f64>(),
+            1.0,
+            epsilon = 1e-9
+        );
+
+        // a partial ensemble measurement absorbs part of the walker at a target
+        let mut partial = new_sim(Measurement::with_probability(
+            0.5,
+            MeasurementOutcome::Ensemble,
+            0,
+        ));
+        let mut full = new_sim(Measurement::with_probability(
+            1.0,
+            MeasurementOutcome::Ensemble,
+            0,
+        ));
+        partial.run(3).unwrap();
+        full.run(3).unwrap();
+        let (partial, full) = (
+            partial.get_target_accumulation()[3],
+            full.get_target_accumulation()[3],
+        );
+        assert!(partial > 0.0 && partial < full);
+    }
+
+    #[test]
+    fn test_custom_initial_states() {
+        // the two halves of the superposition walk away from each other
+        let quantum = Simulation::new(
+            cycle_graph(6),
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        let labels = quantum.get_labels().to_vec();
+        let mut amplitudes = DVector::from_element(labels.len(), Complex::new(0.0, 0.0));
+        for (i, label) in labels.iter().enumerate() {
+            if *label == (0, 1) || *label == (3, 4) {
+                amplitudes[i] = Complex::new(1.0, 0.0);
+            }
+        }
+        let mut quantum = quantum.with_initial_state(amplitudes.clone()).unwrap();
+        let probabilities = quantum.get_node_probabilities();
+        assert_abs_diff_eq!(probabilities[0], 0.5, epsilon = 1e-12);
+        assert_abs_diff_eq!(probabilities[3], 0.5, epsilon = 1e-12);
+        quantum.run(2).unwrap();
+        quantum.reset();
+        assert_abs_diff_eq!(quantum.get_node_probabilities()[3], 0.5, epsilon = 1e-12);
+        assert!(quantum
+            .clone()
+            .with_initial_state(amplitudes * Complex::new(0.0, 0.0))
+            .is_err());
+        assert!(quantum
+            .with_initial_state(DVector::from_element(1, Complex::new(1.0, 0.0)))
+            .is_err());
+
+        let graph = Graph::new(4, vec![(0, 1), (1, 2), (2, 3)]).unwrap();
+        let matrix = graph.uniform_transition_matrix();
+        let mut classical =
+            Simulation::new(graph, SimulationMode::Classical(matrix), 0, HashSet::new())
+                .unwrap()
+                .with_initial_distribution(DVector::from_vec(vec![1.0, 0.0, 0.0, 3.0]))
+                .unwrap();
+        let probabilities = classical.get_node_probabilities();
+        assert_abs_diff_eq!(probabilities[0], 0.25, epsilon = 1e-12);
+        assert_abs_diff_eq!(probabilities[3], 0.75, epsilon = 1e-12);
+        classical.step().unwrap();
+        assert_abs_diff_eq!(classical.get_node_probabilities()[1], 0.25, epsilon = 1e-12);
+        assert_abs_diff_eq!(classical.get_node_probabilities()[2], 0.75, epsilon = 1e-12);
+        assert!(classical
+            .with_initial_distribution(DVector::from_vec(vec![1.0, -1.0, 0.0, 0.0]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_run_until() {
+        let graph = path_graph();
+        let matrix = graph.uniform_transition_matrix();
+        let new_sim = || {
+            Simulation::new(
+                graph.clone(),
+                SimulationMode::Classical(matrix.clone()),
+                0,
+                HashSet::from([2]),
+            )
+            .unwrap()
+        };
+
+        // half the walker reaches the target after two steps
+        let mut sim = new_sim();
+        let reason = sim.run_until(&RunUntil::default()).unwrap();
+        assert_eq!(reason, StopReason::TargetAccumulation(0.5));
+        assert_eq!(sim.get_step(), 2);
+
+        let mut sim = new_sim();
+        let run_until = RunUntil {
+            use_target_threshold: false,
+            use_node_threshold: true,
+            node: 1,
+            node_threshold: 0.9,
+            ..Default::default()
+        };
+        assert_eq!(
+            sim.run_until(&run_until).unwrap(),
+            StopReason::NodeProbability(1, 1.0)
+        );
+        assert_eq!(sim.get_step(), 1);
+
+        // the walker is eventually absorbed, so the distribution stops changing
+        let mut sim = new_sim();
+        let run_until = RunUntil {
+            use_target_threshold: false,
+            use_convergence: true,
+            epsilon: 1e-3,
+            ..Default::default()
+        };
+        assert!(matches!(
+            sim.run_until(&run_until).unwrap(),
+            StopReason::Converged(x) if x < 1e-3
+        ));
+        assert!(sim.get_target_accumulation()[2] > 0.99);
+
+        let mut sim = new_sim();
+        assert_eq!(
+            sim.run_until(&RunUntil::steps(5)).unwrap(),
+            StopReason::MaxSteps
+        );
+        assert_eq!(sim.get_step(), 5);
+    }
+
+    #[test]
+    fn test_step_history() {
+        let mut sim = Simulation::new(
+            cycle_graph(6),
+            SimulationMode::Quantum(Coin::Fourier),
+            0,
+            HashSet::new(),
+        )
+        .unwrap()
+        .with_history_capacity(4);
+        let mut amplitudes = vec![sim.get_amplitudes().unwrap().clone()];
+        for _ in 0..8 {
+            sim.step().unwrap();
+            amplitudes.push(sim.get_amplitudes().unwrap().clone());
+        }
+
+        sim.go_to_step(6).unwrap();
+        assert_eq!(sim.get_step(), 6);
+        assert_eq!(sim.get_amplitudes().unwrap(), &amplitudes[6]);
+        sim.go_to_step(8).unwrap();
+        assert!(sim.go_to_step(2).is_err());
+
+        // steps evicted from the history are undone with the adjoint
+        for step in (0..8).rev() {
+            sim.step_back().unwrap();
+            assert_eq!(sim.get_step(), step);
+            assert_abs_diff_eq!(
+                sim.get_amplitudes().unwrap().as_slice(),
+                amplitudes[step].as_slice(),
+                epsilon = 1e-9
+            );
+        }
+        assert!(sim.step_back().is_err());
+
+        // absorption at a target cannot be undone, so only the history is used
+        let graph = path_graph();
+        let matrix = graph.uniform_transition_matrix();
+        let mut sim = Simulation::new(
+            graph,
+            SimulationMode::Classical(matrix),
+            0,
+            HashSet::from([2]),
+        )
+        .unwrap()
+        .with_history_capacity(2);
+        sim.run(3).unwrap();
+        sim.step_back().unwrap();
+        assert_eq!(sim.get_step(), 2);
+        assert_abs_diff_eq!(sim.get_target_accumulation()[2], 0.5, epsilon = 1e-12);
+        assert!(sim.step_back().is_err());
+    }
+
+    #[test]
+    fn test_two_particle_walk() {
+        let new_sim = |statistics, second_start_node_idx, interaction_phase| {
+            Simulation::new(
+                cycle_graph(6),
+                SimulationMode::Quantum(Coin::Fourier),
+                0,
+                HashSet::new(),
+            )
+            .unwrap()
+            .with_two_particle_walk(TwoParticleWalk::new(
+                statistics,
+                second_start_node_idx,
+                interaction_phase,
+            ))
+        };
+
+        // a product state leaves each walker to walk as it would alone
+        let mut distinguishable = new_sim(ParticleStatistics::Distinguishable, 0, 0.0).unwrap();
+        distinguishable.run(4).unwrap();
+        let correlations = distinguishable.get_two_particle_correlations().unwrap();
+        let single = distinguishable.get_node_probabilities();
+        let expected = &single * single.transpose();
+        assert_abs_diff_eq!(correlations.sum(), 1.0, epsilon = 1e-9);
+        for (a, b) in correlations.iter().zip(expected.iter()) {
+            assert_abs_diff_eq!(a, b, epsilon = 1e-9);
+        }
+
+        // exchange symmetry is kept by every step
+        for statistics in [ParticleStatistics::Bosonic, ParticleStatistics::Fermionic] {
+            let mut sim = new_sim(statistics, 3, 0.5).unwrap();
+            sim.run(5).unwrap();
+            let correlations = sim.get_two_particle_correlations().unwrap();
+            assert_abs_diff_eq!(correlations.sum(), 1.0, epsilon = 1e-9);
+            for (a, b) in correlations.iter().zip(correlations.transpose().iter()) {
+                assert_abs_diff_eq!(a, b, epsilon = 1e-9);
+            }
+        }
+
+        // fermions cannot share a starting state
+        assert!(new_sim(ParticleStatistics::Fermionic, 0, 0.0).is_err());
+        assert!(new_sim(ParticleStatistics::Bosonic, 6, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_weighted_classical_walk() {
+        // node 0 is three times as likely to move to node 1 as to node 2
+        let graph =
+            Graph::from_weighted_arcs(3, &[(0, 1, 3.0), (0, 2, 1.0), (1, 0, 3.0), (2, 0, 1.0)])
+                .unwrap();
+        assert_eq!(graph.edges, vec![(0, 1), (0, 2)]);
+        assert_eq!(graph.weight(1, 0), 3.0);
+
+        let matrix = graph.uniform_transition_matrix();
+        let mut sim =
+            Simulation::new(graph, SimulationMode::Classical(matrix), 0, HashSet::new()).unwrap();
+        sim.step().unwrap();
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().as_slice(),
+            [0.0, 0.75, 0.25].as_slice(),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_node_space_classical_walk() {
+        let graph = cycle_graph(7);
+        let matrix = graph.uniform_transition_matrix();
+        let mut sim = Simulation::new(
+            graph,
+            SimulationMode::Classical(matrix.clone()),
+            2,
+            HashSet::from([5]),
+        )
+        .unwrap();
+        let StateManager::Classical(csm) = &sim.state_manager else {
+            panic!("Expected a classical state manager");
+        };
+        assert_eq!(csm.get_state().len(), 7);
+
+        let mut edge_csm = ClassicalStateManager::with_edge_states(&matrix).unwrap();
+        edge_csm.set_start_node_idx(2);
+        edge_csm.set_target_node_indexes(HashSet::from([5]));
+        edge_csm.reset_state(&matrix);
+        assert_eq!(edge_csm.get_state().len(), 49);
+
+        for _ in 0..10 {
+            sim.step().unwrap();
+            edge_csm.step_forward().unwrap();
+            assert_abs_diff_eq!(
+                sim.get_node_probabilities().as_slice(),
+                edge_csm.get_state_data().as_slice(),
+                epsilon = 1e-12
+            );
+        }
+    }
+
+    #[test]
+    fn test_directed_quantum_walk() {
+        // a one way cycle sends the walker around it one node per step
+        let graph = Graph::new(3, vec![])
+            .unwrap()
+            .with_directed_edges(vec![(0, 1), (1, 2), (2, 0)])
+            .unwrap();
+        let mut sim = Simulation::new(
+            graph,
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(sim.get_labels(), &[(0, 1), (1, 2), (2, 0)]);
+
+        sim.step().unwrap();
+        assert_abs_diff_eq!(sim.get_node_probabilities()[1], 1.0, epsilon = 1e-9);
+        sim.step().unwrap();
+        assert_abs_diff_eq!(sim.get_node_probabilities()[2], 1.0, epsilon = 1e-9);
+
+        // node 2 can be arrived at along two edges but only left along one
+        let unbalanced = Graph::new(3, vec![])
+            .unwrap()
+            .with_directed_edges(vec![(0, 2), (1, 2), (2, 0)])
+            .unwrap();
+        assert!(Simulation::new(
+            unbalanced.clone(),
+            SimulationMode::Quantum(Coin::Grover),
+            0,
+            HashSet::new()
+        )
+        .is_err());
+
+        // classical walks have no such restriction and never move backwards
+        let matrix = unbalanced.uniform_transition_matrix();
+        let mut sim = Simulation::new(
+            unbalanced,
+            SimulationMode::Classical(matrix),
+            1,
+            HashSet::new(),
+        )
+        .unwrap();
+        sim.run(2).unwrap();
+        assert_abs_diff_eq!(sim.get_node_probabilities()[0], 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_szegedy_walk() {
+        let graph = cycle_graph(4);
+        let matrix = graph.uniform_transition_matrix();
+        let (operator, labels) = ComplexTransitionMatrix::szegedy_operator(&matrix);
+        assert_eq!(labels.len(), 8);
+        assert_abs_diff_eq!(
+            &(operator.adjoint() * &operator),
+            &DMatrix::identity(8, 8),
+            epsilon = 1e-12
+        );
+
+        let mut sim =
+            Simulation::new(graph, SimulationMode::Szegedy(matrix), 0, HashSet::new()).unwrap();
+        sim.step().unwrap();
+        assert_abs_diff_eq!(
+            sim.get_node_probabilities().as_slice(),
+            [0.0, 0.5, 0.0, 0.5].as_slice(),
+            epsilon = 1e-12
+        );
+        sim.run(5).unwrap();
+        assert_abs_diff_eq!(sim.get_node_probabilities().sum(), 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_continuous_quantum_walk() {
+        let graph = cycle_graph(4);
+        let hamiltonian = graph.adjacency_matrix().map(Complex::from);
+        let mut sim = Simulation::new(
+            graph,
+            SimulationMode::ContinuousQuantum {
+                hamiltonian,
+                time_step: 0.25,
+            },
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(sim.get_labels(), &[(0, 0), (1, 1), (2, 2), (3, 3)]);
+
+        // on a 4-cycle the walker at node 0 reaches node 2 with probability
+        // sin^4(t) after a time t
+        sim.run(4).unwrap();
+        let probabilities = sim.get_node_probabilities();
+        assert_abs_diff_eq!(probabilities[2], 1.0_f64.sin().powi(4), epsilon = 1e-9);
+        assert_abs_diff_eq!(probabilities[1], probabilities[3], epsilon = 1e-9);
+        assert_abs_diff_eq!(probabilities.iter().sum::<f64>(), 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_continuous_classical_walk() {
+        let graph = cycle_graph(4);
+        let rates = graph.adjacency_matrix();
+        let mut sim = Simulation::new(
+            graph,
+            SimulationMode::ContinuousClassical {
+                rates,
+                time_step: 0.25,
+            },
+            0,
+            HashSet::new(),
+        )
+        .unwrap();
+
+        // each node of a 4-cycle leaves at rate 2, so the walker is still at
+        // its start node with probability (1 + 2e^{-2t} + e^{-4t}) / 4
+        sim.run(4).unwrap();
+        let probabilities = sim.get_node_probabilities();
+        let t = 1.0_f64;
+        let expected = (1.0 + 2.0 * (-2.0 * t).exp() + (-4.0 * t).exp()) / 4.0;
+        assert_abs_diff_eq!(probabilities[0], expected, epsilon = 1e-9);
+        assert_abs_diff_eq!(probabilities[1], probabilities[3], epsilon = 1e-9);
+        assert_abs_diff_eq!(probabilities.iter().sum::<f64>(), 1.0, epsilon = 1e-9);
+
+        sim.reset();
+        assert_eq!(sim.get_step(), 0);
+        assert_abs_diff_eq!(sim.get_node_probabilities()[0], 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        assert!(Graph::new(2, vec![(0, 2)]).is_err());
+        assert!(Simulation::new(
+            path_graph(),
+            SimulationMode::Quantum(Coin::Grover),
+            3,
+            HashSet::new()
+        )
+        .is_err());
+
+        let isolated = Graph::new(3, vec![(0, 1)]).unwrap();
+        assert!(Simulation::new(
+            isolated,
+            SimulationMode::Quantum(Coin::Grover),
+            2,
+            HashSet::new()
+        )
+        .is_err());
+
+        let wrong_size = SimulationMode::Classical(DMatrix::from_element(2, 2, 1.0));
+        assert!(Simulation::new(path_graph(), wrong_size, 0, HashSet::new()).is_err());
+        let wrong_size = SimulationMode::Szegedy(DMatrix::from_element(2, 2, 0.5));
+        assert_eq!(
+            Simulation::new(path_graph(), wrong_size, 0, HashSet::new())
+                .unwrap_err()
+                .to_string(),
+            "Transition matrix is 2x2 but the graph has 3 nodes"
+        );
+
+        // targets past the last node are rejected rather than panicking once
+        // their accumulation is read
+        let classical = SimulationMode::Classical(path_graph().uniform_transition_matrix());
+        for mode in [classical, SimulationMode::Quantum(Coin::Grover)] {
+            let res = Simulation::new(path_graph(), mode, 0, HashSet::from([1, 3]));
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                "Target node 3 is outside of the 3 node graph"
+            );
+        }
+
+        // custom coins that are not unitary are rejected whether or not the
+        // graph is large enough for the operator to be stored sparsely
+        for n in [4, 60] {
+            let damped = DMatrix::identity(2, 2) * Complex::new(0.9, 0.0);
+            let coin = Coin::Custom((0..n).map(|i| (i, damped.clone())).collect());
+            let res = Simulation::new(
+                cycle_graph(n),
+                SimulationMode::Quantum(coin),
+                0,
+                HashSet::new(),
+            );
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                "Coin for node 0 is not unitary"
+            );
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use nalgebra::{Complex, DMatrix};
+
+use crate::editors::ClassicalTransitionMatrix;
+
+use super::Coin;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationMode {
+    /// Discrete-time classical walk driven by an NxN matrix where entry
+    /// `(i, j)` is the weight of moving from node `j` to node `i`. Columns are
+    /// normalized to be stochastic.
+    Classical(DMatrix<f64>),
+    /// Continuous-time classical walk on the nodes, evolving by `exp(Qt)` each
+    /// step. Off-diagonal entry `(i, j)` of the NxN `rates` is the rate of
+    /// moving from node `j` to node `i`; the diagonal is ignored.
+    ContinuousClassical { rates: DMatrix<f64>, time_step: f64 },
+    /// Coined discrete-time quantum walk on the half-edges of the graph.
+    Quantum(Coin),
+    /// Continuous-time quantum walk on the nodes, evolving by `exp(-iHt)` each
+    /// step. Only the Hermitian part of the NxN `hamiltonian` is used.
+    ContinuousQuantum {
+        hamiltonian: DMatrix<Complex<f64>>,
+        time_step: f64,
+    },
+    /// Szegedy quantization of a classical chain, given as an NxN matrix in
+    /// the same form as [`Self::Classical`], walking on the pairs of nodes the
+    /// chain connects.
+    Szegedy(DMatrix<f64>),
+}
+
+impl SimulationMode {
+    /// The column stochastic matrix a classical walk applies each step, or
+    /// `None` for quantum walks.
+    pub fn classical_transition_matrix(&self) -> Option<DMatrix<f64>> {
+        match self {
+            Self::Classical(matrix) => Some(matrix.clone()),
+            Self::ContinuousClassical { rates, time_step } => {
+                let generator = ClassicalTransitionMatrix::generator_from_rates(rates);
+                Some(ClassicalTransitionMatrix::evolution_operator(
+                    &generator, *time_step,
+                ))
+            }
+            _ => None,
+        }
+    }
+}
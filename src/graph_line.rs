@@ -8,6 +8,11 @@ use crate::graph_node::GraphNode;
 pub struct GraphLine {
     pub start: Rc<RefCell<GraphNode>>,
     pub end: Rc<RefCell<GraphNode>>,
+    /// Directed lines can only be traversed from `start` to `end`.
+    pub directed: bool,
+    /// Relative likelihood of the walker taking this line, used when filling
+    /// in the walk's matrices from the canvas.
+    pub weight: f64,
 }
 
 impl GraphLine {
@@ -15,6 +20,18 @@ impl GraphLine {
         Self {
             start: start_node,
             end: end_node,
+            directed: false,
+            weight: 1.0,
+        }
+    }
+
+    pub fn new_directed(
+        start_node: Rc<RefCell<GraphNode>>,
+        end_node: Rc<RefCell<GraphNode>>,
+    ) -> Self {
+        Self {
+            directed: true,
+            ..Self::new(start_node, end_node)
         }
     }
 
@@ -26,6 +43,11 @@ impl GraphLine {
         }
     }
 
+    pub fn midpoint(&self) -> GraphNode {
+        let (a, b) = (self.start.borrow(), self.end.borrow());
+        GraphNode::new_unlabelled((a.x + b.x) / 2.0, (a.y + b.y) / 2.0)
+    }
+
     pub fn is_attached(&self, other: &GraphNode) -> bool {
         self.start.borrow().clone() == *other || self.end.borrow().clone() == *other
     }
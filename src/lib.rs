@@ -6,6 +6,7 @@ pub mod canvas_actions;
 pub mod constants;
 pub mod context_menu;
 pub mod editors;
+pub mod engine;
 pub mod graph_line;
 pub mod graph_node;
 pub mod options;
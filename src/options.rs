@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
 use egui::{Color32, Ui};
+use strum::{Display, VariantArray};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
 pub struct Options {
@@ -15,7 +16,15 @@ pub struct Options {
 impl Options {
     pub fn show_mode_buttons(&mut self, ui: &mut Ui) {
         ui.heading("Computation Style");
-        for mode in [Mode::Edit, Mode::Classical, Mode::Quantum] {
+        for mode in [
+            Mode::Edit,
+            Mode::Classical,
+            Mode::Quantum,
+            Mode::ContinuousClassical,
+            Mode::ContinuousQuantum,
+            Mode::Szegedy,
+            Mode::Comparison,
+        ] {
             let mut btn = ui.button(mode.name().to_string());
             if mode == self.mode {
                 btn = btn.highlight();
@@ -33,6 +42,10 @@ impl Options {
             Mode::Edit => self.specific.edit.show_options(ui),
             Mode::Classical => self.specific.classical.show_options(ui),
             Mode::Quantum => self.specific.quantum.show_options(ui),
+            Mode::ContinuousClassical => self.specific.continuous_classical.show_options(ui),
+            Mode::ContinuousQuantum => self.specific.continuous_quantum.show_options(ui),
+            Mode::Szegedy => self.specific.szegedy.show_options(ui),
+            Mode::Comparison => self.specific.comparison.show_options(ui),
         }
     }
 
@@ -54,6 +67,23 @@ impl Options {
                 }
             }
         });
+
+        if matches!(
+            self.mode,
+            Mode::Quantum | Mode::ContinuousQuantum | Mode::Szegedy
+        ) {
+            egui::ComboBox::from_label("Canvas Distribution")
+                .selected_text(format!("{}", self.generic.distribution_view))
+                .show_ui(ui, |ui| {
+                    for view in DistributionView::VARIANTS {
+                        ui.selectable_value(
+                            &mut self.generic.distribution_view,
+                            *view,
+                            format!("{}", view),
+                        );
+                    }
+                });
+        }
     }
 
     pub fn get_node_color(&self) -> Color32 {
@@ -65,6 +95,10 @@ impl Options {
             Mode::Edit => Color32::BLUE,
             Mode::Classical => Color32::WHITE,
             Mode::Quantum => Color32::WHITE,
+            Mode::ContinuousClassical => Color32::WHITE,
+            Mode::ContinuousQuantum => Color32::WHITE,
+            Mode::Szegedy => Color32::WHITE,
+            Mode::Comparison => Color32::WHITE,
         }
     }
 
@@ -89,6 +123,11 @@ pub enum Mode {
     Edit,
     Classical,
     Quantum,
+    ContinuousClassical,
+    ContinuousQuantum,
+    Szegedy,
+    /// A classical and a quantum walk run side by side.
+    Comparison,
 }
 
 impl Mode {
@@ -97,6 +136,10 @@ impl Mode {
             Mode::Edit => "Edit",
             Mode::Classical => "Classical",
             Mode::Quantum => "Quantum",
+            Mode::ContinuousClassical => "Continuous Classical",
+            Mode::ContinuousQuantum => "Continuous Quantum",
+            Mode::Szegedy => "Szegedy",
+            Mode::Comparison => "Classical vs Quantum",
         }
     }
 
@@ -105,15 +148,49 @@ impl Mode {
             Mode::Edit => "Editing Options",
             Mode::Classical => "Simulation Options (Classical)",
             Mode::Quantum => "Simulation Options (Quantum)",
+            Mode::ContinuousClassical => "Simulation Options (Continuous Classical)",
+            Mode::ContinuousQuantum => "Simulation Options (Continuous Quantum)",
+            Mode::Szegedy => "Simulation Options (Szegedy)",
+            Mode::Comparison => "Simulation Options (Comparison)",
         }
     }
 }
 
+/// Which distribution of a quantum walk is drawn on the canvas.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Display,
+    VariantArray,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum DistributionView {
+    /// The probabilities at the current step.
+    #[default]
+    Instantaneous,
+    /// The average of the probabilities over every step so far.
+    #[strum(to_string = "Running Average")]
+    RunningAverage,
+    /// The exact average over infinitely many steps.
+    #[strum(to_string = "Infinite Time Limit")]
+    Limit,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct ModeOptions {
     pub edit: EditOptions,
     pub classical: ClassicalOptions,
     pub quantum: QuantumOptions,
+    pub continuous_classical: ContinuousClassicalOptions,
+    pub continuous_quantum: ContinuousQuantumOptions,
+    pub szegedy: SzegedyOptions,
+    pub comparison: ComparisonOptions,
 }
 
 trait ModeOptionsShow {
@@ -168,10 +245,47 @@ impl ModeOptionsShow for EditOptions {
 }
 
 #[derive(Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
-pub struct ClassicalOptions {}
+#[serde(default)]
+pub struct ClassicalOptions {
+    target_node_text: String,
+    pub target_node_indexes: HashSet<usize>,
+    pub show_stationary_overlay: bool,
+}
 
 impl ModeOptionsShow for ClassicalOptions {
-    fn show_options(&mut self, _ui: &mut Ui) {}
+    fn show_options(&mut self, ui: &mut Ui) {
+        ui.label("Absorbing Target Node Indexes (space separated)");
+        if ui
+            .text_edit_singleline(&mut self.target_node_text)
+            .lost_focus()
+        {
+            self.target_node_indexes = parse_node_indexes(&self.target_node_text);
+        }
+        ui.checkbox(
+            &mut self.show_stationary_overlay,
+            "Overlay stationary distribution",
+        );
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
+pub struct ContinuousClassicalOptions {
+    #[serde(default)]
+    target_node_text: String,
+    #[serde(default)]
+    pub target_node_indexes: HashSet<usize>,
+}
+
+impl ModeOptionsShow for ContinuousClassicalOptions {
+    fn show_options(&mut self, ui: &mut Ui) {
+        ui.label("Absorbing Target Node Indexes (space separated)");
+        if ui
+            .text_edit_singleline(&mut self.target_node_text)
+            .lost_focus()
+        {
+            self.target_node_indexes = parse_node_indexes(&self.target_node_text);
+        }
+    }
 }
 
 #[derive(Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
@@ -182,11 +296,7 @@ pub struct QuantumOptions {
 
 impl QuantumOptions {
     fn update_target_node_indexes(&mut self) {
-        self.target_node_indexes = self
-            .target_node_text
-            .split_whitespace()
-            .filter_map(|x| x.parse::<usize>().ok())
-            .collect();
+        self.target_node_indexes = parse_node_indexes(&self.target_node_text);
     }
 }
 
@@ -202,11 +312,78 @@ impl ModeOptionsShow for QuantumOptions {
     }
 }
 
+#[derive(Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct ContinuousQuantumOptions {
+    target_node_text: String,
+    pub target_node_indexes: HashSet<usize>,
+}
+
+impl ModeOptionsShow for ContinuousQuantumOptions {
+    fn show_options(&mut self, ui: &mut Ui) {
+        ui.label("Target Node Indexes (space separated)");
+        if ui
+            .text_edit_singleline(&mut self.target_node_text)
+            .lost_focus()
+        {
+            self.target_node_indexes = parse_node_indexes(&self.target_node_text);
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct SzegedyOptions {
+    target_node_text: String,
+    pub target_node_indexes: HashSet<usize>,
+}
+
+impl ModeOptionsShow for SzegedyOptions {
+    fn show_options(&mut self, ui: &mut Ui) {
+        ui.label("Target Node Indexes (space separated)");
+        if ui
+            .text_edit_singleline(&mut self.target_node_text)
+            .lost_focus()
+        {
+            self.target_node_indexes = parse_node_indexes(&self.target_node_text);
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct ComparisonOptions {
+    target_node_text: String,
+    pub target_node_indexes: HashSet<usize>,
+}
+
+impl ModeOptionsShow for ComparisonOptions {
+    fn show_options(&mut self, ui: &mut Ui) {
+        ui.label("Target Node Indexes (space separated)");
+        if ui
+            .text_edit_singleline(&mut self.target_node_text)
+            .lost_focus()
+        {
+            self.target_node_indexes = parse_node_indexes(&self.target_node_text);
+        }
+    }
+}
+
+/// Reads whitespace separated node indexes, ignoring anything that is not a
+/// valid index.
+fn parse_node_indexes(text: &str) -> HashSet<usize> {
+    text.split_whitespace()
+        .filter_map(|x| x.parse::<usize>().ok())
+        .collect()
+}
+
 #[derive(Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
 pub struct GenericComputationOptions {
     pub start_node_idx: usize,
     pub start_node_idx_text_field: String,
     pub previous_start_node_idx_text_field: String,
+    #[serde(default)]
+    pub distribution_view: DistributionView,
 }
 
 impl Default for GenericComputationOptions {
@@ -215,6 +392,48 @@ impl Default for GenericComputationOptions {
             start_node_idx: Default::default(),
             start_node_idx_text_field: String::from("0"),
             previous_start_node_idx_text_field: String::from("0"),
+            distribution_view: Default::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_options_saved_before_mode_specific_targets() {
+        let json = r#"{
+            "mode": "Classical",
+            "mode_change_data": null,
+            "specific": {
+                "edit": { "snap": "None" },
+                "classical": {},
+                "quantum": { "target_node_text": "2", "target_node_indexes": [2] }
+            },
+            "generic": {
+                "start_node_idx": 1,
+                "start_node_idx_text_field": "1",
+                "previous_start_node_idx_text_field": "1"
+            }
+        }"#;
+        let options = serde_json::from_str::<Options>(json).unwrap();
+        assert_eq!(options.mode, Mode::Classical);
+        assert_eq!(options.specific.classical, ClassicalOptions::default());
+        assert_eq!(
+            options.specific.quantum.target_node_indexes,
+            HashSet::from([2])
+        );
+        assert_eq!(options.generic.start_node_idx, 1);
+
+        // a mode saved with only some of its fields keeps the rest at default
+        let json = r#"{ "szegedy": { "target_node_text": "0" }, "comparison": {} }"#;
+        let specific = serde_json::from_str::<ModeOptions>(json).unwrap();
+        assert!(specific.szegedy.target_node_indexes.is_empty());
+        assert_eq!(specific.comparison, ComparisonOptions::default());
+        assert_eq!(
+            specific.continuous_quantum,
+            ContinuousQuantumOptions::default()
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use egui::Ui;
 
+use crate::canvas_actions::CanvasActions;
+
 // TODO implement enum to string and enum iter crate
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -9,6 +11,7 @@ pub enum Tool {
     Node,
     Line,
     Label,
+    Weight,
 }
 
 impl Tool {
@@ -18,18 +21,33 @@ impl Tool {
             Tool::Node => "Node",
             Tool::Line => "Line",
             Tool::Label => "Label",
+            Tool::Weight => "Weight",
         }
     }
 
-    pub fn show(&self, ui: &mut Ui, selected_tool: &mut Tool, label_text: &mut String) {
+    pub fn show(&self, ui: &mut Ui, selected_tool: &mut Tool, canvas_actions: &mut CanvasActions) {
         let mut btn = ui.button(self.name().to_string());
         if selected_tool == self {
             btn = btn.highlight();
-            if *self == Tool::Label {
-                ui.group(|ui| {
-                    ui.label("Label text:");
-                    ui.text_edit_singleline(label_text);
-                });
+            match self {
+                Tool::Line => {
+                    ui.group(|ui| {
+                        ui.checkbox(&mut canvas_actions.add_directed_lines, "Directed");
+                    });
+                }
+                Tool::Label => {
+                    ui.group(|ui| {
+                        ui.label("Label text:");
+                        ui.text_edit_singleline(&mut canvas_actions.add_label_text);
+                    });
+                }
+                Tool::Weight => {
+                    ui.group(|ui| {
+                        ui.label("Weight:");
+                        ui.text_edit_singleline(&mut canvas_actions.set_weight_text);
+                    });
+                }
+                _ => (),
             }
         }
 